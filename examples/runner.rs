@@ -1,11 +1,9 @@
-#![feature(try_trait)]
-
 use dns_query_parser::dns_query::dns_query_struct::DnsRequestQuery;
 use std::convert::TryFrom;
 use std::borrow::Cow;
-use std::num::TryFromIntError;
+use dns_query_parser::dns_query::dns_query_error::DnsQueryWriteError;
 
-fn main() -> Result<(), TryFromIntError> {
+fn main() -> Result<(), DnsQueryWriteError> {
   // let respond = &[b'0', b'0', b'0', b'0', b'8', b'1', b'8', b'0', b'0', b'0',
   //   b'0', b'1', b'0', b'0', b'0', b'1', b'0', b'0', b'0', b'0', b'0', b'0', b'0',
   //   b'0', b'0', b'3', b'7', b'7', b'7', b'7', b'7', b'7', b'0', b'7', b'6', b'5',
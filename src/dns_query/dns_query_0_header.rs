@@ -1,11 +1,10 @@
 #![allow(dead_code)]
 
 use DnsQueryHeaderFlagsQr::{Query, Response};
-use std::mem::transmute;
 use std::convert::{TryFrom, TryInto};
-use std::option::NoneError;
 use std::slice::Iter;
-use crate::dns_query::utils::iter_to_u16_be;
+use crate::dns_query::utils::{iter_to_u8, iter_to_u16_be, dns_code_enum};
+use crate::dns_query::dns_query_error::DnsQueryParseError;
 
 /*
 Header format
@@ -36,21 +35,21 @@ pub struct DnsQueryHeader {
   pub ar_count: u16,
 }
 
-impl TryFrom<&mut Iter<'_, u8>> for DnsQueryHeader {
-  type Error = NoneError;
+impl TryFrom<(&[u8], &mut Iter<'_, u8>)> for DnsQueryHeader {
+  type Error = DnsQueryParseError;
 
-  fn try_from(iter: &mut Iter<'_, u8>) -> Result<Self, Self::Error> {
-    let id = iter_to_u16_be(iter)?;
+  fn try_from((msg, iter): (&[u8], &mut Iter<'_, u8>)) -> Result<Self, Self::Error> {
+    let id = iter_to_u16_be(msg, iter)?;
 
-    let flags = iter.try_into()?;
+    let flags = (msg, &mut *iter).try_into()?;
 
-    let qd_count = iter_to_u16_be(iter)?;
+    let qd_count = iter_to_u16_be(msg, iter)?;
 
-    let an_count = iter_to_u16_be(iter)?;
+    let an_count = iter_to_u16_be(msg, iter)?;
 
-    let ns_count = iter_to_u16_be(iter)?;
+    let ns_count = iter_to_u16_be(msg, iter)?;
 
-    let ar_count = iter_to_u16_be(iter)?;
+    let ar_count = iter_to_u16_be(msg, iter)?;
 
     Ok(Self { id, flags, qd_count, an_count, ns_count, ar_count })
   }
@@ -97,81 +96,81 @@ pub struct DnsQueryHeaderFlags {
   pub r_code: DnsQueryHeaderFlagsRcode,
 }
 
-impl TryFrom<&mut Iter<'_, u8>> for DnsQueryHeaderFlags {
-  type Error = NoneError;
+impl TryFrom<(&[u8], &mut Iter<'_, u8>)> for DnsQueryHeaderFlags {
+  type Error = DnsQueryParseError;
 
-  fn try_from(iter: &mut Iter<'_, u8>) -> Result<Self, Self::Error> {
-    let byte_1 = iter.next()?;
-    let byte_2 = iter.next()?;
+  fn try_from((msg, iter): (&[u8], &mut Iter<'_, u8>)) -> Result<Self, Self::Error> {
+    let byte_1 = iter_to_u8(msg, iter)?;
+    let byte_2 = iter_to_u8(msg, iter)?;
 
     /* qr */
     let qr = {
-      let val = (byte_1 >> 7) as u8;
+      let val = byte_1 >> 7;
       debug_assert!(val <= 0b1);
       if val == 0 { Query } else { Response }
     };
 
     /* op_code */
     let op_code = {
-      let val = ((byte_1 >> 3) & 0b1111) as u8;
+      let val = (byte_1 >> 3) & 0b1111;
       debug_assert!(val <= 0b1111);
-      unsafe { transmute::<u8, DnsQueryHeaderFlagsOpcode>(val) }
+      DnsQueryHeaderFlagsOpcode::from(val)
     };
 
     /* aa */
     let aa = {
-      let val = ((byte_1 >> 2) & 0b1) as u8;
+      let val = (byte_1 >> 2) & 0b1;
       debug_assert!(val <= 0b1);
-      unsafe { transmute::<u8, DnsQueryHeaderFlagsAa>(val) }
+      if val == 0 { DnsQueryHeaderFlagsAa::NonAuthAns } else { DnsQueryHeaderFlagsAa::AuthAns }
     };
 
     /* tc */
     let tc = {
-      let val = ((byte_1 >> 1) & 0b1) as u8;
+      let val = (byte_1 >> 1) & 0b1;
       debug_assert!(val <= 0b1);
-      unsafe { transmute::<u8, DnsQueryHeaderFlagsTc>(val) }
+      if val == 0 { DnsQueryHeaderFlagsTc::NonTrunc } else { DnsQueryHeaderFlagsTc::Trunc }
     };
 
     /* rd */
     let rd = {
-      let val = (byte_1 & 0b1) as u8;
+      let val = byte_1 & 0b1;
       debug_assert!(val <= 0b1);
-      unsafe { transmute::<u8, DnsQueryHeaderFlagsRd>(val) }
+      if val == 0 { DnsQueryHeaderFlagsRd::NotRecur } else { DnsQueryHeaderFlagsRd::Recur }
     };
 
     /* ra */
     let ra = {
-      let val = ((byte_2 >> 7) & 0b1) as u8;
+      let val = (byte_2 >> 7) & 0b1;
       debug_assert!(val <= 0b1);
-      unsafe { transmute::<u8, DnsQueryHeaderFlagsRa>(val) }
+      if val == 0 { DnsQueryHeaderFlagsRa::NotAvailable } else { DnsQueryHeaderFlagsRa::Available }
     };
 
     /* z */
     let z = {
-      let val = ((byte_2 >> 6) & 0b1) as u8;
+      let val = (byte_2 >> 6) & 0b1;
       debug_assert!(val <= 0b1);
       val
     };
 
     /* ad */
     let ad = {
-      let val = ((byte_2 >> 5) & 0b1) as u8;
+      let val = (byte_2 >> 5) & 0b1;
       debug_assert!(val <= 0b1);
-      unsafe { transmute::<u8, DnsQueryHeaderFlagsAd>(val) }
+      if val == 0 { DnsQueryHeaderFlagsAd::NotAuthed } else { DnsQueryHeaderFlagsAd::Authed }
     };
 
     /* cd */
     let cd = {
-      let val = ((byte_2 >> 4) & 0b1) as u8;
+      let val = (byte_2 >> 4) & 0b1;
       debug_assert!(val <= 0b1);
-      unsafe { transmute::<u8, DnsQueryHeaderFlagsCd>(val) }
+      if val == 0 { DnsQueryHeaderFlagsCd::Checked } else { DnsQueryHeaderFlagsCd::NotChecked }
     };
 
     /* r_code */
     let r_code = {
-      let val = (byte_2 & 0b1111) as u8;
+      let val = byte_2 & 0b1111;
       debug_assert!(val <= 0b1111);
-      unsafe { transmute::<u8, DnsQueryHeaderFlagsRcode>(val) }
+      DnsQueryHeaderFlagsRcode::from(val)
     };
 
     Ok(Self {
@@ -202,7 +201,7 @@ impl From<&DnsQueryHeaderFlags> for [u8; 2] {
     }
 
     /* op_code */ {
-      let val = flags.op_code as u8;
+      let val = u8::from(&flags.op_code);
       debug_assert!(val <= 0b1111);
       byte_1 |= val << 3;
     }
@@ -232,7 +231,7 @@ impl From<&DnsQueryHeaderFlags> for [u8; 2] {
     }
 
     /* z */ {
-      let val = flags.z as u8;
+      let val = flags.z;
       debug_assert!(val <= 0b1);
       byte_2 |= val << 6;
     }
@@ -250,7 +249,7 @@ impl From<&DnsQueryHeaderFlags> for [u8; 2] {
     }
 
     /* r_code */ {
-      let val = flags.r_code as u8;
+      let val = u8::from(&flags.r_code);
       debug_assert!(val <= 0b1111);
       byte_2 |= val;
     }
@@ -267,24 +266,23 @@ pub enum DnsQueryHeaderFlagsQr {
   Response = 1,
 }
 
-#[derive(Debug, Copy, Clone)]
-pub enum DnsQueryHeaderFlagsOpcode {
+dns_code_enum! {
+pub enum DnsQueryHeaderFlagsOpcode: u8 {
   /// 0: a standard query (QUERY)
   StdQuery = 0,
   /// 1: an inverse query (IQUERY)
   InvQuery = 1,
   /// 2: a server status request (STATUS)
   StatReq = 2,
-  /// 3: unassigned
-  _UnAssign3 = 3,
+  // /// 3: unassigned
   /// 4: notify
   Notify = 4,
   /// 5: update
   Upd = 5,
   /// 6: DNS Stateful Operations (DSO)
   Dso = 6,
-  /// 7-15: reserved for future use
-  _Resv7To15 = 15,  // use largest possible for correct `std::mem::transmute()` parsing
+  // /// 7-15: reserved for future use
+}
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -335,8 +333,8 @@ pub enum DnsQueryHeaderFlagsCd {
   NotChecked = 1,
 }
 
-#[derive(Debug, Copy, Clone)]
-pub enum DnsQueryHeaderFlagsRcode {
+dns_code_enum! {
+pub enum DnsQueryHeaderFlagsRcode: u8 {
   /// 0: No error condition
   NoErr = 0,
   /// 1: Format error - The name server was unable to interpret the query.
@@ -368,6 +366,6 @@ pub enum DnsQueryHeaderFlagsRcode {
   /// 10: A name used in the Prerequisite or Update sections is not within the
   ///     z//one specified by the Zone section.
   NameNotInZone = 10,
-  /// 11-15: Reserved for future use.
-  _Resv11To15 = 15,  // use largest possible for correct `std::mem::transmute()` parsing
+  // /// 11-15: Reserved for future use.
+}
 }
\ No newline at end of file
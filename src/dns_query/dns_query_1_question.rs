@@ -1,10 +1,9 @@
 #![allow(dead_code)]
 
 use crate::dns_query::utils::{iter_to_str, str_to_vec, iter_to_u16_be,
-                              DnsQueryType, DnsQueryClass};
+                              NameOffsets, DnsQueryType, DnsQueryClass};
 use std::convert::TryFrom;
-use std::num::TryFromIntError;
-use std::option::NoneError;
+use crate::dns_query::dns_query_error::{DnsQueryParseError, DnsQueryWriteError};
 use std::slice::Iter;
 use std::borrow::Cow;
 
@@ -30,48 +29,56 @@ pub struct DnsQueryQuestion<'a> {
   pub q_class: DnsQueryClass,
 }
 
-impl<'a> TryFrom<&mut Iter<'_, u8>> for DnsQueryQuestion<'a> {
-  type Error = NoneError;
+impl<'a> TryFrom<(&[u8], &mut Iter<'_, u8>)> for DnsQueryQuestion<'a> {
+  type Error = DnsQueryParseError;
 
-  fn try_from(iter: &mut Iter<'_, u8>) -> Result<Self, Self::Error> {
+  fn try_from((msg, iter): (&[u8], &mut Iter<'_, u8>)) -> Result<Self, Self::Error> {
     /* Parse q_name */
     let q_name = {
       let mut val = String::new();
-      iter_to_str(iter, &mut val);
+      iter_to_str(msg, iter, &mut val)?;
       Cow::from(val)
     };
 
     /* Parse q_type  */
-    let q_type = iter_to_u16_be(iter)?.into();
+    let q_type = iter_to_u16_be(msg, iter)?.into();
 
     /* Parse q_class */
-    let q_class = iter_to_u16_be(iter)?.into();
+    let q_class = iter_to_u16_be(msg, iter)?.into();
 
     Ok(Self { q_name, q_type, q_class })
   }
 }
 
-impl<'a> TryFrom<&DnsQueryQuestion<'a>> for Vec<u8> {
-  type Error = TryFromIntError;
-
-  fn try_from(question: &DnsQueryQuestion) -> Result<Self, Self::Error> {
-    let mut result = vec![];
-
+impl<'a> DnsQueryQuestion<'a> {
+  /// Append this question to `vec`, which holds the message written so far.
+  pub(crate) fn write_to(&self, vec: &mut Vec<u8>, offsets: &mut NameOffsets
+  ) -> Result<(), DnsQueryWriteError> {
     /* Parse q_name */ {
-      str_to_vec(&question.q_name, &mut result)?;
+      str_to_vec(&self.q_name, vec, offsets)?;
     }
 
     /* Parse q_type */ {
-      let q_type: u16 = (&question.q_type).into();
-      result.extend_from_slice(&q_type.to_be_bytes());
+      let q_type: u16 = (&self.q_type).into();
+      vec.extend_from_slice(&q_type.to_be_bytes());
     }
 
     /* Parse q_class */ {
-      let q_class: u16 = (&question.q_class).into();
-      result.extend_from_slice(&q_class.to_be_bytes());
+      let q_class: u16 = (&self.q_class).into();
+      vec.extend_from_slice(&q_class.to_be_bytes());
     }
 
+    Ok(())
+  }
+}
+
+impl<'a> TryFrom<&DnsQueryQuestion<'a>> for Vec<u8> {
+  type Error = DnsQueryWriteError;
+
+  fn try_from(question: &DnsQueryQuestion) -> Result<Self, Self::Error> {
+    let mut result = vec![];
+    question.write_to(&mut result, &mut None)?;
     result.shrink_to_fit();
     Ok(result)
   }
-}
\ No newline at end of file
+}
@@ -1,12 +1,13 @@
 #![allow(dead_code)]
 
-use crate::dns_query::utils::{iter_to_str, iter_to_u16_be, iter_to_u32_be,
-                              DnsQueryClass, DnsQueryType};
-use crate::dns_query::utils::DnsQueryType::{A, CName};
-use crate::dns_query::utils::DnsQueryClass::In;
-use std::net::Ipv4Addr;
+use crate::dns_query::utils::{iter_to_str, iter_to_slice,
+                              iter_to_u16_be, iter_to_u32_be, str_to_vec,
+                              NameOffsets, DnsQueryClass, DnsQueryType};
+use crate::dns_query::dns_query_2_resource_record_rdata::DnsQueryResourceRecordRDataType;
 use std::convert::TryFrom;
-use std::option::NoneError;
+use crate::dns_query::dns_query_error::{DnsQueryParseError,
+                                       DnsQueryParseErrorKind::{Truncated, BadRdLength},
+                                       DnsQueryWriteError};
 use std::slice::Iter;
 
 /*
@@ -34,71 +35,165 @@ Answer/Authority/Additional format
 /                                               /
 +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
 */
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DnsQueryResourceRecord {
-  name: String,
-  type_: DnsQueryType,
-  class: DnsQueryClass,
-  ttl: u32,
-  rd_length: u16,
-  r_data: DnsQueryResourceRecordRDataType,
+  pub name: String,
+  pub type_: DnsQueryType,
+  pub class: DnsQueryClass,
+  pub ttl: u32,
+  /// Length of `r_data` as parsed, ignored when serializing
+  pub rd_length: u16,
+  pub r_data: DnsQueryResourceRecordRDataType,
 }
 
-#[derive(Debug)]
-pub enum DnsQueryResourceRecordRDataType {
-  Ipv4Addr(Ipv4Addr),
-  String(String),
-  _Other(Vec<u8>),
-}
-
-impl TryFrom<&mut Iter<'_, u8>> for DnsQueryResourceRecord {
-  type Error = NoneError;
+impl TryFrom<(&[u8], &mut Iter<'_, u8>)> for DnsQueryResourceRecord {
+  type Error = DnsQueryParseError;
 
-  fn try_from(iter: &mut Iter<'_, u8>) -> Result<Self, Self::Error> {
+  fn try_from((msg, iter): (&[u8], &mut Iter<'_, u8>)) -> Result<Self, Self::Error> {
     /* Parse name */
     let name = {
       let mut name = String::new();
-      iter_to_str(iter, &mut name);
+      iter_to_str(msg, iter, &mut name)?;
       name
     };
 
     /* Parse type_ */
-    let type_ = iter_to_u16_be(iter)?.into();
+    let type_ = iter_to_u16_be(msg, iter)?.into();
 
     /* Parse class */
-    let class = iter_to_u16_be(iter)?.into();
+    let class = iter_to_u16_be(msg, iter)?.into();
 
     /* Parse ttl */
-    let ttl = iter_to_u32_be(iter)?;
+    let ttl = iter_to_u32_be(msg, iter)?;
 
     /* Parse rd_length */
-    let rd_length = iter_to_u16_be(iter)?;
-
-    /* Parse r_data */
-    let r_data;
-    {
-      if let In = class {
-        match type_ {
-          A => {
-            r_data = DnsQueryResourceRecordRDataType::Ipv4Addr(Ipv4Addr::new(
-              *iter.next()?, *iter.next()?,
-              *iter.next()?, *iter.next()?,
-            ));
-          }
-          CName => {
-            let mut s = String::new();
-            iter_to_str(iter, &mut s);
-            r_data = DnsQueryResourceRecordRDataType::String(s);
-          }
-          _ => {
-            r_data = DnsQueryResourceRecordRDataType::_Other(iter.as_slice().to_vec());
-          }
-        }
-      } else {
-        r_data = DnsQueryResourceRecordRDataType::_Other(iter.as_slice().to_vec());
+    let rd_length = iter_to_u16_be(msg, iter)?;
+
+    /* Parse r_data, which must take up exactly rd_length bytes */
+    let r_data = {
+      let start = msg.len() - iter.as_slice().len();
+      iter_to_slice(msg, iter, rd_length as usize)?;
+
+      /* Cut `msg` at the end of r_data, so nothing can be read beyond it */
+      let msg = &msg[..start + rd_length as usize];
+      let mut iter = msg[start..].iter();
+
+      let r_data = DnsQueryResourceRecordRDataType::parse(msg, &mut iter, type_, class)
+        .map_err(|err| match err.kind {
+          Truncated => DnsQueryParseError { kind: BadRdLength, ..err },
+          _ => err,
+        })?;
+
+      if iter.len() != 0 {
+        return Err(DnsQueryParseError::at(msg, &iter, BadRdLength));
       }
-    }
+      r_data
+    };
 
     Ok(Self { name, type_, class, ttl, rd_length, r_data })
   }
-}
\ No newline at end of file
+}
+
+impl DnsQueryResourceRecord {
+  /// Append this record to `vec`, which holds the message written so far.
+  ///
+  /// `rd_length` is computed from the encoded `r_data`.
+  pub(crate) fn write_to(&self, vec: &mut Vec<u8>, offsets: &mut NameOffsets
+  ) -> Result<(), DnsQueryWriteError> {
+    /* Write name */ {
+      str_to_vec(&self.name, vec, offsets)?;
+    }
+
+    /* Write type_ */ {
+      let type_: u16 = (&self.type_).into();
+      vec.extend_from_slice(&type_.to_be_bytes());
+    }
+
+    /* Write class */ {
+      let class: u16 = (&self.class).into();
+      vec.extend_from_slice(&class.to_be_bytes());
+    }
+
+    /* Write ttl */ {
+      vec.extend_from_slice(&self.ttl.to_be_bytes());
+    }
+
+    /* Write rd_length, filled in after r_data */
+    let rd_length_pos = vec.len();
+    vec.extend_from_slice(&[0, 0]);
+
+    /* Write r_data */ {
+      self.r_data.write_to(vec, offsets)?;
+    }
+
+    /* Fill in rd_length */ {
+      let rd_length = u16::try_from(vec.len() - rd_length_pos - 2)?;
+      vec[rd_length_pos..rd_length_pos + 2].copy_from_slice(&rd_length.to_be_bytes());
+    }
+
+    Ok(())
+  }
+}
+
+impl TryFrom<&DnsQueryResourceRecord> for Vec<u8> {
+  type Error = DnsQueryWriteError;
+
+  fn try_from(record: &DnsQueryResourceRecord) -> Result<Self, Self::Error> {
+    let mut result = vec![];
+    record.write_to(&mut result, &mut None)?;
+    result.shrink_to_fit();
+    Ok(result)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use data_encoding::HEXLOWER;
+
+  /// `example.com` at offset 0, then the record `record` at offset 13.
+  fn message(record: &str) -> Vec<u8> {
+    HEXLOWER.decode(format!("076578616d706c6503636f6d00{}", record).as_bytes()).unwrap()
+  }
+
+  fn parse(msg: &[u8]) -> Result<(DnsQueryResourceRecord, usize), DnsQueryParseError> {
+    let mut iter = msg[13..].iter();
+    let record = DnsQueryResourceRecord::try_from((msg, &mut iter))?;
+    Ok((record, iter.len()))
+  }
+
+  #[test]
+  fn rd_length_mismatch() {
+    /* A record with 3 and 5 bytes of r_data */
+    let short = message("c000000100010000012c0003c00002");
+    assert_eq!(parse(&short).err(),
+               Some(DnsQueryParseError { offset: 25, kind: BadRdLength }));
+    let long = message("c000000100010000012c0005c000020100");
+    assert_eq!(parse(&long).err(),
+               Some(DnsQueryParseError { offset: 29, kind: BadRdLength }));
+
+    /* rd_length past the end of the message */
+    let truncated = message("c000000100010000012c0004c00002");
+    assert_eq!(parse(&truncated).err(),
+               Some(DnsQueryParseError { offset: 25, kind: Truncated }));
+  }
+
+  #[test]
+  fn r_data_ends_at_rd_length() {
+    /* Unknown type, followed by one more byte of the message */
+    let msg = message("c000ff0000010000012c0002abcd00");
+    let (record, left) = parse(&msg).unwrap();
+    assert_eq!(record.r_data.to_string(), "\\# 2 ABCD");
+    assert_eq!(left, 1);
+  }
+
+  #[test]
+  fn pointer_in_r_data() {
+    /* CNAME www.example.com, pointing back before the record */
+    let msg = message("c000000500010000012c000603777777c000");
+    let (record, left) = parse(&msg).unwrap();
+    assert_eq!(record.rd_length, 6);
+    assert_eq!(record.r_data.to_string(), "www.example.com.");
+    assert_eq!(left, 0);
+  }
+}
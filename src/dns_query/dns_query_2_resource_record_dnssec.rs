@@ -0,0 +1,224 @@
+#![allow(dead_code)]
+
+use crate::dns_query::utils::{iter_to_slice, iter_to_u8, dns_code_enum, DnsQueryType};
+use crate::dns_query::dns_query_error::{DnsQueryParseError,
+                                       DnsQueryParseErrorKind::BadRData};
+use std::slice::Iter;
+
+/*
+ *  Reference:
+ *  - [RFC 4034](https://tools.ietf.org/html/rfc4034)
+ *  - [RFC 5155](https://tools.ietf.org/html/rfc5155)
+ *  - [RFC 8624](https://tools.ietf.org/html/rfc8624)
+ */
+
+dns_code_enum! {
+pub enum DnsQueryDnsSecAlgorithm: u8 {
+  /// 1: RSA/MD5 (deprecated)
+  RsaMd5 = 1,
+  /// 2: Diffie-Hellman
+  Dh = 2,
+  /// 3: DSA/SHA-1
+  DsaSha1 = 3,
+  /// 5: RSA/SHA-1
+  RsaSha1 = 5,
+  /// 6: DSA-NSEC3-SHA1
+  DsaNSec3Sha1 = 6,
+  /// 7: RSASHA1-NSEC3-SHA1
+  RsaSha1NSec3Sha1 = 7,
+  /// 8: RSA/SHA-256
+  RsaSha256 = 8,
+  /// 10: RSA/SHA-512
+  RsaSha512 = 10,
+  /// 12: GOST R 34.10-2001
+  EccGost = 12,
+  /// 13: ECDSA Curve P-256 with SHA-256
+  EcdsaP256Sha256 = 13,
+  /// 14: ECDSA Curve P-384 with SHA-384
+  EcdsaP384Sha384 = 14,
+  /// 15: Ed25519
+  Ed25519 = 15,
+  /// 16: Ed448
+  Ed448 = 16,
+  /// 253: Private algorithm
+  PrivateDns = 253,
+  /// 254: Private algorithm OID
+  PrivateOid = 254,
+}
+}
+
+dns_code_enum! {
+pub enum DnsQueryDsDigestType: u8 {
+  /// 1: SHA-1
+  Sha1 = 1,
+  /// 2: SHA-256
+  Sha256 = 2,
+  /// 3: GOST R 34.11-94
+  Gost = 3,
+  /// 4: SHA-384
+  Sha384 = 4,
+}
+}
+
+/// DNSKEY flag: the key is a zone key
+pub const DNSKEY_FLAG_ZONE: u16 = 0b0000_0001_0000_0000;
+/// DNSKEY flag: the key has been revoked (RFC 5011)
+pub const DNSKEY_FLAG_REVOKE: u16 = 0b0000_0000_1000_0000;
+/// DNSKEY flag: Secure Entry Point, usually set on key signing keys
+pub const DNSKEY_FLAG_SEP: u16 = 0b0000_0000_0000_0001;
+
+/// NSEC3 flag: the span may cover unsigned delegations
+pub const NSEC3_FLAG_OPT_OUT: u8 = 0b0000_0001;
+
+/// Key tag of a DNSKEY, see RFC 4034 Appendix B.
+pub fn dnskey_key_tag(flags: u16, protocol: u8, algorithm: DnsQueryDnsSecAlgorithm,
+                      public_key: &[u8]) -> u16 {
+  /* Algorithm 1 uses the most significant 16 bits of the modulus */ {
+    if algorithm == DnsQueryDnsSecAlgorithm::RsaMd5 {
+      let len = public_key.len();
+      return if len >= 3 {
+        u16::from_be_bytes([public_key[len - 3], public_key[len - 2]])
+      } else {
+        0
+      };
+    }
+  }
+
+  let mut r_data = Vec::with_capacity(4 + public_key.len());
+  r_data.extend_from_slice(&flags.to_be_bytes());
+  r_data.push(protocol);
+  r_data.push(u8::from(&algorithm));
+  r_data.extend_from_slice(public_key);
+
+  let mut acc: u32 = 0;
+  for (i, byte) in r_data.iter().enumerate() {
+    acc += if i % 2 == 0 { u32::from(*byte) << 8 } else { u32::from(*byte) };
+  }
+  acc += (acc >> 16) & 0xFFFF;
+  #[allow(clippy::cast_possible_truncation)]
+  let tag = (acc & 0xFFFF) as u16;
+  tag
+}
+
+/*
+Type bit maps format, see RFC 4034 §4.1.2
+
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+|  window block number  |     bitmap length     |  -+ repeated
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+   | with increasing
+/                    bitmap                     /   | window block number
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+  -+
+*/
+
+/// Parse type bit maps from all of `iter`.
+pub(crate) fn iter_to_type_bit_maps(msg: &[u8], iter: &mut Iter<u8>
+) -> Result<Vec<DnsQueryType>, DnsQueryParseError> {
+  let mut types = vec![];
+  let mut last_window = None;
+
+  while iter.len() != 0 {
+    let err = DnsQueryParseError::at(msg, iter, BadRData);
+
+    let window = iter_to_u8(msg, iter)?;
+    let len = iter_to_u8(msg, iter)?;
+
+    if last_window.is_some_and(|last| last >= window) || len == 0 || len > 32 {
+      return Err(err);
+    }
+    last_window = Some(window);
+
+    for (i, byte) in iter_to_slice(msg, iter, len as usize)?.iter().enumerate() {
+      for bit in 0..8 {
+        if byte & (0b1000_0000 >> bit) != 0 {
+          #[allow(clippy::cast_possible_truncation)]
+          let type_ = u16::from_be_bytes([window, (i * 8 + bit) as u8]);
+          types.push(type_.into());
+        }
+      }
+    }
+  }
+  Ok(types)
+}
+
+/// Append `types` as type bit maps to `vec`.
+pub(crate) fn type_bit_maps_to_vec(types: &[DnsQueryType], vec: &mut Vec<u8>) {
+  let mut types: Vec<u16> = types.iter().map(u16::from).collect();
+  types.sort_unstable();
+  types.dedup();
+
+  let mut types = types.as_slice();
+  while let Some(first) = types.first() {
+    let window = first.to_be_bytes()[0];
+    let len = types.iter().take_while(|type_| type_.to_be_bytes()[0] == window).count();
+
+    let mut bitmap = [0_u8; 32];
+    let mut bitmap_len = 0;
+    for type_ in &types[..len] {
+      let low = type_.to_be_bytes()[1] as usize;
+      bitmap[low / 8] |= 0b1000_0000 >> (low % 8);
+      bitmap_len = low / 8 + 1;
+    }
+
+    vec.push(window);
+    #[allow(clippy::cast_possible_truncation)]
+    vec.push(bitmap_len as u8);
+    vec.extend_from_slice(&bitmap[..bitmap_len]);
+
+    types = &types[len..];
+  }
+}
+
+/* Presentation form of signature times, see RFC 4034 §3.2 */
+
+/// Format `time` as `YYYYMMDDHHmmSS` in UTC.
+pub(crate) fn fmt_dnssec_time(time: u32) -> String {
+  let days = i64::from(time / 86400);
+  let secs = time % 86400;
+
+  /* Civil from days, proleptic Gregorian calendar */
+  let z = days + 719_468;
+  let era = z.div_euclid(146_097);
+  let doe = z.rem_euclid(146_097);
+  let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = doy - (153 * mp + 2) / 5 + 1;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 };
+  let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+  format!("{:04}{:02}{:02}{:02}{:02}{:02}",
+          year, month, day, secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Parse `YYYYMMDDHHmmSS` in UTC, or a plain number of seconds since epoch.
+pub(crate) fn parse_dnssec_time(s: &str) -> Option<u32> {
+  if s.len() != 14 {
+    return s.parse().ok();
+  }
+  if !s.bytes().all(|b| b.is_ascii_digit()) {
+    return None;
+  }
+
+  let field = |range: std::ops::Range<usize>| s[range].parse::<i64>().ok();
+  let (year, month, day) = (field(0..4)?, field(4..6)?, field(6..8)?);
+  let (hour, minute, second) = (field(8..10)?, field(10..12)?, field(12..14)?);
+  if !(1..=12).contains(&month) || !(1..=31).contains(&day)
+    || hour > 23 || minute > 59 || second > 59 {
+    return None;
+  }
+
+  /* Days from civil, proleptic Gregorian calendar */
+  let y = if month <= 2 { year - 1 } else { year };
+  let era = y.div_euclid(400);
+  let yoe = y - era * 400;
+  let mp = if month > 2 { month - 3 } else { month + 9 };
+  let doy = (153 * mp + 2) / 5 + day - 1;
+  let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+  let days = era * 146_097 + doe - 719_468;
+
+  /* Times after 2106 wrap around, as they are compared in serial arithmetic */
+  let secs = days * 86400 + hour * 3600 + minute * 60 + second;
+  #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+  let secs = secs.rem_euclid(1 << 32) as u32;
+  Some(secs)
+}
@@ -0,0 +1,784 @@
+#![allow(dead_code)]
+
+use crate::dns_query::utils::{iter_to_slice, iter_to_u16_be, dns_code_enum,
+                              DnsQueryClass, DnsQueryType};
+use crate::dns_query::dns_query_0_header::DnsQueryHeaderFlagsRcode;
+use crate::dns_query::dns_query_2_resource_record::DnsQueryResourceRecord;
+use crate::dns_query::dns_query_2_resource_record_rdata::DnsQueryResourceRecordRDataType;
+use crate::dns_query::dns_query_error::{DnsQueryParseError,
+                                       DnsQueryParseErrorKind::BadEdnsOption,
+                                       DnsQueryWriteError};
+use data_encoding::HEXUPPER;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::slice::Iter;
+
+/*
+ *  Reference:
+ *  - [RFC 5001](https://tools.ietf.org/html/rfc5001)
+ *  - [RFC 6891](https://tools.ietf.org/html/rfc6891)
+ *  - [RFC 7871](https://tools.ietf.org/html/rfc7871)
+ *  - [RFC 7830](https://tools.ietf.org/html/rfc7830)
+ *  - [RFC 7873](https://tools.ietf.org/html/rfc7873)
+ *  - [RFC 8914](https://tools.ietf.org/html/rfc8914)
+ */
+
+/// UDP payload size advertised when none is given, see DNS Flag Day 2020.
+pub const DEFAULT_UDP_PAYLOAD_SIZE: u16 = 1232;
+
+/*
+OPT pseudo-record, in place of the usual class and ttl
+
+ 15 14 13 12 11 10  9  8  7  6  5  4  3  2  1  0
+  0  1  2  3  4  5  6  7  0  1  2  3  4  5  6  7
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+|               udp_payload_size                |  class
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+|    extended_r_code    |        version        |  ttl
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+|DO|                     z                      |
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+
+OPT r_data format
+
+ 15 14 13 12 11 10  9  8  7  6  5  4  3  2  1  0
+  0  1  2  3  4  5  6  7  0  1  2  3  4  5  6  7
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+|                     code                      |  -+
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+   |
+|                    length                     |   | options,
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+   | repeated
+/                     data                      /   |
+/                                               /  -+
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsQueryEdns {
+  /// Largest UDP payload the sender is able to reassemble
+  pub udp_payload_size: u16,
+  /// Upper 8 bits of the 12-bit RCODE, see [`Self::r_code`]
+  pub extended_r_code: u8,
+  pub version: u8,
+  /// DNSSEC OK, see RFC 3225
+  pub dnssec_ok: bool,
+  /// Only the lowest 15 bits are sent
+  pub z: u16,
+  pub options: Vec<DnsQueryEdnsOption>,
+}
+
+dns_code_enum! {
+pub enum DnsQueryEdnsOptionCode: u16 {
+  /// 1: Long-Lived Queries
+  Llq = 1,
+  /// 2: Update Lease
+  UpdateLease = 2,
+  /// 3: Name Server Identifier
+  Nsid = 3,
+  /// 5: DNSSEC Algorithm Understood
+  Dau = 5,
+  /// 6: DS Hash Understood
+  Dhu = 6,
+  /// 7: NSEC3 Hash Understood
+  N3u = 7,
+  /// 8: Client Subnet
+  ClientSubnet = 8,
+  /// 9: Zone Expire
+  Expire = 9,
+  /// 10: DNS Cookie
+  Cookie = 10,
+  /// 11: TCP Keepalive
+  TcpKeepalive = 11,
+  /// 12: Padding
+  Padding = 12,
+  /// 13: CHAIN query requests
+  Chain = 13,
+  /// 14: DNSSEC Key Tag
+  KeyTag = 14,
+  /// 15: Extended DNS Error
+  ExtendedError = 15,
+}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DnsQueryEdnsOption {
+  /// Name server identifier, empty in queries
+  Nsid(Vec<u8>),
+  ClientSubnet(DnsQueryClientSubnet),
+  /// Client cookie, and a server cookie of 8 to 32 bytes unless only the
+  /// client cookie is sent
+  Cookie {
+    client: [u8; 8],
+    server: Vec<u8>,
+  },
+  /// Length of the padding, which is sent as zeros
+  Padding(u16),
+  ExtendedError(DnsQueryExtendedError),
+  /// Data of a code without a variant of its own
+  Other(u16, Vec<u8>),
+}
+
+/*
+Client Subnet option data
+
+ 15 14 13 12 11 10  9  8  7  6  5  4  3  2  1  0
+  0  1  2  3  4  5  6  7  0  1  2  3  4  5  6  7
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+|                    family                     |
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+|  source_prefix_len    |   scope_prefix_len    |
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/                    address                    /
+/                                               /
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+*/
+/// Client Subnet, with `address` holding no bits beyond
+/// `source_prefix_len`, of which only the covering bytes are sent.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DnsQueryClientSubnet {
+  address: IpAddr,
+  source_prefix_len: u8,
+  scope_prefix_len: u8,
+}
+
+/*
+Extended DNS Error option data
+
+ 15 14 13 12 11 10  9  8  7  6  5  4  3  2  1  0
+  0  1  2  3  4  5  6  7  0  1  2  3  4  5  6  7
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+|                   info_code                   |
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/                  extra_text                   /
+/                                               /
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+*/
+/// Extended DNS Error, telling why a response has its RCODE.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsQueryExtendedError {
+  pub info_code: DnsQueryExtendedErrorCode,
+  /// UTF-8 text for humans, possibly empty
+  pub extra_text: String,
+}
+
+dns_code_enum! {
+pub enum DnsQueryExtendedErrorCode: u16 {
+  /// 0: Other Error
+  Other = 0,
+  /// 1: Unsupported DNSKEY Algorithm
+  UnsupportedDnsKeyAlgorithm = 1,
+  /// 2: Unsupported DS Digest Type
+  UnsupportedDsDigestType = 2,
+  /// 3: Stale Answer
+  StaleAnswer = 3,
+  /// 4: Forged Answer
+  ForgedAnswer = 4,
+  /// 5: DNSSEC Indeterminate
+  DnsSecIndeterminate = 5,
+  /// 6: DNSSEC Bogus
+  DnsSecBogus = 6,
+  /// 7: Signature Expired
+  SignatureExpired = 7,
+  /// 8: Signature Not Yet Valid
+  SignatureNotYetValid = 8,
+  /// 9: DNSKEY Missing
+  DnsKeyMissing = 9,
+  /// 10: RRSIGs Missing
+  RrSigsMissing = 10,
+  /// 11: No Zone Key Bit Set
+  NoZoneKeyBitSet = 11,
+  /// 12: NSEC Missing
+  NSecMissing = 12,
+  /// 13: Cached Error
+  CachedError = 13,
+  /// 14: Not Ready
+  NotReady = 14,
+  /// 15: Blocked
+  Blocked = 15,
+  /// 16: Censored
+  Censored = 16,
+  /// 17: Filtered
+  Filtered = 17,
+  /// 18: Prohibited
+  Prohibited = 18,
+  /// 19: Stale NXDomain Answer
+  StaleNxDomainAnswer = 19,
+  /// 20: Not Authoritative
+  NotAuthoritative = 20,
+  /// 21: Not Supported
+  NotSupported = 21,
+  /// 22: No Reachable Authority
+  NoReachableAuthority = 22,
+  /// 23: Network Error
+  NetworkError = 23,
+  /// 24: Invalid Data
+  InvalidData = 24,
+  /// 25: Signature Expired before Valid
+  SignatureExpiredBeforeValid = 25,
+  /// 26: Too Early
+  TooEarly = 26,
+  /// 27: Unsupported NSEC3 Iterations Value
+  UnsupportedNSec3Iterations = 27,
+  /// 28: Unable to conform to policy
+  UnableToConformToPolicy = 28,
+  /// 29: Synthesized
+  Synthesized = 29,
+  /// 30: Invalid Query Type
+  InvalidQueryType = 30,
+}
+}
+
+dns_code_enum! {
+/// Full 12-bit RCODE, made of the one in the header and the upper bits in
+/// the OPT record, see RFC 6891 §6.1.3.
+pub enum DnsQueryRcode: u16 {
+  /// 0: No Error
+  NoErr = 0,
+  /// 1: Format Error
+  FormatErr = 1,
+  /// 2: Server Failure
+  SvrFail = 2,
+  /// 3: Non-Existent Domain
+  NameErr = 3,
+  /// 4: Not Implemented
+  NotImpl = 4,
+  /// 5: Query Refused
+  Refused = 5,
+  /// 6: Name Exists when it should not
+  NameExist = 6,
+  /// 7: RR Set Exists when it should not
+  ResRecordExist = 7,
+  /// 8: RR Set that should exist does not
+  ResRecordNotExist = 8,
+  /// 9: Server Not Authoritative for zone
+  ZoneNotAuth = 9,
+  /// 10: Name not contained in zone
+  NameNotInZone = 10,
+  /// 16: Bad OPT Version, also [`Self::BAD_SIG`]
+  BadVers = 16,
+  /// 17: Key not recognized
+  BadKey = 17,
+  /// 18: Signature out of time window
+  BadTime = 18,
+  /// 19: Bad TKEY Mode
+  BadMode = 19,
+  /// 20: Duplicate key name
+  BadName = 20,
+  /// 21: Algorithm not supported
+  BadAlg = 21,
+  /// 22: Bad Truncation
+  BadTrunc = 22,
+  /// 23: Bad/missing Server Cookie
+  BadCookie = 23,
+}
+}
+
+impl DnsQueryRcode {
+  /// 16: TSIG Signature Failure, which shares its value with BADVERS
+  pub const BAD_SIG: Self = Self::BadVers;
+}
+
+impl DnsQueryEdns {
+  /// EDNS version 0 advertising `udp_payload_size`, without options.
+  pub const fn new(udp_payload_size: u16) -> Self {
+    Self {
+      udp_payload_size,
+      extended_r_code: 0,
+      version: 0,
+      dnssec_ok: false,
+      z: 0,
+      options: Vec::new(),
+    }
+  }
+
+  /// View of `record`, or `None` if it is no OPT record.
+  pub fn from_record(record: &DnsQueryResourceRecord) -> Option<Self> {
+    let options = match (&record.type_, &record.r_data) {
+      (DnsQueryType::Opt, DnsQueryResourceRecordRDataType::Opt(options)) => options,
+      _ => return None,
+    };
+
+    let ttl = record.ttl.to_be_bytes();
+    Some(Self {
+      udp_payload_size: (&record.class).into(),
+      extended_r_code: ttl[0],
+      version: ttl[1],
+      dnssec_ok: ttl[2] & 0b1000_0000 != 0,
+      z: u16::from_be_bytes([ttl[2] & 0b0111_1111, ttl[3]]),
+      options: options.clone(),
+    })
+  }
+
+  /// 12-bit RCODE of a message with `r_code` in its header.
+  pub fn r_code(&self, r_code: DnsQueryHeaderFlagsRcode) -> DnsQueryRcode {
+    (u16::from(self.extended_r_code) << 4 | u16::from(u8::from(&r_code))).into()
+  }
+
+  /// Split `r_code` between this record and the returned RCODE of the
+  /// header.
+  #[allow(clippy::cast_possible_truncation)]
+  pub fn set_r_code(&mut self, r_code: DnsQueryRcode) -> DnsQueryHeaderFlagsRcode {
+    let r_code = u16::from(&r_code);
+    self.extended_r_code = (r_code >> 4) as u8;
+    ((r_code & 0b1111) as u8).into()
+  }
+
+  /// First option with `code`.
+  pub fn option(&self, code: DnsQueryEdnsOptionCode) -> Option<&DnsQueryEdnsOption> {
+    self.options.iter().find(|option| option.code() == code)
+  }
+
+  /// Replace every option with the code of `option` by `option`.
+  pub fn set_option(&mut self, option: DnsQueryEdnsOption) {
+    let code = option.code();
+    self.options.retain(|other| other.code() != code);
+    self.options.push(option);
+  }
+
+  pub fn client_subnet(&self) -> Option<DnsQueryClientSubnet> {
+    match self.option(DnsQueryEdnsOptionCode::ClientSubnet)? {
+      DnsQueryEdnsOption::ClientSubnet(subnet) => Some(*subnet),
+      _ => None,
+    }
+  }
+
+  pub fn nsid(&self) -> Option<&[u8]> {
+    match self.option(DnsQueryEdnsOptionCode::Nsid)? {
+      DnsQueryEdnsOption::Nsid(nsid) => Some(nsid),
+      _ => None,
+    }
+  }
+
+  /// Every Extended DNS Error, of which there may be several.
+  pub fn extended_errors(&self) -> impl Iterator<Item = &DnsQueryExtendedError> {
+    self.options.iter().filter_map(|option| match option {
+      DnsQueryEdnsOption::ExtendedError(error) => Some(error),
+      _ => None,
+    })
+  }
+
+  /// Client and server cookie, the latter being empty if not sent.
+  pub fn cookie(&self) -> Option<(&[u8; 8], &[u8])> {
+    match self.option(DnsQueryEdnsOptionCode::Cookie)? {
+      DnsQueryEdnsOption::Cookie { client, server } => Some((client, server)),
+      _ => None,
+    }
+  }
+}
+
+impl DnsQueryClientSubnet {
+  /// Subnet of `address` for a query, truncated to `source_prefix_len`
+  /// bits, or `None` if that is longer than the address.
+  pub fn new(address: IpAddr, source_prefix_len: u8) -> Option<Self> {
+    Self::with_scope(address, source_prefix_len, 0)
+  }
+
+  /// Subnet of `address` for a response, see [`Self::new`].
+  pub fn with_scope(address: IpAddr, source_prefix_len: u8, scope_prefix_len: u8
+  ) -> Option<Self> {
+    let max_len = match address {
+      IpAddr::V4(_) => 32,
+      IpAddr::V6(_) => 128,
+    };
+    if source_prefix_len > max_len || scope_prefix_len > max_len {
+      return None;
+    }
+
+    let address = match address {
+      IpAddr::V4(addr) => {
+        let mask = u32::MAX.checked_shl(u32::from(32 - source_prefix_len)).unwrap_or(0);
+        IpAddr::V4(Ipv4Addr::from(u32::from(addr) & mask))
+      }
+      IpAddr::V6(addr) => {
+        let mask = u128::MAX.checked_shl(u32::from(128 - source_prefix_len)).unwrap_or(0);
+        IpAddr::V6(Ipv6Addr::from(u128::from(addr) & mask))
+      }
+    };
+    Some(Self { address, source_prefix_len, scope_prefix_len })
+  }
+
+  /// Address family, 1 for IPv4 and 2 for IPv6
+  pub const fn family(&self) -> u16 {
+    match self.address {
+      IpAddr::V4(_) => 1,
+      IpAddr::V6(_) => 2,
+    }
+  }
+
+  pub const fn address(&self) -> IpAddr {
+    self.address
+  }
+
+  pub const fn source_prefix_len(&self) -> u8 {
+    self.source_prefix_len
+  }
+
+  /// Prefix length the answer is valid for, 0 in queries
+  pub const fn scope_prefix_len(&self) -> u8 {
+    self.scope_prefix_len
+  }
+
+  /// Decode from `data`, `None` if it is malformed, see RFC 7871 §7.1.2.
+  fn from_wire(data: &[u8]) -> Option<Self> {
+    if data.len() < 4 {
+      return None;
+    }
+    let family = u16::from_be_bytes([data[0], data[1]]);
+    let source_prefix_len = data[2];
+    let scope_prefix_len = data[3];
+    let bytes = &data[4..];
+
+    /* Exactly the bytes covering the source prefix are sent */
+    if bytes.len() != (source_prefix_len as usize).div_ceil(8) {
+      return None;
+    }
+    let address = match family {
+      1 if bytes.len() <= 4 => {
+        let mut octets = [0; 4];
+        octets[..bytes.len()].copy_from_slice(bytes);
+        IpAddr::V4(Ipv4Addr::from(octets))
+      }
+      2 if bytes.len() <= 16 => {
+        let mut octets = [0; 16];
+        octets[..bytes.len()].copy_from_slice(bytes);
+        IpAddr::V6(Ipv6Addr::from(octets))
+      }
+      _ => return None,
+    };
+
+    /* Bits beyond the source prefix must be zero */
+    let subnet = Self::with_scope(address, source_prefix_len, scope_prefix_len)?;
+    if subnet.address != address {
+      return None;
+    }
+    Some(subnet)
+  }
+
+  fn write_data_to(&self, vec: &mut Vec<u8>) {
+    let len = (self.source_prefix_len as usize).div_ceil(8);
+    vec.extend_from_slice(&self.family().to_be_bytes());
+    vec.extend_from_slice(&[self.source_prefix_len, self.scope_prefix_len]);
+    match self.address {
+      IpAddr::V4(addr) => vec.extend_from_slice(&addr.octets()[..len]),
+      IpAddr::V6(addr) => vec.extend_from_slice(&addr.octets()[..len]),
+    }
+  }
+}
+
+impl From<&DnsQueryEdns> for DnsQueryResourceRecord {
+  fn from(edns: &DnsQueryEdns) -> Self {
+    let flags = u16::from(edns.dnssec_ok) << 15 | (edns.z & 0x7fff);
+    let flags = flags.to_be_bytes();
+
+    Self {
+      name: String::new(),
+      type_: DnsQueryType::Opt,
+      class: DnsQueryClass::from(edns.udp_payload_size),
+      ttl: u32::from_be_bytes([edns.extended_r_code, edns.version, flags[0], flags[1]]),
+      rd_length: 0,
+      r_data: DnsQueryResourceRecordRDataType::Opt(edns.options.clone()),
+    }
+  }
+}
+
+/// Parse the options of OPT r_data from all of `iter`.
+pub(crate) fn iter_to_edns_options(msg: &[u8], iter: &mut Iter<u8>
+) -> Result<Vec<DnsQueryEdnsOption>, DnsQueryParseError> {
+  let mut options = vec![];
+  while iter.len() != 0 {
+    let err = DnsQueryParseError::at(msg, iter, BadEdnsOption);
+
+    let code = iter_to_u16_be(msg, iter)?;
+    let len = iter_to_u16_be(msg, iter)?;
+    let data = iter_to_slice(msg, iter, len as usize)?;
+
+    options.push(DnsQueryEdnsOption::from_wire(code.into(), data).ok_or(err)?);
+  }
+  Ok(options)
+}
+
+/// Append `options` as OPT r_data to `vec`.
+pub(crate) fn edns_options_to_vec(options: &[DnsQueryEdnsOption], vec: &mut Vec<u8>
+) -> Result<(), DnsQueryWriteError> {
+  for option in options {
+    let mut data = vec![];
+    option.write_data_to(&mut data)?;
+
+    vec.extend_from_slice(&u16::from(&option.code()).to_be_bytes());
+    vec.extend_from_slice(&u16::try_from(data.len())?.to_be_bytes());
+    vec.extend_from_slice(&data);
+  }
+  Ok(())
+}
+
+impl DnsQueryEdnsOption {
+  pub fn code(&self) -> DnsQueryEdnsOptionCode {
+    match self {
+      Self::Nsid(_) => DnsQueryEdnsOptionCode::Nsid,
+      Self::ClientSubnet(_) => DnsQueryEdnsOptionCode::ClientSubnet,
+      Self::Cookie { .. } => DnsQueryEdnsOptionCode::Cookie,
+      Self::Padding(_) => DnsQueryEdnsOptionCode::Padding,
+      Self::ExtendedError(_) => DnsQueryEdnsOptionCode::ExtendedError,
+      Self::Other(code, _) => (*code).into(),
+    }
+  }
+
+  /// Decode the wire form `data` of `code`, `None` if it is malformed.
+  fn from_wire(code: DnsQueryEdnsOptionCode, data: &[u8]) -> Option<Self> {
+    let option = match code {
+      DnsQueryEdnsOptionCode::Nsid => Self::Nsid(data.to_vec()),
+      DnsQueryEdnsOptionCode::ClientSubnet => {
+        Self::ClientSubnet(DnsQueryClientSubnet::from_wire(data)?)
+      }
+      DnsQueryEdnsOptionCode::Cookie => {
+        if data.len() != 8 && !(16..=40).contains(&data.len()) {
+          return None;
+        }
+        let mut client = [0; 8];
+        client.copy_from_slice(&data[..8]);
+        Self::Cookie { client, server: data[8..].to_vec() }
+      }
+      /* Receivers must not care about the content, see RFC 7830 §4 */
+      DnsQueryEdnsOptionCode::Padding => Self::Padding(u16::try_from(data.len()).ok()?),
+      DnsQueryEdnsOptionCode::ExtendedError => {
+        if data.len() < 2 {
+          return None;
+        }
+        /* A trailing NUL is tolerated, see RFC 8914 §2 */
+        let extra_text = String::from_utf8_lossy(&data[2..]);
+        Self::ExtendedError(DnsQueryExtendedError {
+          info_code: u16::from_be_bytes([data[0], data[1]]).into(),
+          extra_text: extra_text.trim_end_matches('\0').to_owned(),
+        })
+      }
+      _ => Self::Other((&code).into(), data.to_vec()),
+    };
+    Some(option)
+  }
+
+  /// Append the wire form of the data of this option to `vec`.
+  fn write_data_to(&self, vec: &mut Vec<u8>) -> Result<(), DnsQueryWriteError> {
+    match self {
+      Self::Nsid(nsid) => vec.extend_from_slice(nsid),
+      Self::ClientSubnet(subnet) => subnet.write_data_to(vec),
+      Self::Cookie { client, server } => {
+        vec.extend_from_slice(client);
+        vec.extend_from_slice(server);
+      }
+      Self::Padding(len) => vec.resize(vec.len() + *len as usize, 0),
+      Self::ExtendedError(error) => {
+        vec.extend_from_slice(&u16::from(&error.info_code).to_be_bytes());
+        vec.extend_from_slice(error.extra_text.as_bytes());
+      }
+      Self::Other(_, data) => vec.extend_from_slice(data),
+    }
+    Ok(())
+  }
+}
+
+/// Option in the style of dig, with the NSID in hex followed by its text
+/// when printable, see RFC 5001 §2.4.
+impl Display for DnsQueryEdnsOption {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Nsid(nsid) => {
+        write!(f, "NSID: {}", HEXUPPER.encode(nsid))?;
+        if !nsid.is_empty() && nsid.iter().all(|byte| (0x20..=0x7e).contains(byte)) {
+          write!(f, " (\"{}\")", String::from_utf8_lossy(nsid))?;
+        }
+        Ok(())
+      }
+      Self::ClientSubnet(subnet) => {
+        write!(f, "CLIENT-SUBNET: {}/{}/{}", subnet.address, subnet.source_prefix_len,
+               subnet.scope_prefix_len)
+      }
+      Self::Cookie { client, server } => {
+        write!(f, "COOKIE: {}{}", HEXUPPER.encode(client), HEXUPPER.encode(server))
+      }
+      Self::Padding(len) => write!(f, "PADDING: {}", len),
+      Self::ExtendedError(error) => write!(f, "EDE: {}", error),
+      Self::Other(code, data) => write!(f, "OPT{}: {}", code, HEXUPPER.encode(data)),
+    }
+  }
+}
+
+impl Display for DnsQueryExtendedError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{} ({:?})", u16::from(&self.info_code), self.info_code)?;
+    if !self.extra_text.is_empty() {
+      write!(f, ": {}", self.extra_text)?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::dns_query::dns_query_error::DnsQueryParseErrorKind::Truncated;
+  use std::str::FromStr;
+  use data_encoding::HEXLOWER;
+
+  /// Parse the OPT r_data `hex`.
+  fn options(hex: &str) -> Result<Vec<DnsQueryEdnsOption>, DnsQueryParseError> {
+    let bytes = HEXLOWER.decode(hex.as_bytes()).unwrap();
+    iter_to_edns_options(&bytes, &mut bytes.iter())
+  }
+
+  /// Wire form of `options` as OPT r_data.
+  fn wire(options: &[DnsQueryEdnsOption]) -> String {
+    let mut bytes = vec![];
+    edns_options_to_vec(options, &mut bytes).unwrap();
+    HEXLOWER.encode(&bytes)
+  }
+
+  #[test]
+  fn extended_r_code() {
+    /* BADCOOKIE is 23: 7 in the header and 1 in the OPT record */
+    let mut edns = DnsQueryEdns::new(DEFAULT_UDP_PAYLOAD_SIZE);
+    let header = edns.set_r_code(DnsQueryRcode::BadCookie);
+    assert_eq!(header, DnsQueryHeaderFlagsRcode::from(7));
+    assert_eq!(edns.extended_r_code, 1);
+    assert_eq!(edns.r_code(header), DnsQueryRcode::BadCookie);
+
+    /* BADVERS has no bits in the header */
+    let header = edns.set_r_code(DnsQueryRcode::BadVers);
+    assert_eq!(header, DnsQueryHeaderFlagsRcode::NoErr);
+    assert_eq!(edns.r_code(header), DnsQueryRcode::BadVers);
+
+    let header = edns.set_r_code(DnsQueryRcode::NameErr);
+    assert_eq!((header, edns.extended_r_code), (DnsQueryHeaderFlagsRcode::NameErr, 0));
+  }
+
+  #[test]
+  fn ttl_and_class() {
+    let mut edns = DnsQueryEdns::new(4096);
+    edns.extended_r_code = 1;
+    edns.version = 2;
+    edns.dnssec_ok = true;
+    edns.z = 0xffff;
+
+    let record = DnsQueryResourceRecord::from(&edns);
+    assert_eq!(u16::from(&record.class), 4096);
+    assert_eq!(record.ttl, 0x0102_ffff);
+
+    /* Only 15 bits of z are sent */
+    edns.z = 0x7fff;
+    assert_eq!(DnsQueryEdns::from_record(&record), Some(edns.clone()));
+
+    edns.dnssec_ok = false;
+    edns.z = 0;
+    assert_eq!(DnsQueryResourceRecord::from(&edns).ttl, 0x0102_0000);
+
+    /* Records of other types have no EDNS view */
+    let mut record = record;
+    record.type_ = DnsQueryType::Txt;
+    assert_eq!(DnsQueryEdns::from_record(&record), None);
+  }
+
+  #[test]
+  fn option_list() {
+    let list = [DnsQueryEdnsOption::Nsid(vec![]), DnsQueryEdnsOption::Padding(2),
+                DnsQueryEdnsOption::Other(65001, vec![0xab])];
+    let hex = "00030000000c00020000fde90001ab";
+    assert_eq!(wire(&list), hex);
+    assert_eq!(options(hex), Ok(list.to_vec()));
+    assert_eq!(options(""), Ok(vec![]));
+
+    /* An option running past the end of the r_data */
+    assert_eq!(options("000300020a"),
+               Err(DnsQueryParseError { offset: 4, kind: Truncated }));
+    assert_eq!(options("0003"),
+               Err(DnsQueryParseError { offset: 2, kind: Truncated }));
+  }
+
+  fn subnet(address: &str, source_prefix_len: u8) -> Option<DnsQueryClientSubnet> {
+    DnsQueryClientSubnet::new(IpAddr::from_str(address).unwrap(), source_prefix_len)
+  }
+
+  #[test]
+  fn client_subnet() {
+    /* Truncated to the source prefix, of which only the covering bytes are sent */
+    let v4 = subnet("192.0.2.255", 23).unwrap();
+    assert_eq!(v4.address(), IpAddr::from_str("192.0.2.0").unwrap());
+    assert_eq!((v4.family(), v4.source_prefix_len(), v4.scope_prefix_len()), (1, 23, 0));
+    assert_eq!(wire(&[DnsQueryEdnsOption::ClientSubnet(v4)]), "0008000700011700c00002");
+    let mut edns = DnsQueryEdns::new(DEFAULT_UDP_PAYLOAD_SIZE);
+    assert_eq!(edns.client_subnet(), None);
+    edns.set_option(DnsQueryEdnsOption::ClientSubnet(v4));
+    assert_eq!(edns.client_subnet(), Some(v4));
+
+    let v6 = DnsQueryClientSubnet::with_scope(IpAddr::from_str("2001:db8:ffff::1").unwrap(),
+                                              36, 48).unwrap();
+    assert_eq!(v6.address(), IpAddr::from_str("2001:db8:f000::").unwrap());
+    let hex = "000800090002243020010db8f0";
+    assert_eq!(wire(&[DnsQueryEdnsOption::ClientSubnet(v6)]), hex);
+    assert_eq!(options(hex), Ok(vec![DnsQueryEdnsOption::ClientSubnet(v6)]));
+
+    /* No address bytes at all for a zero prefix */
+    let any = subnet("192.0.2.1", 0).unwrap();
+    assert_eq!(wire(&[DnsQueryEdnsOption::ClientSubnet(any)]), "0008000400010000");
+
+    assert_eq!(subnet("192.0.2.1", 33), None);
+    assert_eq!(subnet("2001:db8::1", 129), None);
+    assert_eq!(DnsQueryClientSubnet::with_scope(IpAddr::from_str("192.0.2.1").unwrap(), 24, 33),
+               None);
+  }
+
+  #[test]
+  fn malformed_client_subnet() {
+    for data in [
+      /* Non-zero bits past the source prefix */
+      "00011700c00003".to_owned(),
+      /* More or fewer address bytes than the source prefix covers */
+      "00011800c0000200".to_owned(),
+      "00011800c000".to_owned(),
+      /* A prefix longer than the address of the family */
+      "00012100c000020000".to_owned(),
+      format!("00028100{}", "00".repeat(17)),
+      /* Unknown family, and no room for the prefix lengths */
+      "00030800c0".to_owned(),
+      "000118".to_owned(),
+    ] {
+      let hex = format!("0008{:04x}{}", data.len() / 2, data);
+      assert_eq!(options(&hex), Err(DnsQueryParseError { offset: 0, kind: BadEdnsOption }),
+                 "{}", data);
+    }
+  }
+
+  #[test]
+  fn extended_error() {
+    /* Info code and UTF-8 text, with a trailing NUL tolerated */
+    let error = DnsQueryEdnsOption::ExtendedError(DnsQueryExtendedError {
+      info_code: DnsQueryExtendedErrorCode::DnsSecBogus,
+      extra_text: "signature expired ✗".to_owned(),
+    });
+    let hex = "000f001700067369676e6174757265206578706972656420e29c97";
+    assert_eq!(wire(std::slice::from_ref(&error)), hex);
+    assert_eq!(options(hex), Ok(vec![error.clone()]));
+    assert_eq!(options(&format!("000f0018{}00", &hex[8..])), Ok(vec![error.clone()]));
+    assert_eq!(error.to_string(), "EDE: 6 (DnsSecBogus): signature expired ✗");
+
+    /* Unregistered codes are kept, and the text may be empty */
+    let unknown = DnsQueryEdnsOption::ExtendedError(DnsQueryExtendedError {
+      info_code: DnsQueryExtendedErrorCode::from(60000),
+      extra_text: String::new(),
+    });
+    assert_eq!(options("000f0002ea60"), Ok(vec![unknown.clone()]));
+    assert_eq!(unknown.to_string(), "EDE: 60000 (Unknown(60000))");
+
+    assert_eq!(options("000f000100"), Err(DnsQueryParseError { offset: 0, kind: BadEdnsOption }));
+  }
+
+  #[test]
+  fn nsid() {
+    let nsid = DnsQueryEdnsOption::Nsid(b"ns1".to_vec());
+    assert_eq!(wire(std::slice::from_ref(&nsid)), "000300036e7331");
+    assert_eq!(nsid.to_string(), "NSID: 6E7331 (\"ns1\")");
+    assert_eq!(DnsQueryEdnsOption::Nsid(vec![0x00, 0xff]).to_string(), "NSID: 00FF");
+
+    /* Empty in queries */
+    assert_eq!(options("00030000"), Ok(vec![DnsQueryEdnsOption::Nsid(vec![])]));
+    assert_eq!(DnsQueryEdnsOption::Nsid(vec![]).to_string(), "NSID: ");
+  }
+}
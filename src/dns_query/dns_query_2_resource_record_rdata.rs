@@ -0,0 +1,1049 @@
+#![allow(dead_code)]
+
+use crate::dns_query::utils::{iter_to_str, iter_to_slice, iter_to_u8,
+                              iter_to_u16_be, iter_to_u32_be, iter_to_char_str,
+                              str_to_vec, char_str_to_vec, escape_char_str,
+                              unescape_char_str, split_presentation, trim_root,
+                              canonical_name, NameOffsets, DnsQueryClass, DnsQueryType};
+use crate::dns_query::utils::DnsQueryType::{A, Ns, CName, Soa, Mb, Mg, Mr,
+                                            Ptr, HInfo, MInfo, Mx, Txt, Aaaa,
+                                            Srv, NaPtr, SshFp, Tlsa, SMimeA,
+                                            Svcb, Https, Caa, DnsKey, CDnsKey,
+                                            RrSig, Ds, Cds, NSec, NSec3,
+                                            NSec3Param, Opt, TSig, Sig, Key};
+use crate::dns_query::utils::DnsQueryClass::In;
+use crate::dns_query::dns_query_2_resource_record_svcb::DnsQuerySvcb;
+use crate::dns_query::dns_query_2_resource_record_opt::{DnsQueryEdnsOption,
+                                                       DnsQueryRcode,
+                                                       iter_to_edns_options,
+                                                       edns_options_to_vec};
+use crate::dns_query::dns_query_2_resource_record_dnssec::{DnsQueryDnsSecAlgorithm,
+                                                          DnsQueryDsDigestType,
+                                                          dnskey_key_tag,
+                                                          iter_to_type_bit_maps,
+                                                          type_bit_maps_to_vec,
+                                                          fmt_dnssec_time,
+                                                          parse_dnssec_time};
+use crate::dns_query::dns_query_error::{DnsQueryParseError,
+                                       DnsQueryParseErrorKind::BadRData,
+                                       DnsQueryPresentationError,
+                                       DnsQueryWriteError};
+use data_encoding::{HEXUPPER, HEXLOWER_PERMISSIVE, BASE64, BASE32HEX_NOPAD};
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::slice::Iter;
+
+#[derive(Debug, Clone)]
+pub enum DnsQueryResourceRecordRDataType {
+  Ipv4Addr(Ipv4Addr),
+  Ipv6Addr(Ipv6Addr),
+  /// A domain name, as in CNAME, NS, PTR, MB, MG and MR
+  String(String),
+  Mx {
+    preference: u16,
+    exchange: String,
+  },
+  Soa {
+    m_name: String,
+    r_name: String,
+    serial: u32,
+    refresh: u32,
+    retry: u32,
+    expire: u32,
+    minimum: u32,
+  },
+  HInfo {
+    cpu: Vec<u8>,
+    os: Vec<u8>,
+  },
+  MInfo {
+    r_mail_bx: String,
+    e_mail_bx: String,
+  },
+  /// One or more character-strings
+  Txt(Vec<Vec<u8>>),
+  Srv {
+    priority: u16,
+    weight: u16,
+    port: u16,
+    target: String,
+  },
+  NaPtr {
+    order: u16,
+    preference: u16,
+    flags: Vec<u8>,
+    services: Vec<u8>,
+    regexp: Vec<u8>,
+    replacement: String,
+  },
+  /// SVCB and HTTPS
+  Svcb(DnsQuerySvcb),
+  Caa {
+    flags: u8,
+    tag: String,
+    value: Vec<u8>,
+  },
+  /// TLSA and SMIMEA
+  Tlsa {
+    cert_usage: u8,
+    selector: u8,
+    matching_type: u8,
+    data: Vec<u8>,
+  },
+  SshFp {
+    algorithm: u8,
+    fp_type: u8,
+    fingerprint: Vec<u8>,
+  },
+  /// DNSKEY, CDNSKEY and KEY
+  DnsKey {
+    flags: u16,
+    protocol: u8,
+    algorithm: DnsQueryDnsSecAlgorithm,
+    public_key: Vec<u8>,
+  },
+  /// RRSIG and SIG
+  RrSig {
+    type_covered: DnsQueryType,
+    algorithm: DnsQueryDnsSecAlgorithm,
+    labels: u8,
+    original_ttl: u32,
+    /// Seconds since epoch, in serial number arithmetic
+    sig_expiration: u32,
+    /// Seconds since epoch, in serial number arithmetic
+    sig_inception: u32,
+    key_tag: u16,
+    signer_name: String,
+    signature: Vec<u8>,
+  },
+  /// DS and CDS
+  Ds {
+    key_tag: u16,
+    algorithm: DnsQueryDnsSecAlgorithm,
+    digest_type: DnsQueryDsDigestType,
+    digest: Vec<u8>,
+  },
+  NSec {
+    next_domain_name: String,
+    type_bit_maps: Vec<DnsQueryType>,
+  },
+  NSec3 {
+    hash_algorithm: u8,
+    flags: u8,
+    iterations: u16,
+    salt: Vec<u8>,
+    /// Raw hash, without base32hex encoding
+    next_hashed_owner_name: Vec<u8>,
+    type_bit_maps: Vec<DnsQueryType>,
+  },
+  NSec3Param {
+    hash_algorithm: u8,
+    flags: u8,
+    iterations: u16,
+    salt: Vec<u8>,
+  },
+  /// Options of the OPT pseudo-record, see [`DnsQueryEdns`] for the rest
+  ///
+  /// [`DnsQueryEdns`]: crate::dns_query::dns_query_2_resource_record_opt::DnsQueryEdns
+  Opt(Vec<DnsQueryEdnsOption>),
+  /// Transaction signature, see [`dns_query_tsig`] to create and check it
+  ///
+  /// [`dns_query_tsig`]: crate::dns_query::dns_query_tsig
+  TSig {
+    algorithm: String,
+    /// Seconds since epoch, 48 bits wide
+    time_signed: u64,
+    fudge: u16,
+    mac: Vec<u8>,
+    original_id: u16,
+    error: DnsQueryRcode,
+    other_data: Vec<u8>,
+  },
+  _Other(Vec<u8>),
+}
+
+impl DnsQueryResourceRecordRDataType {
+  /// Parse r_data of a record with `type_` and `class` from all of `iter`.
+  pub(crate) fn parse(msg: &[u8], iter: &mut Iter<u8>,
+                      type_: DnsQueryType, class: DnsQueryClass
+  ) -> Result<Self, DnsQueryParseError> {
+    let name = |iter: &mut Iter<u8>| -> Result<String, DnsQueryParseError> {
+      let mut s = String::new();
+      iter_to_str(msg, iter, &mut s)?;
+      Ok(s)
+    };
+
+    let r_data = match (type_, class) {
+      (A, In) => {
+        let octets = iter_to_slice(msg, iter, 4)?;
+        Self::Ipv4Addr(Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]))
+      }
+      (Aaaa, _) => {
+        let mut octets = [0; 16];
+        octets.copy_from_slice(iter_to_slice(msg, iter, 16)?);
+        Self::Ipv6Addr(Ipv6Addr::from(octets))
+      }
+      (CName, _) | (Ns, _) | (Ptr, _) | (Mb, _) | (Mg, _) | (Mr, _) => {
+        Self::String(name(iter)?)
+      }
+      (Mx, _) => {
+        let preference = iter_to_u16_be(msg, iter)?;
+        let exchange = name(iter)?;
+        Self::Mx { preference, exchange }
+      }
+      (Soa, _) => {
+        let m_name = name(iter)?;
+        let r_name = name(iter)?;
+        let serial = iter_to_u32_be(msg, iter)?;
+        let refresh = iter_to_u32_be(msg, iter)?;
+        let retry = iter_to_u32_be(msg, iter)?;
+        let expire = iter_to_u32_be(msg, iter)?;
+        let minimum = iter_to_u32_be(msg, iter)?;
+        Self::Soa { m_name, r_name, serial, refresh, retry, expire, minimum }
+      }
+      (HInfo, _) => {
+        let cpu = iter_to_char_str(msg, iter)?.to_vec();
+        let os = iter_to_char_str(msg, iter)?.to_vec();
+        Self::HInfo { cpu, os }
+      }
+      (MInfo, _) => {
+        let r_mail_bx = name(iter)?;
+        let e_mail_bx = name(iter)?;
+        Self::MInfo { r_mail_bx, e_mail_bx }
+      }
+      (Txt, _) => {
+        let mut strings = vec![iter_to_char_str(msg, iter)?.to_vec()];
+        while iter.len() != 0 {
+          strings.push(iter_to_char_str(msg, iter)?.to_vec());
+        }
+        Self::Txt(strings)
+      }
+      (Srv, _) => {
+        let priority = iter_to_u16_be(msg, iter)?;
+        let weight = iter_to_u16_be(msg, iter)?;
+        let port = iter_to_u16_be(msg, iter)?;
+        let target = name(iter)?;
+        Self::Srv { priority, weight, port, target }
+      }
+      (NaPtr, _) => {
+        let order = iter_to_u16_be(msg, iter)?;
+        let preference = iter_to_u16_be(msg, iter)?;
+        let flags = iter_to_char_str(msg, iter)?.to_vec();
+        let services = iter_to_char_str(msg, iter)?.to_vec();
+        let regexp = iter_to_char_str(msg, iter)?.to_vec();
+        let replacement = name(iter)?;
+        Self::NaPtr { order, preference, flags, services, regexp, replacement }
+      }
+      (Svcb, _) | (Https, _) => Self::Svcb(DnsQuerySvcb::parse(msg, iter)?),
+      (Caa, _) => {
+        let flags = iter_to_u8(msg, iter)?;
+        let err = DnsQueryParseError::at(msg, iter, BadRData);
+        let tag = iter_to_char_str(msg, iter)?;
+        if tag.is_empty() || !tag.iter().all(u8::is_ascii_alphanumeric) {
+          return Err(err);
+        }
+        let tag = String::from_utf8_lossy(tag).into_owned();
+        let value = iter_to_slice(msg, iter, iter.len())?.to_vec();
+        Self::Caa { flags, tag, value }
+      }
+      (Tlsa, _) | (SMimeA, _) => {
+        let cert_usage = iter_to_u8(msg, iter)?;
+        let selector = iter_to_u8(msg, iter)?;
+        let matching_type = iter_to_u8(msg, iter)?;
+        let data = iter_to_slice(msg, iter, iter.len())?.to_vec();
+        Self::Tlsa { cert_usage, selector, matching_type, data }
+      }
+      (SshFp, _) => {
+        let algorithm = iter_to_u8(msg, iter)?;
+        let fp_type = iter_to_u8(msg, iter)?;
+        let fingerprint = iter_to_slice(msg, iter, iter.len())?.to_vec();
+        Self::SshFp { algorithm, fp_type, fingerprint }
+      }
+      (DnsKey, _) | (CDnsKey, _) | (Key, _) => {
+        let flags = iter_to_u16_be(msg, iter)?;
+        let protocol = iter_to_u8(msg, iter)?;
+        let algorithm = iter_to_u8(msg, iter)?.into();
+        let public_key = iter_to_slice(msg, iter, iter.len())?.to_vec();
+        Self::DnsKey { flags, protocol, algorithm, public_key }
+      }
+      (RrSig, _) | (Sig, _) => {
+        let type_covered = iter_to_u16_be(msg, iter)?.into();
+        let algorithm = iter_to_u8(msg, iter)?.into();
+        let labels = iter_to_u8(msg, iter)?;
+        let original_ttl = iter_to_u32_be(msg, iter)?;
+        let sig_expiration = iter_to_u32_be(msg, iter)?;
+        let sig_inception = iter_to_u32_be(msg, iter)?;
+        let key_tag = iter_to_u16_be(msg, iter)?;
+        let signer_name = name(iter)?;
+        let signature = iter_to_slice(msg, iter, iter.len())?.to_vec();
+        Self::RrSig {
+          type_covered, algorithm, labels, original_ttl, sig_expiration,
+          sig_inception, key_tag, signer_name, signature,
+        }
+      }
+      (Ds, _) | (Cds, _) => {
+        let key_tag = iter_to_u16_be(msg, iter)?;
+        let algorithm = iter_to_u8(msg, iter)?.into();
+        let digest_type = iter_to_u8(msg, iter)?.into();
+        let digest = iter_to_slice(msg, iter, iter.len())?.to_vec();
+        Self::Ds { key_tag, algorithm, digest_type, digest }
+      }
+      (NSec, _) => {
+        let next_domain_name = name(iter)?;
+        let type_bit_maps = iter_to_type_bit_maps(msg, iter)?;
+        Self::NSec { next_domain_name, type_bit_maps }
+      }
+      (NSec3, _) => {
+        let hash_algorithm = iter_to_u8(msg, iter)?;
+        let flags = iter_to_u8(msg, iter)?;
+        let iterations = iter_to_u16_be(msg, iter)?;
+        let len = iter_to_u8(msg, iter)?;
+        let salt = iter_to_slice(msg, iter, len as usize)?.to_vec();
+        let err = DnsQueryParseError::at(msg, iter, BadRData);
+        let len = iter_to_u8(msg, iter)?;
+        if len == 0 {
+          return Err(err);
+        }
+        let next_hashed_owner_name = iter_to_slice(msg, iter, len as usize)?.to_vec();
+        let type_bit_maps = iter_to_type_bit_maps(msg, iter)?;
+        Self::NSec3 {
+          hash_algorithm, flags, iterations, salt, next_hashed_owner_name, type_bit_maps,
+        }
+      }
+      (NSec3Param, _) => {
+        let hash_algorithm = iter_to_u8(msg, iter)?;
+        let flags = iter_to_u8(msg, iter)?;
+        let iterations = iter_to_u16_be(msg, iter)?;
+        let len = iter_to_u8(msg, iter)?;
+        let salt = iter_to_slice(msg, iter, len as usize)?.to_vec();
+        Self::NSec3Param { hash_algorithm, flags, iterations, salt }
+      }
+      (Opt, _) => Self::Opt(iter_to_edns_options(msg, iter)?),
+      (TSig, _) => {
+        let algorithm = name(iter)?;
+        let time_signed = iter_to_slice(msg, iter, 6)?.iter()
+          .fold(0, |time, byte| time << 8 | u64::from(*byte));
+        let fudge = iter_to_u16_be(msg, iter)?;
+        let len = iter_to_u16_be(msg, iter)?;
+        let mac = iter_to_slice(msg, iter, len as usize)?.to_vec();
+        let original_id = iter_to_u16_be(msg, iter)?;
+        let error = iter_to_u16_be(msg, iter)?.into();
+        let len = iter_to_u16_be(msg, iter)?;
+        let other_data = iter_to_slice(msg, iter, len as usize)?.to_vec();
+        Self::TSig { algorithm, time_signed, fudge, mac, original_id, error, other_data }
+      }
+      _ => Self::_Other(iter_to_slice(msg, iter, iter.len())?.to_vec()),
+    };
+    Ok(r_data)
+  }
+
+  /// Append this r_data to `vec`, which holds the message written so far.
+  pub(crate) fn write_to(&self, vec: &mut Vec<u8>, offsets: &mut NameOffsets
+  ) -> Result<(), DnsQueryWriteError> {
+    match self {
+      Self::Ipv4Addr(addr) => {
+        vec.extend_from_slice(&addr.octets());
+      }
+      Self::Ipv6Addr(addr) => {
+        vec.extend_from_slice(&addr.octets());
+      }
+      Self::String(s) => {
+        str_to_vec(s, vec, offsets)?;
+      }
+      Self::Mx { preference, exchange } => {
+        vec.extend_from_slice(&preference.to_be_bytes());
+        str_to_vec(exchange, vec, offsets)?;
+      }
+      Self::Soa { m_name, r_name, serial, refresh, retry, expire, minimum } => {
+        str_to_vec(m_name, vec, offsets)?;
+        str_to_vec(r_name, vec, offsets)?;
+        for val in &[serial, refresh, retry, expire, minimum] {
+          vec.extend_from_slice(&val.to_be_bytes());
+        }
+      }
+      Self::HInfo { cpu, os } => {
+        char_str_to_vec(cpu, vec)?;
+        char_str_to_vec(os, vec)?;
+      }
+      Self::MInfo { r_mail_bx, e_mail_bx } => {
+        str_to_vec(r_mail_bx, vec, offsets)?;
+        str_to_vec(e_mail_bx, vec, offsets)?;
+      }
+      Self::Txt(strings) => {
+        for s in strings {
+          char_str_to_vec(s, vec)?;
+        }
+      }
+      /* Names in types newer than RFC 1035 are never compressed */
+      Self::Srv { priority, weight, port, target } => {
+        for val in &[priority, weight, port] {
+          vec.extend_from_slice(&val.to_be_bytes());
+        }
+        str_to_vec(target, vec, &mut None)?;
+      }
+      Self::NaPtr { order, preference, flags, services, regexp, replacement } => {
+        vec.extend_from_slice(&order.to_be_bytes());
+        vec.extend_from_slice(&preference.to_be_bytes());
+        char_str_to_vec(flags, vec)?;
+        char_str_to_vec(services, vec)?;
+        char_str_to_vec(regexp, vec)?;
+        str_to_vec(replacement, vec, &mut None)?;
+      }
+      Self::Svcb(svcb) => {
+        svcb.write_to(vec)?;
+      }
+      Self::Caa { flags, tag, value } => {
+        vec.push(*flags);
+        char_str_to_vec(tag.as_bytes(), vec)?;
+        vec.extend_from_slice(value);
+      }
+      Self::Tlsa { cert_usage, selector, matching_type, data } => {
+        vec.extend_from_slice(&[*cert_usage, *selector, *matching_type]);
+        vec.extend_from_slice(data);
+      }
+      Self::SshFp { algorithm, fp_type, fingerprint } => {
+        vec.extend_from_slice(&[*algorithm, *fp_type]);
+        vec.extend_from_slice(fingerprint);
+      }
+      Self::DnsKey { flags, protocol, algorithm, public_key } => {
+        vec.extend_from_slice(&flags.to_be_bytes());
+        vec.extend_from_slice(&[*protocol, algorithm.into()]);
+        vec.extend_from_slice(public_key);
+      }
+      Self::RrSig {
+        type_covered, algorithm, labels, original_ttl, sig_expiration,
+        sig_inception, key_tag, signer_name, signature,
+      } => {
+        vec.extend_from_slice(&u16::from(type_covered).to_be_bytes());
+        vec.extend_from_slice(&[algorithm.into(), *labels]);
+        for val in &[original_ttl, sig_expiration, sig_inception] {
+          vec.extend_from_slice(&val.to_be_bytes());
+        }
+        vec.extend_from_slice(&key_tag.to_be_bytes());
+        str_to_vec(signer_name, vec, &mut None)?;
+        vec.extend_from_slice(signature);
+      }
+      Self::Ds { key_tag, algorithm, digest_type, digest } => {
+        vec.extend_from_slice(&key_tag.to_be_bytes());
+        vec.extend_from_slice(&[algorithm.into(), digest_type.into()]);
+        vec.extend_from_slice(digest);
+      }
+      Self::NSec { next_domain_name, type_bit_maps } => {
+        str_to_vec(next_domain_name, vec, &mut None)?;
+        type_bit_maps_to_vec(type_bit_maps, vec);
+      }
+      Self::NSec3 {
+        hash_algorithm, flags, iterations, salt, next_hashed_owner_name, type_bit_maps,
+      } => {
+        vec.extend_from_slice(&[*hash_algorithm, *flags]);
+        vec.extend_from_slice(&iterations.to_be_bytes());
+        char_str_to_vec(salt, vec)?;
+        char_str_to_vec(next_hashed_owner_name, vec)?;
+        type_bit_maps_to_vec(type_bit_maps, vec);
+      }
+      Self::NSec3Param { hash_algorithm, flags, iterations, salt } => {
+        vec.extend_from_slice(&[*hash_algorithm, *flags]);
+        vec.extend_from_slice(&iterations.to_be_bytes());
+        char_str_to_vec(salt, vec)?;
+      }
+      Self::Opt(options) => {
+        edns_options_to_vec(options, vec)?;
+      }
+      Self::TSig { algorithm, time_signed, fudge, mac, original_id, error, other_data } => {
+        str_to_vec(algorithm, vec, &mut None)?;
+        vec.extend_from_slice(&time_signed.to_be_bytes()[2..]);
+        vec.extend_from_slice(&fudge.to_be_bytes());
+        vec.extend_from_slice(&u16::try_from(mac.len())?.to_be_bytes());
+        vec.extend_from_slice(mac);
+        vec.extend_from_slice(&original_id.to_be_bytes());
+        vec.extend_from_slice(&u16::from(error).to_be_bytes());
+        vec.extend_from_slice(&u16::try_from(other_data.len())?.to_be_bytes());
+        vec.extend_from_slice(other_data);
+      }
+      Self::_Other(bytes) => {
+        vec.extend_from_slice(bytes);
+      }
+    }
+    Ok(())
+  }
+
+  /// Append the canonical form of this r_data to `vec`, see RFC 4034 §6.2.
+  ///
+  /// Names are never compressed, and are lowercased in the types listed
+  /// there, without NSEC as updated by RFC 6840 §5.1.
+  pub(crate) fn write_canonical_to(&self, vec: &mut Vec<u8>) -> Result<(), DnsQueryWriteError> {
+    let lower = |name: &str| canonical_name(name).ok_or(DnsQueryWriteError::BadEscape);
+
+    let canonical = match self {
+      Self::String(s) => Self::String(lower(s)?),
+      Self::Mx { preference, exchange } => {
+        Self::Mx { preference: *preference, exchange: lower(exchange)? }
+      }
+      Self::Soa { m_name, r_name, serial, refresh, retry, expire, minimum } => {
+        Self::Soa {
+          m_name: lower(m_name)?, r_name: lower(r_name)?, serial: *serial,
+          refresh: *refresh, retry: *retry, expire: *expire, minimum: *minimum,
+        }
+      }
+      Self::MInfo { r_mail_bx, e_mail_bx } => {
+        Self::MInfo { r_mail_bx: lower(r_mail_bx)?, e_mail_bx: lower(e_mail_bx)? }
+      }
+      Self::Srv { priority, weight, port, target } => {
+        Self::Srv { priority: *priority, weight: *weight, port: *port, target: lower(target)? }
+      }
+      Self::NaPtr { order, preference, flags, services, regexp, replacement } => {
+        Self::NaPtr {
+          order: *order, preference: *preference, flags: flags.clone(),
+          services: services.clone(), regexp: regexp.clone(), replacement: lower(replacement)?,
+        }
+      }
+      Self::RrSig {
+        type_covered, algorithm, labels, original_ttl, sig_expiration,
+        sig_inception, key_tag, signer_name, signature,
+      } => {
+        Self::RrSig {
+          type_covered: *type_covered, algorithm: *algorithm, labels: *labels,
+          original_ttl: *original_ttl, sig_expiration: *sig_expiration,
+          sig_inception: *sig_inception, key_tag: *key_tag,
+          signer_name: lower(signer_name)?, signature: signature.clone(),
+        }
+      }
+      _ => return self.write_to(vec, &mut None),
+    };
+    canonical.write_to(vec, &mut None)
+  }
+
+  /// Key tag of a DNSKEY, CDNSKEY or KEY, or `None` for other r_data.
+  pub fn key_tag(&self) -> Option<u16> {
+    match self {
+      Self::DnsKey { flags, protocol, algorithm, public_key } => {
+        Some(dnskey_key_tag(*flags, *protocol, *algorithm, public_key))
+      }
+      _ => None,
+    }
+  }
+}
+
+/* Presentation form */
+
+/// Write `name` as an absolute domain name.
+pub(crate) fn fmt_name(f: &mut Formatter<'_>, name: &str) -> std::fmt::Result {
+  write!(f, "{}.", trim_root(name))
+}
+
+/// Write `types` as space-separated mnemonics, each preceded by a space.
+fn fmt_types(f: &mut Formatter<'_>, types: &[DnsQueryType]) -> std::fmt::Result {
+  for type_ in types {
+    write!(f, " {}", type_)?;
+  }
+  Ok(())
+}
+
+/// Write `salt` as hex, or `-` when empty.
+fn fmt_salt(f: &mut Formatter<'_>, salt: &[u8]) -> std::fmt::Result {
+  if salt.is_empty() {
+    write!(f, "-")
+  } else {
+    write!(f, "{}", HEXUPPER.encode(salt))
+  }
+}
+
+impl Display for DnsQueryResourceRecordRDataType {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Ipv4Addr(addr) => write!(f, "{}", addr),
+      Self::Ipv6Addr(addr) => write!(f, "{}", addr),
+      Self::String(s) => fmt_name(f, s),
+      Self::Mx { preference, exchange } => {
+        write!(f, "{} ", preference)?;
+        fmt_name(f, exchange)
+      }
+      Self::Soa { m_name, r_name, serial, refresh, retry, expire, minimum } => {
+        fmt_name(f, m_name)?;
+        write!(f, " ")?;
+        fmt_name(f, r_name)?;
+        write!(f, " {} {} {} {} {}", serial, refresh, retry, expire, minimum)
+      }
+      Self::HInfo { cpu, os } => {
+        write!(f, "\"{}\" \"{}\"", escape_char_str(cpu, b""), escape_char_str(os, b""))
+      }
+      Self::MInfo { r_mail_bx, e_mail_bx } => {
+        fmt_name(f, r_mail_bx)?;
+        write!(f, " ")?;
+        fmt_name(f, e_mail_bx)
+      }
+      Self::Txt(strings) => {
+        let strings: Vec<String> = strings.iter()
+          .map(|s| format!("\"{}\"", escape_char_str(s, b"")))
+          .collect();
+        write!(f, "{}", strings.join(" "))
+      }
+      Self::Srv { priority, weight, port, target } => {
+        write!(f, "{} {} {} ", priority, weight, port)?;
+        fmt_name(f, target)
+      }
+      Self::NaPtr { order, preference, flags, services, regexp, replacement } => {
+        write!(f, "{} {} \"{}\" \"{}\" \"{}\" ", order, preference,
+               escape_char_str(flags, b""), escape_char_str(services, b""),
+               escape_char_str(regexp, b""))?;
+        fmt_name(f, replacement)
+      }
+      Self::Svcb(svcb) => write!(f, "{}", svcb),
+      Self::Caa { flags, tag, value } => {
+        write!(f, "{} {} \"{}\"", flags, tag, escape_char_str(value, b""))
+      }
+      Self::Tlsa { cert_usage, selector, matching_type, data } => {
+        write!(f, "{} {} {} {}", cert_usage, selector, matching_type, HEXUPPER.encode(data))
+      }
+      Self::SshFp { algorithm, fp_type, fingerprint } => {
+        write!(f, "{} {} {}", algorithm, fp_type, HEXUPPER.encode(fingerprint))
+      }
+      Self::DnsKey { flags, protocol, algorithm, public_key } => {
+        write!(f, "{} {} {} {}", flags, protocol, u8::from(algorithm),
+               BASE64.encode(public_key))
+      }
+      Self::RrSig {
+        type_covered, algorithm, labels, original_ttl, sig_expiration,
+        sig_inception, key_tag, signer_name, signature,
+      } => {
+        write!(f, "{} {} {} {} {} {} {} ", type_covered, u8::from(algorithm), labels,
+               original_ttl, fmt_dnssec_time(*sig_expiration),
+               fmt_dnssec_time(*sig_inception), key_tag)?;
+        fmt_name(f, signer_name)?;
+        write!(f, " {}", BASE64.encode(signature))
+      }
+      Self::Ds { key_tag, algorithm, digest_type, digest } => {
+        write!(f, "{} {} {} {}", key_tag, u8::from(algorithm), u8::from(digest_type),
+               HEXUPPER.encode(digest))
+      }
+      Self::NSec { next_domain_name, type_bit_maps } => {
+        fmt_name(f, next_domain_name)?;
+        fmt_types(f, type_bit_maps)
+      }
+      Self::NSec3 {
+        hash_algorithm, flags, iterations, salt, next_hashed_owner_name, type_bit_maps,
+      } => {
+        write!(f, "{} {} {} ", hash_algorithm, flags, iterations)?;
+        fmt_salt(f, salt)?;
+        write!(f, " {}", BASE32HEX_NOPAD.encode(next_hashed_owner_name))?;
+        fmt_types(f, type_bit_maps)
+      }
+      Self::NSec3Param { hash_algorithm, flags, iterations, salt } => {
+        write!(f, "{} {} {} ", hash_algorithm, flags, iterations)?;
+        fmt_salt(f, salt)
+      }
+      /* OPT and TSIG have no presentation form of their own */
+      Self::Opt(_) | Self::TSig { .. } => {
+        let mut bytes = vec![];
+        self.write_to(&mut bytes, &mut None).map_err(|_| std::fmt::Error)?;
+        Self::_Other(bytes).fmt(f)
+      }
+      /* RFC 3597 §5 */
+      Self::_Other(bytes) => {
+        write!(f, "\\# {}", bytes.len())?;
+        if !bytes.is_empty() {
+          write!(f, " {}", HEXUPPER.encode(bytes))?;
+        }
+        Ok(())
+      }
+    }
+  }
+}
+
+impl DnsQueryResourceRecordRDataType {
+  /// Parse the presentation form of r_data of a record with `type_` and
+  /// `class`, as written by `Display`.
+  ///
+  /// The generic form of RFC 3597 §5, `\# <length> <hex>`, is accepted for
+  /// every type.
+  pub fn from_presentation(s: &str, type_: DnsQueryType, class: DnsQueryClass
+  ) -> Result<Self, DnsQueryPresentationError> {
+    let bad = |reason| DnsQueryPresentationError { reason };
+
+    let fields = split_presentation(s).ok_or_else(|| bad("unterminated quote"))?;
+
+    /* Generic form */ {
+      if fields.first().map(String::as_str) == Some("\\#") {
+        let len: usize = fields.get(1)
+          .and_then(|len| len.parse().ok())
+          .ok_or_else(|| bad("bad r_data length"))?;
+        let bytes = HEXLOWER_PERMISSIVE.decode(fields[2..].concat().as_bytes())
+          .map_err(|_| bad("bad hex"))?;
+        if bytes.len() != len {
+          return Err(bad("r_data length mismatch"));
+        }
+        let mut iter = bytes.iter();
+        let r_data = Self::parse(&bytes, &mut iter, type_, class)
+          .map_err(|_| bad("bad r_data"))?;
+        /* The typed r_data must take up every byte, as with rd_length */
+        if iter.len() != 0 {
+          return Err(bad("r_data length mismatch"));
+        }
+        return Ok(r_data);
+      }
+    }
+
+    let mut fields = fields.iter();
+    let mut next = |reason| fields.next().map(String::as_str).ok_or_else(|| bad(reason));
+
+    fn num<T: std::str::FromStr>(field: &str) -> Result<T, DnsQueryPresentationError> {
+      field.parse().map_err(|_| DnsQueryPresentationError { reason: "bad number" })
+    }
+    fn name(field: &str) -> String {
+      trim_root(field).to_owned()
+    }
+    fn char_str(field: &str) -> Result<Vec<u8>, DnsQueryPresentationError> {
+      unescape_char_str(field).ok_or(DnsQueryPresentationError { reason: "bad escape sequence" })
+    }
+    fn hex(field: &str) -> Result<Vec<u8>, DnsQueryPresentationError> {
+      HEXLOWER_PERMISSIVE.decode(field.as_bytes())
+        .map_err(|_| DnsQueryPresentationError { reason: "bad hex" })
+    }
+    fn base64(field: &str) -> Result<Vec<u8>, DnsQueryPresentationError> {
+      BASE64.decode(field.as_bytes())
+        .map_err(|_| DnsQueryPresentationError { reason: "bad base64" })
+    }
+    fn salt(field: &str) -> Result<Vec<u8>, DnsQueryPresentationError> {
+      if field == "-" { Ok(vec![]) } else { hex(field) }
+    }
+    fn time(field: &str) -> Result<u32, DnsQueryPresentationError> {
+      parse_dnssec_time(field).ok_or(DnsQueryPresentationError { reason: "bad time" })
+    }
+
+    let r_data = match (type_, class) {
+      (A, In) => {
+        Self::Ipv4Addr(next("missing address")?.parse().map_err(|_| bad("bad address"))?)
+      }
+      (Aaaa, _) => {
+        Self::Ipv6Addr(next("missing address")?.parse().map_err(|_| bad("bad address"))?)
+      }
+      (CName, _) | (Ns, _) | (Ptr, _) | (Mb, _) | (Mg, _) | (Mr, _) => {
+        Self::String(name(next("missing name")?))
+      }
+      (Mx, _) => {
+        let preference = num(next("missing preference")?)?;
+        let exchange = name(next("missing exchange")?);
+        Self::Mx { preference, exchange }
+      }
+      (Soa, _) => {
+        let m_name = name(next("missing m_name")?);
+        let r_name = name(next("missing r_name")?);
+        let serial = num(next("missing serial")?)?;
+        let refresh = num(next("missing refresh")?)?;
+        let retry = num(next("missing retry")?)?;
+        let expire = num(next("missing expire")?)?;
+        let minimum = num(next("missing minimum")?)?;
+        Self::Soa { m_name, r_name, serial, refresh, retry, expire, minimum }
+      }
+      (HInfo, _) => {
+        let cpu = char_str(next("missing cpu")?)?;
+        let os = char_str(next("missing os")?)?;
+        Self::HInfo { cpu, os }
+      }
+      (MInfo, _) => {
+        let r_mail_bx = name(next("missing r_mail_bx")?);
+        let e_mail_bx = name(next("missing e_mail_bx")?);
+        Self::MInfo { r_mail_bx, e_mail_bx }
+      }
+      (Txt, _) => {
+        let mut strings = vec![char_str(next("missing string")?)?];
+        while let Ok(field) = next("") {
+          strings.push(char_str(field)?);
+        }
+        Self::Txt(strings)
+      }
+      (Srv, _) => {
+        let priority = num(next("missing priority")?)?;
+        let weight = num(next("missing weight")?)?;
+        let port = num(next("missing port")?)?;
+        let target = name(next("missing target")?);
+        Self::Srv { priority, weight, port, target }
+      }
+      (NaPtr, _) => {
+        let order = num(next("missing order")?)?;
+        let preference = num(next("missing preference")?)?;
+        let flags = char_str(next("missing flags")?)?;
+        let services = char_str(next("missing services")?)?;
+        let regexp = char_str(next("missing regexp")?)?;
+        let replacement = name(next("missing replacement")?);
+        Self::NaPtr { order, preference, flags, services, regexp, replacement }
+      }
+      (Svcb, _) | (Https, _) => return Ok(Self::Svcb(s.parse()?)),
+      (Caa, _) => {
+        let flags = num(next("missing flags")?)?;
+        let tag = next("missing tag")?;
+        if tag.is_empty() || !tag.bytes().all(|b| b.is_ascii_alphanumeric()) {
+          return Err(bad("bad tag"));
+        }
+        let tag = tag.to_owned();
+        let value = char_str(next("missing value")?)?;
+        Self::Caa { flags, tag, value }
+      }
+      (Tlsa, _) | (SMimeA, _) => {
+        let cert_usage = num(next("missing cert_usage")?)?;
+        let selector = num(next("missing selector")?)?;
+        let matching_type = num(next("missing matching_type")?)?;
+        let data = hex(&fields.by_ref().cloned().collect::<String>())?;
+        Self::Tlsa { cert_usage, selector, matching_type, data }
+      }
+      (SshFp, _) => {
+        let algorithm = num(next("missing algorithm")?)?;
+        let fp_type = num(next("missing fp_type")?)?;
+        let fingerprint = hex(&fields.by_ref().cloned().collect::<String>())?;
+        Self::SshFp { algorithm, fp_type, fingerprint }
+      }
+      (DnsKey, _) | (CDnsKey, _) | (Key, _) => {
+        let flags = num(next("missing flags")?)?;
+        let protocol = num(next("missing protocol")?)?;
+        let algorithm = num::<u8>(next("missing algorithm")?)?.into();
+        let public_key = base64(&fields.by_ref().cloned().collect::<String>())?;
+        Self::DnsKey { flags, protocol, algorithm, public_key }
+      }
+      (RrSig, _) | (Sig, _) => {
+        let type_covered = next("missing type_covered")?.parse()?;
+        let algorithm = num::<u8>(next("missing algorithm")?)?.into();
+        let labels = num(next("missing labels")?)?;
+        let original_ttl = num(next("missing original_ttl")?)?;
+        let sig_expiration = time(next("missing sig_expiration")?)?;
+        let sig_inception = time(next("missing sig_inception")?)?;
+        let key_tag = num(next("missing key_tag")?)?;
+        let signer_name = name(next("missing signer_name")?);
+        let signature = base64(&fields.by_ref().cloned().collect::<String>())?;
+        Self::RrSig {
+          type_covered, algorithm, labels, original_ttl, sig_expiration,
+          sig_inception, key_tag, signer_name, signature,
+        }
+      }
+      (Ds, _) | (Cds, _) => {
+        let key_tag = num(next("missing key_tag")?)?;
+        let algorithm = num::<u8>(next("missing algorithm")?)?.into();
+        let digest_type = num::<u8>(next("missing digest_type")?)?.into();
+        let digest = hex(&fields.by_ref().cloned().collect::<String>())?;
+        Self::Ds { key_tag, algorithm, digest_type, digest }
+      }
+      (NSec, _) => {
+        let next_domain_name = name(next("missing next_domain_name")?);
+        let type_bit_maps = fields.by_ref()
+          .map(|type_| type_.parse())
+          .collect::<Result<_, _>>()?;
+        Self::NSec { next_domain_name, type_bit_maps }
+      }
+      (NSec3, _) => {
+        let hash_algorithm = num(next("missing hash_algorithm")?)?;
+        let flags = num(next("missing flags")?)?;
+        let iterations = num(next("missing iterations")?)?;
+        let salt = salt(next("missing salt")?)?;
+        let next_hashed_owner_name = BASE32HEX_NOPAD
+          .decode(next("missing next_hashed_owner_name")?.to_ascii_uppercase().as_bytes())
+          .map_err(|_| bad("bad base32hex"))?;
+        let type_bit_maps = fields.by_ref()
+          .map(|type_| type_.parse())
+          .collect::<Result<_, _>>()?;
+        Self::NSec3 {
+          hash_algorithm, flags, iterations, salt, next_hashed_owner_name, type_bit_maps,
+        }
+      }
+      (NSec3Param, _) => {
+        let hash_algorithm = num(next("missing hash_algorithm")?)?;
+        let flags = num(next("missing flags")?)?;
+        let iterations = num(next("missing iterations")?)?;
+        let salt = salt(next("missing salt")?)?;
+        Self::NSec3Param { hash_algorithm, flags, iterations, salt }
+      }
+      _ => return Err(bad("type only has the generic form")),
+    };
+
+    if fields.next().is_some() {
+      return Err(bad("trailing fields"));
+    }
+    Ok(r_data)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use data_encoding::HEXLOWER;
+
+  fn parse(s: &str, type_: DnsQueryType) -> Result<DnsQueryResourceRecordRDataType,
+                                                   DnsQueryPresentationError> {
+    DnsQueryResourceRecordRDataType::from_presentation(s, type_, DnsQueryClass::In)
+  }
+
+  /// Check that `presentation` is written back as is, and as `wire` in wire
+  /// format, which parses back to `presentation`.
+  fn round_trip(type_: DnsQueryType, presentation: &str, wire: &str) {
+    let r_data = parse(presentation, type_).unwrap();
+    assert_eq!(r_data.to_string(), presentation);
+
+    let mut bytes = vec![];
+    r_data.write_to(&mut bytes, &mut None).unwrap();
+    assert_eq!(HEXLOWER.encode(&bytes), wire, "{}", presentation);
+
+    let mut iter = bytes.iter();
+    let r_data = DnsQueryResourceRecordRDataType::parse(&bytes, &mut iter, type_, In).unwrap();
+    assert_eq!(r_data.to_string(), presentation);
+    assert_eq!(iter.len(), 0);
+  }
+
+  #[test]
+  fn generic_form() {
+    assert_eq!(parse("\\# 4 0A000001", DnsQueryType::A).map(|r_data| r_data.to_string()),
+               Ok("10.0.0.1".to_owned()));
+    assert_eq!(parse("\\# 4 0a 00 00 01", DnsQueryType::A).map(|r_data| r_data.to_string()),
+               Ok("10.0.0.1".to_owned()));
+
+    let mismatch = Err(DnsQueryPresentationError { reason: "r_data length mismatch" });
+    assert_eq!(parse("\\# 5 0102030405", DnsQueryType::A).map(|_| ()), mismatch);
+    assert_eq!(parse("\\# 5 01020304", DnsQueryType::A).map(|_| ()), mismatch);
+    assert_eq!(parse("\\# 3 010203", DnsQueryType::A).map(|_| ()),
+               Err(DnsQueryPresentationError { reason: "bad r_data" }));
+  }
+
+  #[test]
+  fn basic_types() {
+    round_trip(Ns, "ns1.example.", "036e7331076578616d706c6500");
+    round_trip(Ptr, "host.example.", "04686f7374076578616d706c6500");
+    round_trip(Mx, "10 mail.example.", "000a046d61696c076578616d706c6500");
+    round_trip(Soa, "ns1.example. hostmaster.example. 2024010101 7200 3600 1209600 300",
+               "036e7331076578616d706c65000a686f73746d6173746572076578616d706c6500\
+               78a3f17500001c2000000e10001275000000012c");
+    round_trip(HInfo, "\"PC\" \"Linux\"", "025043054c696e7578");
+    round_trip(MInfo, "admin.example. errors.example.",
+               "0561646d696e076578616d706c6500066572726f7273076578616d706c6500");
+    round_trip(Txt, "\"hello world\" \"a\\\"b\\255\"", "0b68656c6c6f20776f726c6404612262ff");
+  }
+
+  #[test]
+  fn basic_types_lenient_input() {
+    /* Relative names, unquoted strings and decimal escapes */
+    assert_eq!(parse("10 mail.example", Mx).map(|r_data| r_data.to_string()),
+               Ok("10 mail.example.".to_owned()));
+    assert_eq!(parse("PC \\076inux", HInfo).map(|r_data| r_data.to_string()),
+               Ok("\"PC\" \"Linux\"".to_owned()));
+    assert_eq!(parse("ns1.example.", Soa).map(|_| ()),
+               Err(DnsQueryPresentationError { reason: "missing r_name" }));
+    assert_eq!(parse("10 mail.example. extra", Mx).map(|_| ()),
+               Err(DnsQueryPresentationError { reason: "trailing fields" }));
+  }
+
+  /// DNSKEY of RFC 4034 §2.3.
+  const RFC4034_DNSKEY: &str = "256 3 5 AQPSKmynfzW4kyBv015MUG2DeIQ3Cbl+BBZH4b/0PY1kxkmvHjcZc8n\
+    okfzj31GajIQKY+5CptLr3buXA10hWqTkF7H6RfoRqXQeogmMHfpftf6zMv1LyBUgia7za6ZEzOJBOztyvhjL742iU\
+    /TpPSEDhm2SNKLijfUppn1UaNvv4w==";
+
+  #[test]
+  fn dnssec_types() {
+    /* Examples of RFC 4034 §2.3, §3.3, §4.3 and §5.4, RFC 5155 Appendix A */
+    round_trip(DnsKey, RFC4034_DNSKEY, "010003050103d22a6ca77f35b893206fd35e4c506d8378843709b9\
+      7e041647e1bff43d8d64c649af1e371973c9e891fce3df519a8c840a63ee42a6d2ebddbb97035d215aa4e417b1fa\
+      45fa11a9741ea2098c1dfa5fb5feb332fd4bc8152089aef36ba644cce2413b3b72be18cbef8da253f4e93d210386\
+      6d9234a2e28df529a67d5468dbefe3");
+    round_trip(RrSig, "A 5 3 86400 20030322173103 20030220173103 2642 example.com. \
+      oJB1W6WNGv+ldvQ3WDG0MQkg5IEhjRip8WTrPYGv07h108dUKGMeDPKijVCHX3DDKdfb+v6oB9wfuh3DTJXUAfI/M0z\
+      mO/zz8bW0Rznl8O3tGNazPwQKkRN20XPXV6nwwfoXmJQbsLNrLfkGJ5D6fwFm8nN+6pBzeDQfsS3Ap3o=",
+               "00010503000151803e7c9dd73e5510d70a52076578616d706c6503636f6d00a090755ba58d1a\
+      ffa576f4375831b4310920e481218d18a9f164eb3d81afd3b875d3c75428631e0cf2a28d50875f70c329d7dbfafe\
+      a807dc1fba1dc34c95d401f23f334ce63bfcf3f1b5b44739e5f0eded18d6b33f040a911376d173d757a9f0c1fa17\
+      98941bb0b36b2df9062790fa7f0166f2737eea907378341fb12dc0a77a");
+    round_trip(Ds, "60485 5 1 2BB183AF5F22588179A53B0A98631FAD1A292118",
+               "ec4505012bb183af5f22588179a53b0a98631fad1a292118");
+    round_trip(NSec, "host.example.com. A MX RRSIG NSEC TYPE1234",
+               "04686f7374076578616d706c6503636f6d000006400100000003041b00000000000000000000\
+      0000000000000000000000000000000020");
+    round_trip(NSec3, "1 1 12 AABBCCDD 2T7B4G4VSA5SMI47K61MV5BV1A22BOJR NS SOA MX RRSIG DNSKEY \
+      NSEC3PARAM",
+               "0101000c04aabbccdd14174eb2409fe28bcb4887a1836f957f0a8425e27b000722010000000290");
+    round_trip(NSec3Param, "1 0 12 AABBCCDD", "0100000c04aabbccdd");
+    round_trip(NSec3Param, "1 0 0 -", "0100000000");
+  }
+
+  #[test]
+  fn type_bit_maps() {
+    /* Written sorted and without duplicates, in windows 0 and 4 */
+    let nsec = parse("host.example.com. TYPE1234 NSEC A MX A RRSIG", NSec).unwrap();
+    let mut bytes = vec![];
+    nsec.write_to(&mut bytes, &mut None).unwrap();
+    let mut iter = bytes.iter();
+    let nsec = DnsQueryResourceRecordRDataType::parse(&bytes, &mut iter, NSec, In).unwrap();
+    assert_eq!(nsec.to_string(), "host.example.com. A MX RRSIG NSEC TYPE1234");
+
+    /* Windows must be in increasing order, and neither empty nor over 32 bytes */
+    let name = "04686f737400";
+    let window_4 = format!("041b{}20", "00".repeat(26));
+    let window_0 = "000140";
+    for (windows, offset) in [(format!("{}{}", window_4, window_0), 35),
+                              (format!("{}{}", window_0, window_0), 9),
+                              ("0000".to_owned(), 6),
+                              (format!("0021{}", "00".repeat(33)), 6)] {
+      let bytes = HEXLOWER.decode(format!("{}{}", name, windows).as_bytes()).unwrap();
+      let mut iter = bytes.iter();
+      assert_eq!(DnsQueryResourceRecordRDataType::parse(&bytes, &mut iter, NSec, In).err(),
+                 Some(DnsQueryParseError { offset, kind: BadRData }), "{}", windows);
+    }
+  }
+
+  #[test]
+  fn key_tag() {
+    /* Key tag of the RFC 4034 §2.3 DNSKEY, as used by the RRSIG of §3.3 */
+    assert_eq!(parse(RFC4034_DNSKEY, DnsKey).unwrap().key_tag(), Some(2642));
+    assert_eq!(parse("60485 5 1 2BB183AF5F22588179A53B0A98631FAD1A292118", Ds).unwrap()
+                 .key_tag(), None);
+  }
+
+  #[test]
+  fn canonical_form() {
+    let canonical = |type_, presentation| {
+      let mut bytes = vec![];
+      parse(presentation, type_).unwrap().write_canonical_to(&mut bytes).unwrap();
+      HEXLOWER.encode(&bytes)
+    };
+
+    /* Labels are lowercased once unescaped, and escaped dots stay in their label */
+    assert_eq!(canonical(Ns, "\\065B.Example."), "026162076578616d706c6500");
+    assert_eq!(canonical(Ns, "A\\.B.example."), "03612e62076578616d706c6500");
+    assert_eq!(canonical(Mx, "10 MAIL.example."), "000a046d61696c076578616d706c6500");
+
+    /* But not the next owner name of NSEC, see RFC 6840 §5.1 */
+    assert_eq!(canonical(NSec, "Host.example. A"), "04486f7374076578616d706c6500000140");
+  }
+
+  #[test]
+  fn caa() {
+    round_trip(Caa, "0 issue \"ca.example.net\"",
+               "0005697373756563612e6578616d706c652e6e6574");
+    round_trip(Caa, "128 tbs \"Unknown\"", "8003746273556e6b6e6f776e");
+
+    /* Tags are non-empty and alphanumeric, see RFC 8659 §4.1 */
+    let bad_tag = Err(DnsQueryPresentationError { reason: "bad tag" });
+    assert_eq!(parse("0 is-sue \"ca.example.net\"", Caa).map(|_| ()), bad_tag);
+    assert_eq!(parse("0 \"\" \"ca.example.net\"", Caa).map(|_| ()), bad_tag);
+    for tag in ["012d", "00"] {
+      let bytes = HEXLOWER.decode(format!("00{}", tag).as_bytes()).unwrap();
+      let mut iter = bytes.iter();
+      assert_eq!(DnsQueryResourceRecordRDataType::parse(&bytes, &mut iter, Caa, In).err(),
+                 Some(DnsQueryParseError { offset: 1, kind: BadRData }));
+    }
+  }
+
+  #[test]
+  fn hex_data() {
+    round_trip(Tlsa, "3 1 1 0C72AC70B745AC19998811B131D662C9AC69DBDBE7CB23E5B514B56664C5D3D6",
+               "0301010c72ac70b745ac19998811b131d662c9ac69dbdbe7cb23e5b514b56664c5d3d6");
+    round_trip(SMimeA, "3 0 0 0102", "0300000102");
+    round_trip(SshFp, "4 2 123456789ABCDEF67890123456789ABCDEF67890123456789ABCDEF123456789AB",
+               "0402123456789abcdef67890123456789abcdef67890123456789abcdef123456789ab");
+
+    /* Lowercase hex, split by whitespace */
+    assert_eq!(parse("3 1 1 0c72ac70b745ac19 998811b131d662c9\tac69dbdbe7cb23e5\n\
+                      b514b56664c5d3d6", Tlsa).map(|r_data| r_data.to_string()),
+               Ok("3 1 1 0C72AC70B745AC19998811B131D662C9AC69DBDBE7CB23E5B514B56664C5D3D6"
+                 .to_owned()));
+    assert_eq!(parse("1 1 dd465c09 cfa51fb4 5020cc83 316fff21 b9ec74ac", SshFp)
+                 .map(|r_data| r_data.to_string()),
+               Ok("1 1 DD465C09CFA51FB45020CC83316FFF21B9EC74AC".to_owned()));
+    assert_eq!(parse("1 1 dd465c0", SshFp).map(|_| ()),
+               Err(DnsQueryPresentationError { reason: "bad hex" }));
+  }
+}
@@ -0,0 +1,593 @@
+#![allow(dead_code)]
+
+use crate::dns_query::utils::{iter_to_str, iter_to_slice, iter_to_u16_be,
+                              iter_to_char_str, str_to_vec, char_str_to_vec,
+                              escape_char_str, unescape_char_str,
+                              split_presentation, trim_root, dns_code_enum};
+use crate::dns_query::dns_query_2_resource_record_rdata::fmt_name;
+use crate::dns_query::dns_query_error::{DnsQueryParseError,
+                                       DnsQueryParseErrorKind::BadSvcParam,
+                                       DnsQueryPresentationError,
+                                       DnsQueryWriteError};
+use data_encoding::BASE64;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::slice::Iter;
+use std::str::FromStr;
+
+/*
+ *  Reference:
+ *  - [RFC 9460](https://tools.ietf.org/html/rfc9460)
+ */
+
+/*
+SVCB and HTTPS r_data format
+
+ 15 14 13 12 11 10  9  8  7  6  5  4  3  2  1  0
+  0  1  2  3  4  5  6  7  0  1  2  3  4  5  6  7
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+|                 svc_priority                  |
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/                  target_name                  /
+/                                               /
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+|                      key                      |  -+
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+   |
+|                    length                     |   | svc_params,
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+   | repeated
+/                     value                     /   |
+/                                               /  -+
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsQuerySvcb {
+  /// 0 for AliasMode, otherwise ServiceMode
+  pub svc_priority: u16,
+  pub target_name: String,
+  pub svc_params: Vec<DnsQuerySvcParam>,
+}
+
+dns_code_enum! {
+pub enum DnsQuerySvcParamKey: u16 {
+  /// 0: Mandatory keys in this RR
+  Mandatory = 0,
+  /// 1: Additional supported protocols
+  Alpn = 1,
+  /// 2: No support for default protocol
+  NoDefaultAlpn = 2,
+  /// 3: Port for alternative endpoint
+  Port = 3,
+  /// 4: IPv4 address hints
+  Ipv4Hint = 4,
+  /// 5: Encrypted ClientHello info
+  Ech = 5,
+  /// 6: IPv6 address hints
+  Ipv6Hint = 6,
+}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DnsQuerySvcParam {
+  Mandatory(Vec<DnsQuerySvcParamKey>),
+  Alpn(Vec<Vec<u8>>),
+  NoDefaultAlpn,
+  Port(u16),
+  Ipv4Hint(Vec<Ipv4Addr>),
+  Ech(Vec<u8>),
+  Ipv6Hint(Vec<Ipv6Addr>),
+  /// Value of a key without a variant of its own
+  Other(u16, Vec<u8>),
+}
+
+impl DnsQuerySvcb {
+  /// Parse from all of `iter`.
+  pub(crate) fn parse(msg: &[u8], iter: &mut Iter<u8>) -> Result<Self, DnsQueryParseError> {
+    let svc_priority = iter_to_u16_be(msg, iter)?;
+
+    let mut target_name = String::new();
+    iter_to_str(msg, iter, &mut target_name)?;
+
+    let mut svc_params: Vec<DnsQuerySvcParam> = vec![];
+    while iter.len() != 0 {
+      let err = DnsQueryParseError::at(msg, iter, BadSvcParam);
+
+      let key = iter_to_u16_be(msg, iter)?;
+      let len = iter_to_u16_be(msg, iter)?;
+      let value = iter_to_slice(msg, iter, len as usize)?;
+
+      /* Keys must be in strictly increasing order */ {
+        if let Some(last) = svc_params.last() {
+          if u16::from(&last.key()) >= key {
+            return Err(err);
+          }
+        }
+      }
+
+      svc_params.push(DnsQuerySvcParam::from_wire(key.into(), value).ok_or(err)?);
+    }
+
+    let result = Self { svc_priority, target_name, svc_params };
+    result.validate().map_err(|_| DnsQueryParseError::at(msg, iter, BadSvcParam))?;
+    Ok(result)
+  }
+
+  /// Append to `vec`, with svc_params sorted by key.
+  ///
+  /// The target name is never compressed.
+  pub(crate) fn write_to(&self, vec: &mut Vec<u8>) -> Result<(), DnsQueryWriteError> {
+    vec.extend_from_slice(&self.svc_priority.to_be_bytes());
+    str_to_vec(&self.target_name, vec, &mut None)?;
+
+    let mut svc_params: Vec<&DnsQuerySvcParam> = self.svc_params.iter().collect();
+    svc_params.sort_by_key(|param| u16::from(&param.key()));
+
+    for param in svc_params {
+      let mut value = vec![];
+      param.write_value_to(&mut value)?;
+
+      vec.extend_from_slice(&u16::from(&param.key()).to_be_bytes());
+      vec.extend_from_slice(&u16::try_from(value.len())?.to_be_bytes());
+      vec.extend_from_slice(&value);
+    }
+    Ok(())
+  }
+
+  /// Check the rules of RFC 9460 §2.2 and §8 that span several svc_params.
+  ///
+  /// Keys must be unique, every key listed in `mandatory` must be present,
+  /// and `no-default-alpn` requires `alpn`.
+  pub fn validate(&self) -> Result<(), DnsQueryPresentationError> {
+    let keys: Vec<DnsQuerySvcParamKey> =
+      self.svc_params.iter().map(DnsQuerySvcParam::key).collect();
+    let has = |key| keys.contains(&key);
+
+    for (i, key) in keys.iter().enumerate() {
+      if keys[..i].contains(key) {
+        return Err(DnsQueryPresentationError { reason: "duplicate SvcParamKey" });
+      }
+    }
+
+    for param in &self.svc_params {
+      match param {
+        DnsQuerySvcParam::Mandatory(mandatory) => {
+          if mandatory.is_empty() {
+            return Err(DnsQueryPresentationError { reason: "empty mandatory list" });
+          }
+          for (i, key) in mandatory.iter().enumerate() {
+            if *key == DnsQuerySvcParamKey::Mandatory {
+              return Err(DnsQueryPresentationError { reason: "mandatory lists itself" });
+            }
+            if mandatory[..i].contains(key) {
+              return Err(DnsQueryPresentationError { reason: "duplicate key in mandatory" });
+            }
+            if !has(*key) {
+              return Err(DnsQueryPresentationError { reason: "mandatory key is missing" });
+            }
+          }
+        }
+        DnsQuerySvcParam::Alpn(ids) => {
+          if ids.is_empty() || ids.iter().any(Vec::is_empty) {
+            return Err(DnsQueryPresentationError { reason: "empty alpn-id" });
+          }
+        }
+        DnsQuerySvcParam::NoDefaultAlpn => {
+          if !has(DnsQuerySvcParamKey::Alpn) {
+            return Err(DnsQueryPresentationError { reason: "no-default-alpn without alpn" });
+          }
+        }
+        DnsQuerySvcParam::Ipv4Hint(addrs) => {
+          if addrs.is_empty() {
+            return Err(DnsQueryPresentationError { reason: "empty ipv4hint" });
+          }
+        }
+        DnsQuerySvcParam::Ipv6Hint(addrs) => {
+          if addrs.is_empty() {
+            return Err(DnsQueryPresentationError { reason: "empty ipv6hint" });
+          }
+        }
+        DnsQuerySvcParam::Port(_)
+        | DnsQuerySvcParam::Ech(_)
+        | DnsQuerySvcParam::Other(..) => {}
+      }
+    }
+    Ok(())
+  }
+}
+
+impl DnsQuerySvcParam {
+  pub fn key(&self) -> DnsQuerySvcParamKey {
+    match self {
+      Self::Mandatory(_) => DnsQuerySvcParamKey::Mandatory,
+      Self::Alpn(_) => DnsQuerySvcParamKey::Alpn,
+      Self::NoDefaultAlpn => DnsQuerySvcParamKey::NoDefaultAlpn,
+      Self::Port(_) => DnsQuerySvcParamKey::Port,
+      Self::Ipv4Hint(_) => DnsQuerySvcParamKey::Ipv4Hint,
+      Self::Ech(_) => DnsQuerySvcParamKey::Ech,
+      Self::Ipv6Hint(_) => DnsQuerySvcParamKey::Ipv6Hint,
+      Self::Other(key, _) => (*key).into(),
+    }
+  }
+
+  /// Decode the wire form `value` of `key`, `None` if it is malformed.
+  fn from_wire(key: DnsQuerySvcParamKey, value: &[u8]) -> Option<Self> {
+    let param = match key {
+      DnsQuerySvcParamKey::Mandatory => {
+        if value.is_empty() || !value.len().is_multiple_of(2) {
+          return None;
+        }
+        Self::Mandatory(value.chunks(2)
+          .map(|key| u16::from_be_bytes([key[0], key[1]]).into())
+          .collect())
+      }
+      DnsQuerySvcParamKey::Alpn => {
+        let mut iter = value.iter();
+        let mut ids = vec![];
+        while iter.len() != 0 {
+          ids.push(iter_to_char_str(value, &mut iter).ok()?.to_vec());
+        }
+        Self::Alpn(ids)
+      }
+      DnsQuerySvcParamKey::NoDefaultAlpn => {
+        if !value.is_empty() {
+          return None;
+        }
+        Self::NoDefaultAlpn
+      }
+      DnsQuerySvcParamKey::Port => {
+        if value.len() != 2 {
+          return None;
+        }
+        Self::Port(u16::from_be_bytes([value[0], value[1]]))
+      }
+      DnsQuerySvcParamKey::Ipv4Hint => {
+        if !value.len().is_multiple_of(4) {
+          return None;
+        }
+        Self::Ipv4Hint(value.chunks(4)
+          .map(|addr| Ipv4Addr::new(addr[0], addr[1], addr[2], addr[3]))
+          .collect())
+      }
+      DnsQuerySvcParamKey::Ech => Self::Ech(value.to_vec()),
+      DnsQuerySvcParamKey::Ipv6Hint => {
+        if !value.len().is_multiple_of(16) {
+          return None;
+        }
+        Self::Ipv6Hint(value.chunks(16)
+          .map(|addr| {
+            let mut octets = [0; 16];
+            octets.copy_from_slice(addr);
+            Ipv6Addr::from(octets)
+          })
+          .collect())
+      }
+      DnsQuerySvcParamKey::Unknown(key) => Self::Other(key, value.to_vec()),
+    };
+    Some(param)
+  }
+
+  fn write_value_to(&self, vec: &mut Vec<u8>) -> Result<(), DnsQueryWriteError> {
+    match self {
+      Self::Mandatory(keys) => {
+        let mut keys: Vec<u16> = keys.iter().map(u16::from).collect();
+        keys.sort_unstable();
+        for key in keys {
+          vec.extend_from_slice(&key.to_be_bytes());
+        }
+      }
+      Self::Alpn(ids) => {
+        for id in ids {
+          char_str_to_vec(id, vec)?;
+        }
+      }
+      Self::NoDefaultAlpn => {}
+      Self::Port(port) => vec.extend_from_slice(&port.to_be_bytes()),
+      Self::Ipv4Hint(addrs) => {
+        for addr in addrs {
+          vec.extend_from_slice(&addr.octets());
+        }
+      }
+      Self::Ech(config) => vec.extend_from_slice(config),
+      Self::Ipv6Hint(addrs) => {
+        for addr in addrs {
+          vec.extend_from_slice(&addr.octets());
+        }
+      }
+      Self::Other(_, value) => vec.extend_from_slice(value),
+    }
+    Ok(())
+  }
+}
+
+/* Presentation form */
+
+impl Display for DnsQuerySvcParamKey {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Mandatory => write!(f, "mandatory"),
+      Self::Alpn => write!(f, "alpn"),
+      Self::NoDefaultAlpn => write!(f, "no-default-alpn"),
+      Self::Port => write!(f, "port"),
+      Self::Ipv4Hint => write!(f, "ipv4hint"),
+      Self::Ech => write!(f, "ech"),
+      Self::Ipv6Hint => write!(f, "ipv6hint"),
+      Self::Unknown(key) => write!(f, "key{}", key),
+    }
+  }
+}
+
+impl FromStr for DnsQuerySvcParamKey {
+  type Err = DnsQueryPresentationError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let key = match s {
+      "mandatory" => Self::Mandatory,
+      "alpn" => Self::Alpn,
+      "no-default-alpn" => Self::NoDefaultAlpn,
+      "port" => Self::Port,
+      "ipv4hint" => Self::Ipv4Hint,
+      "ech" => Self::Ech,
+      "ipv6hint" => Self::Ipv6Hint,
+      _ => {
+        let num = s.strip_prefix("key")
+          .filter(|num| !num.is_empty() && num.bytes().all(|b| b.is_ascii_digit()))
+          .and_then(|num| num.parse::<u16>().ok())
+          .ok_or(DnsQueryPresentationError { reason: "unknown SvcParamKey" })?;
+        num.into()
+      }
+    };
+    Ok(key)
+  }
+}
+
+impl Display for DnsQuerySvcParam {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.key())?;
+
+    match self {
+      Self::Mandatory(keys) => {
+        let keys: Vec<String> = keys.iter().map(ToString::to_string).collect();
+        write!(f, "={}", keys.join(","))
+      }
+      Self::Alpn(ids) => write!(f, "=\"{}\"", escape_value_list(ids)),
+      Self::NoDefaultAlpn => Ok(()),
+      Self::Port(port) => write!(f, "={}", port),
+      Self::Ipv4Hint(addrs) => {
+        let addrs: Vec<String> = addrs.iter().map(ToString::to_string).collect();
+        write!(f, "={}", addrs.join(","))
+      }
+      Self::Ech(config) => write!(f, "={}", BASE64.encode(config)),
+      Self::Ipv6Hint(addrs) => {
+        let addrs: Vec<String> = addrs.iter().map(ToString::to_string).collect();
+        write!(f, "={}", addrs.join(","))
+      }
+      Self::Other(_, value) => write!(f, "=\"{}\"", escape_char_str(value, b"")),
+    }
+  }
+}
+
+impl FromStr for DnsQuerySvcParam {
+  type Err = DnsQueryPresentationError;
+
+  /// Parse a single `key=value` field, with quotes already removed.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let mut split = s.splitn(2, '=');
+    let key: DnsQuerySvcParamKey = split.next().unwrap_or_default().parse()?;
+    let value = split.next();
+
+    let bad = |reason| DnsQueryPresentationError { reason };
+    let list = |value: Option<&str>| -> Result<Vec<Vec<u8>>, DnsQueryPresentationError> {
+      unescape_value_list(value.ok_or_else(|| bad("missing SvcParamValue"))?)
+        .ok_or_else(|| bad("bad escape sequence"))
+    };
+    let items = |value: Option<&str>| -> Result<Vec<String>, DnsQueryPresentationError> {
+      list(value)?.into_iter()
+        .map(|item| String::from_utf8(item).map_err(|_| bad("bad SvcParamValue")))
+        .collect()
+    };
+
+    let param = match key {
+      DnsQuerySvcParamKey::Mandatory => {
+        Self::Mandatory(items(value)?.iter()
+          .map(|key| key.parse())
+          .collect::<Result<_, _>>()?)
+      }
+      DnsQuerySvcParamKey::Alpn => Self::Alpn(list(value)?),
+      DnsQuerySvcParamKey::NoDefaultAlpn => {
+        if value.is_some() {
+          return Err(bad("no-default-alpn takes no value"));
+        }
+        Self::NoDefaultAlpn
+      }
+      DnsQuerySvcParamKey::Port => {
+        let value = value.ok_or_else(|| bad("missing SvcParamValue"))?;
+        Self::Port(value.parse().map_err(|_| bad("bad port"))?)
+      }
+      DnsQuerySvcParamKey::Ipv4Hint => {
+        Self::Ipv4Hint(items(value)?.iter()
+          .map(|addr| addr.parse().map_err(|_| bad("bad ipv4hint")))
+          .collect::<Result<_, _>>()?)
+      }
+      DnsQuerySvcParamKey::Ech => {
+        let value = value.ok_or_else(|| bad("missing SvcParamValue"))?;
+        Self::Ech(BASE64.decode(value.as_bytes()).map_err(|_| bad("bad ech"))?)
+      }
+      DnsQuerySvcParamKey::Ipv6Hint => {
+        Self::Ipv6Hint(items(value)?.iter()
+          .map(|addr| addr.parse().map_err(|_| bad("bad ipv6hint")))
+          .collect::<Result<_, _>>()?)
+      }
+      DnsQuerySvcParamKey::Unknown(key) => {
+        let value = value.unwrap_or_default();
+        Self::Other(key, unescape_char_str(value).ok_or_else(|| bad("bad escape sequence"))?)
+      }
+    };
+    Ok(param)
+  }
+}
+
+/// Write `items` as a value-list of RFC 9460 Appendix A.1, escaped as the
+/// content of a character-string: a comma inside an item becomes `\\,`.
+fn escape_value_list(items: &[Vec<u8>]) -> String {
+  let mut list = vec![];
+  for (i, item) in items.iter().enumerate() {
+    if i != 0 {
+      list.push(b',');
+    }
+    for &byte in item {
+      if byte == b',' || byte == b'\\' {
+        list.push(b'\\');
+      }
+      list.push(byte);
+    }
+  }
+  escape_char_str(&list, b"")
+}
+
+/// Reverse `escape_value_list`, `None` for a malformed escape sequence.
+fn unescape_value_list(str: &str) -> Option<Vec<Vec<u8>>> {
+  let mut items = vec![vec![]];
+  let mut bytes = unescape_char_str(str)?.into_iter();
+  while let Some(byte) = bytes.next() {
+    match byte {
+      b'\\' => items.last_mut()?.push(bytes.next()?),
+      b',' => items.push(vec![]),
+      _ => items.last_mut()?.push(byte),
+    }
+  }
+  Some(items)
+}
+
+impl Display for DnsQuerySvcb {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{} ", self.svc_priority)?;
+    fmt_name(f, &self.target_name)?;
+    for param in &self.svc_params {
+      write!(f, " {}", param)?;
+    }
+    Ok(())
+  }
+}
+
+impl FromStr for DnsQuerySvcb {
+  type Err = DnsQueryPresentationError;
+
+  /// Parse the r_data fields of the presentation form, for example
+  /// `1 svc.example.com. alpn=h2,h3 port=8443`.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let fields = split_presentation(s)
+      .ok_or(DnsQueryPresentationError { reason: "unterminated quote" })?;
+    let mut fields = fields.iter();
+
+    let svc_priority = fields.next()
+      .and_then(|field| field.parse().ok())
+      .ok_or(DnsQueryPresentationError { reason: "bad SvcPriority" })?;
+
+    let target_name = fields.next()
+      .ok_or(DnsQueryPresentationError { reason: "missing TargetName" })?;
+    let target_name = trim_root(target_name).to_owned();
+
+    let svc_params = fields.map(|field| field.parse()).collect::<Result<_, _>>()?;
+
+    let result = Self { svc_priority, target_name, svc_params };
+    result.validate()?;
+    Ok(result)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use data_encoding::HEXLOWER;
+
+  fn parse_wire(wire: &str) -> Result<DnsQuerySvcb, DnsQueryParseError> {
+    let bytes = HEXLOWER.decode(wire.as_bytes()).unwrap();
+    DnsQuerySvcb::parse(&bytes, &mut bytes.iter())
+  }
+
+  /// Check that `presentation` is written back as is, and as `wire` in wire
+  /// format, which parses back to `presentation`.
+  fn round_trip(presentation: &str, wire: &str) {
+    let svcb: DnsQuerySvcb = presentation.parse().unwrap();
+    assert_eq!(svcb.to_string(), presentation);
+
+    let mut bytes = vec![];
+    svcb.write_to(&mut bytes).unwrap();
+    assert_eq!(HEXLOWER.encode(&bytes), wire, "{}", presentation);
+    assert_eq!(parse_wire(wire).map(|svcb| svcb.to_string()), Ok(presentation.to_owned()));
+  }
+
+  #[test]
+  fn rfc9460_vectors() {
+    /* Appendix D */
+    round_trip("0 foo.example.com.", "000003666f6f076578616d706c6503636f6d00");
+    round_trip("1 .", "000100");
+    round_trip("16 foo.example.com. port=53",
+               "001003666f6f076578616d706c6503636f6d00000300020035");
+    round_trip("16 foo.example.org. mandatory=alpn,ipv4hint alpn=\"h2,h3-19\" \
+                ipv4hint=192.0.2.1",
+               "001003666f6f076578616d706c65036f7267000000000400010004000100090268320568332d3139\
+                00040004c0000201");
+    round_trip("1 foo.example.com. ipv6hint=2001:db8::1,2001:db8::53:1",
+               "000103666f6f076578616d706c6503636f6d000006002020010db80000000000000000000000012001\
+                0db8000000000000000000530001");
+    round_trip("1 foo.example.com. ech=AANhYmM=",
+               "000103666f6f076578616d706c6503636f6d00000500050003616263");
+  }
+
+  #[test]
+  fn alpn_value_list() {
+    /* Appendix A.1: a comma and a backslash inside an alpn-id */
+    round_trip("16 foo.example.org. alpn=\"f\\\\\\\\oo\\\\,bar,h2\"",
+               "001003666f6f076578616d706c65036f7267000001000c08665c6f6f2c626172026832");
+    let svcb: DnsQuerySvcb = "16 foo.example.org. alpn=f\\\\\\092oo\\092,bar,h2".parse().unwrap();
+    assert_eq!(svcb.svc_params, [DnsQuerySvcParam::Alpn(vec![b"f\\oo,bar".to_vec(),
+                                                            b"h2".to_vec()])]);
+  }
+
+  #[test]
+  fn params_are_sorted_and_unique() {
+    /* Written in key order, whatever the order they are given in */
+    let svcb: DnsQuerySvcb = "1 . port=443 alpn=h2".parse().unwrap();
+    let mut bytes = vec![];
+    svcb.write_to(&mut bytes).unwrap();
+    assert_eq!(HEXLOWER.encode(&bytes), "000100000100030268320003000201bb");
+
+    /* On the wire, keys must be strictly increasing */
+    let out_of_order = "00010000030002003500010003026832";
+    assert_eq!(parse_wire(out_of_order).err(),
+               Some(DnsQueryParseError { offset: 9, kind: BadSvcParam }));
+    let twice = "0001000003000200350003000201bb";
+    assert_eq!(parse_wire(twice).err(),
+               Some(DnsQueryParseError { offset: 9, kind: BadSvcParam }));
+    assert_eq!("1 . port=53 port=443".parse::<DnsQuerySvcb>().err(),
+               Some(DnsQueryPresentationError { reason: "duplicate SvcParamKey" }));
+  }
+
+  #[test]
+  fn mandatory() {
+    let err = |s: &str| s.parse::<DnsQuerySvcb>().err().map(|err| err.reason);
+    assert_eq!(err("1 . mandatory=port port=443"), None);
+    assert_eq!(err("1 . mandatory=port"), Some("mandatory key is missing"));
+    assert_eq!(err("1 . mandatory=mandatory"), Some("mandatory lists itself"));
+    assert_eq!(err("1 . mandatory=port,port port=443"), Some("duplicate key in mandatory"));
+    assert_eq!(err("1 . mandatory=key7 key7"), None);
+    assert_eq!(err("1 . no-default-alpn"), Some("no-default-alpn without alpn"));
+
+    /* The same rules apply on the wire, reported past the last param */
+    assert_eq!(parse_wire("000100000000020003").err(),
+               Some(DnsQueryParseError { offset: 9, kind: BadSvcParam }));
+  }
+
+  #[test]
+  fn port() {
+    assert_eq!("1 . port=\"8443\"".parse::<DnsQuerySvcb>().map(|svcb| svcb.svc_params),
+               Ok(vec![DnsQuerySvcParam::Port(8443)]));
+    for port in ["port=84,43", "port=65536", "port="] {
+      assert_eq!(format!("1 . {}", port).parse::<DnsQuerySvcb>().err(),
+                 Some(DnsQueryPresentationError { reason: "bad port" }));
+    }
+    assert_eq!("1 . port".parse::<DnsQuerySvcb>().err(),
+               Some(DnsQueryPresentationError { reason: "missing SvcParamValue" }));
+  }
+}
@@ -0,0 +1,226 @@
+use crate::dns_query::dns_query_0_header::{DnsQueryHeader,
+                                           DnsQueryHeaderFlags,
+                                           DnsQueryHeaderFlagsQr,
+                                           DnsQueryHeaderFlagsOpcode,
+                                           DnsQueryHeaderFlagsAa,
+                                           DnsQueryHeaderFlagsTc,
+                                           DnsQueryHeaderFlagsRd,
+                                           DnsQueryHeaderFlagsRa,
+                                           DnsQueryHeaderFlagsAd,
+                                           DnsQueryHeaderFlagsCd,
+                                           DnsQueryHeaderFlagsRcode};
+use crate::dns_query::dns_query_1_question::DnsQueryQuestion;
+use crate::dns_query::dns_query_2_resource_record::DnsQueryResourceRecord;
+use crate::dns_query::dns_query_2_resource_record_opt::DnsQueryEdns;
+use crate::dns_query::dns_query_struct::{DnsRequestQuery, replace_edns};
+use crate::dns_query::utils::{DnsQueryType, DnsQueryClass};
+use std::borrow::Cow;
+
+/// Builder of [`DnsRequestQuery`].
+///
+/// Starts from a standard recursive query with id 0; the section counts in
+/// the header are filled in by [`Self::build`]. A query always carries a
+/// question, which is the root, type A, class IN if none is added.
+#[derive(Debug)]
+pub struct DnsRequestQueryBuilder<'a> {
+  header: DnsQueryHeader,
+  question: Vec<DnsQueryQuestion<'a>>,
+  additional: Vec<DnsQueryResourceRecord>,
+}
+
+impl<'a> Default for DnsRequestQueryBuilder<'a> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<'a> DnsRequestQueryBuilder<'a> {
+  pub const fn new() -> Self {
+    Self {
+      header: DnsQueryHeader {
+        id: 0,
+        flags: DnsQueryHeaderFlags {
+          qr: DnsQueryHeaderFlagsQr::Query,
+          op_code: DnsQueryHeaderFlagsOpcode::StdQuery,
+          aa: DnsQueryHeaderFlagsAa::NonAuthAns,
+          tc: DnsQueryHeaderFlagsTc::NonTrunc,
+          rd: DnsQueryHeaderFlagsRd::Recur,
+          ra: DnsQueryHeaderFlagsRa::NotAvailable,
+          z: 0,
+          ad: DnsQueryHeaderFlagsAd::NotAuthed,
+          cd: DnsQueryHeaderFlagsCd::Checked,
+          r_code: DnsQueryHeaderFlagsRcode::NoErr,
+        },
+        qd_count: 0,
+        an_count: 0,
+        ns_count: 0,
+        ar_count: 0,
+      },
+      question: Vec::new(),
+      additional: Vec::new(),
+    }
+  }
+
+  /* header */
+
+  pub const fn id(mut self, id: u16) -> Self {
+    self.header.id = id;
+    self
+  }
+
+  pub const fn qr(mut self, qr: DnsQueryHeaderFlagsQr) -> Self {
+    self.header.flags.qr = qr;
+    self
+  }
+
+  pub const fn op_code(mut self, op_code: DnsQueryHeaderFlagsOpcode) -> Self {
+    self.header.flags.op_code = op_code;
+    self
+  }
+
+  pub const fn aa(mut self, aa: DnsQueryHeaderFlagsAa) -> Self {
+    self.header.flags.aa = aa;
+    self
+  }
+
+  pub const fn tc(mut self, tc: DnsQueryHeaderFlagsTc) -> Self {
+    self.header.flags.tc = tc;
+    self
+  }
+
+  pub const fn rd(mut self, rd: DnsQueryHeaderFlagsRd) -> Self {
+    self.header.flags.rd = rd;
+    self
+  }
+
+  pub const fn ra(mut self, ra: DnsQueryHeaderFlagsRa) -> Self {
+    self.header.flags.ra = ra;
+    self
+  }
+
+  /// Only the lowest bit of `z` is sent.
+  pub const fn z(mut self, z: u8) -> Self {
+    self.header.flags.z = z & 0b1;
+    self
+  }
+
+  pub const fn ad(mut self, ad: DnsQueryHeaderFlagsAd) -> Self {
+    self.header.flags.ad = ad;
+    self
+  }
+
+  pub const fn cd(mut self, cd: DnsQueryHeaderFlagsCd) -> Self {
+    self.header.flags.cd = cd;
+    self
+  }
+
+  pub const fn r_code(mut self, r_code: DnsQueryHeaderFlagsRcode) -> Self {
+    self.header.flags.r_code = r_code;
+    self
+  }
+
+  /* question */
+
+  pub fn question<T: Into<Cow<'a, str>>>(mut self, q_name: T,
+                                         q_type: DnsQueryType,
+                                         q_class: DnsQueryClass) -> Self {
+    self.question.push(DnsQueryQuestion { q_name: q_name.into(), q_type, q_class });
+    self
+  }
+
+  /* additional */
+
+  pub fn additional(mut self, record: DnsQueryResourceRecord) -> Self {
+    self.additional.push(record);
+    self
+  }
+
+  /// Carry `edns` in an OPT record, replacing any earlier one.
+  pub fn edns(mut self, edns: DnsQueryEdns) -> Self {
+    replace_edns(&mut self.additional, &edns);
+    self
+  }
+
+  #[allow(clippy::cast_possible_truncation)]
+  pub fn build(mut self) -> DnsRequestQuery<'a> {
+    let mut question = self.question.into_iter();
+    let first = question.next().unwrap_or(DnsQueryQuestion {
+      q_name: Cow::Borrowed(""),
+      q_type: DnsQueryType::A,
+      q_class: DnsQueryClass::In,
+    });
+    let extra_questions: Vec<DnsQueryQuestion<'a>> = question.collect();
+
+    self.header.qd_count = 1 + extra_questions.len() as u16;
+    self.header.ar_count = self.additional.len() as u16;
+
+    DnsRequestQuery {
+      header: self.header,
+      question: first,
+      extra_questions,
+      additional: self.additional,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use data_encoding::HEXLOWER;
+
+  /* `from_url` can be evaluated at compile time */
+  const FROM_URL_ID: u16 = {
+    let query = DnsRequestQuery::from_url(Cow::Borrowed("example.com"));
+    let id = query.header.id;
+    std::mem::forget(query);
+    id
+  };
+
+  #[test]
+  fn from_url() {
+    let query = DnsRequestQuery::from_url("example.com".into());
+    assert_eq!(FROM_URL_ID, 0);
+    assert_eq!(HEXLOWER.encode(&query.to_vec(false).unwrap()),
+               "000001000001000000000000076578616d706c6503636f6d0000010001");
+
+    /* The same query as the builder makes it */
+    let built = DnsRequestQuery::builder()
+      .question("example.com", DnsQueryType::A, DnsQueryClass::In)
+      .build();
+    assert_eq!(built.to_vec(false), query.to_vec(false));
+  }
+
+  #[test]
+  fn every_field() {
+    let mut edns = DnsQueryEdns::new(1232);
+    edns.dnssec_ok = true;
+    let query = DnsRequestQuery::builder()
+      .id(0xbeef)
+      .op_code(DnsQueryHeaderFlagsOpcode::Notify)
+      .rd(DnsQueryHeaderFlagsRd::NotRecur)
+      .ad(DnsQueryHeaderFlagsAd::Authed)
+      .cd(DnsQueryHeaderFlagsCd::NotChecked)
+      .question("example.com", DnsQueryType::Aaaa, DnsQueryClass::In)
+      .question("example.com", DnsQueryType::Mx, DnsQueryClass::In)
+      .edns(edns)
+      .build();
+    assert_eq!(query.questions().count(), 2);
+
+    /* id, opcode 4 with RD clear, AD and CD set, two questions and an OPT
+       record with a payload size of 1232 and the DO bit */
+    let header = "beef20300002000000000001";
+    let question = "076578616d706c6503636f6d00001c0001";
+    let opt = "00002904d0000080000000";
+    assert_eq!(HEXLOWER.encode(&query.to_vec(true).unwrap()),
+               format!("{}{}c00c000f0001{}", header, question, opt));
+    assert_eq!(HEXLOWER.encode(&query.to_vec(false).unwrap()),
+               format!("{}{}076578616d706c6503636f6d00000f0001{}", header, question, opt));
+  }
+
+  #[test]
+  fn root_question_by_default() {
+    let query = DnsRequestQuery::builder().id(1).build();
+    assert_eq!(HEXLOWER.encode(&query.to_vec(false).unwrap()),
+               "0001010000010000000000000000010001");
+  }
+}
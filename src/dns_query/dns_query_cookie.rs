@@ -0,0 +1,426 @@
+use crate::dns_query::dns_query_struct::{DnsRequestQuery, DnsRespondQuery};
+use crate::dns_query::dns_query_2_resource_record_opt::{DnsQueryEdns, DnsQueryEdnsOption,
+                                                       DnsQueryRcode,
+                                                       DEFAULT_UDP_PAYLOAD_SIZE};
+use ring::rand::{SecureRandom, SystemRandom};
+use siphasher::sip::SipHasher24;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hasher;
+use std::net::IpAddr;
+
+/*
+ *  Reference:
+ *  - [RFC 7873](https://tools.ietf.org/html/rfc7873)
+ *  - [RFC 9018](https://tools.ietf.org/html/rfc9018)
+ */
+
+/// Age after which a valid server cookie is replaced, see RFC 9018 §4.3.
+const COOKIE_REFRESH_AGE: u32 = 1800;
+
+/// Age after which a server cookie is no longer accepted.
+const COOKIE_MAX_AGE: u32 = 3600;
+
+/// How far in the future a server cookie may be dated, for clock skew.
+const COOKIE_MAX_SKEW: u32 = 300;
+
+/// Random secret of 16 bytes, as used by SipHash-2-4.
+fn random_secret() -> [u8; 16] {
+  let mut secret = [0; 16];
+  /* The system RNG only fails if the OS has none */
+  SystemRandom::new().fill(&mut secret).expect("no system random number generator");
+  secret
+}
+
+/// Wire form of `addr`.
+fn ip_octets(addr: IpAddr) -> Vec<u8> {
+  match addr {
+    IpAddr::V4(addr) => addr.octets().to_vec(),
+    IpAddr::V6(addr) => addr.octets().to_vec(),
+  }
+}
+
+/// Client side of DNS Cookies, see RFC 7873 §5.1 and §5.3.
+///
+/// Holds one client secret, from which a distinct client cookie is derived
+/// for every server, and remembers the server cookie learned from each.
+#[derive(Debug, Clone)]
+pub struct DnsQueryClientCookies {
+  secret: [u8; 16],
+  server_cookies: HashMap<IpAddr, Vec<u8>>,
+  /// Servers already queried again after BADCOOKIE
+  retried: HashSet<IpAddr>,
+}
+
+/// What to do with a response, see [`DnsQueryClientCookies::on_response`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DnsQueryCookieAction {
+  /// Use the response
+  Accept,
+  /// Send the query again, which now carries the new server cookie
+  Retry,
+  /// Send the query again over TCP, as BADCOOKIE came back after a retry
+  RetryTcp,
+  /// Drop the response, which may be spoofed
+  Discard,
+}
+
+impl Default for DnsQueryClientCookies {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl DnsQueryClientCookies {
+  /// With a random client secret.
+  pub fn new() -> Self {
+    Self::with_secret(random_secret())
+  }
+
+  pub fn with_secret(secret: [u8; 16]) -> Self {
+    Self { secret, server_cookies: HashMap::new(), retried: HashSet::new() }
+  }
+
+  /// Switch to a new random client secret, e.g. when the client address
+  /// changes, and forget every server cookie bound to the old one.
+  pub fn rotate(&mut self) {
+    self.secret = random_secret();
+    self.server_cookies.clear();
+    self.retried.clear();
+  }
+
+  /// Client cookie for `server`, SipHash-2-4 of its address keyed by the
+  /// client secret.
+  pub fn client_cookie(&self, server: IpAddr) -> [u8; 8] {
+    let mut hasher = SipHasher24::new_with_key(&self.secret);
+    hasher.write(&ip_octets(server));
+    hasher.finish().to_le_bytes()
+  }
+
+  /// Server cookie last learned from `server`.
+  pub fn server_cookie(&self, server: IpAddr) -> Option<&[u8]> {
+    self.server_cookies.get(&server).map(Vec::as_slice)
+  }
+
+  /// COOKIE option for a query to `server`.
+  pub fn option(&self, server: IpAddr) -> DnsQueryEdnsOption {
+    DnsQueryEdnsOption::Cookie {
+      client: self.client_cookie(server),
+      server: self.server_cookie(server).unwrap_or_default().to_vec(),
+    }
+  }
+
+  /// Add the COOKIE option for `server` to `request`, along with an OPT
+  /// record if it has none yet.
+  pub fn apply(&self, server: IpAddr, request: &mut DnsRequestQuery<'_>) {
+    let mut edns = request.edns().unwrap_or_else(|| DnsQueryEdns::new(DEFAULT_UDP_PAYLOAD_SIZE));
+    edns.set_option(self.option(server));
+    request.set_edns(&edns);
+  }
+
+  /// Check the COOKIE option of `response` from `server`, and learn its
+  /// server cookie.
+  ///
+  /// A response without cookies is accepted, as `server` may not support
+  /// them, unless a server cookie was learned from it already. BADCOOKIE is
+  /// retried once, then over TCP if it comes back.
+  pub fn on_response(&mut self, server: IpAddr, response: &DnsRespondQuery<'_>
+  ) -> DnsQueryCookieAction {
+    let bad_cookie = response.r_code() == DnsQueryRcode::BadCookie;
+
+    let edns = response.edns();
+    let (client, server_cookie) = match edns.as_ref().and_then(DnsQueryEdns::cookie) {
+      Some(cookie) => cookie,
+      None if bad_cookie || self.server_cookies.contains_key(&server) => {
+        return DnsQueryCookieAction::Discard;
+      }
+      None => return DnsQueryCookieAction::Accept,
+    };
+
+    if *client != self.client_cookie(server) {
+      return DnsQueryCookieAction::Discard;
+    }
+    if !server_cookie.is_empty() {
+      self.server_cookies.insert(server, server_cookie.to_vec());
+    }
+
+    if !bad_cookie {
+      self.retried.remove(&server);
+      DnsQueryCookieAction::Accept
+    } else if self.retried.insert(server) {
+      DnsQueryCookieAction::Retry
+    } else {
+      /* Queries over TCP need no cookie to get through */
+      self.retried.remove(&server);
+      DnsQueryCookieAction::RetryTcp
+    }
+  }
+}
+
+/// Server side of DNS Cookies, creating and verifying the interoperable
+/// server cookies of RFC 9018.
+///
+/// The previous secret is still accepted after [`Self::rotate`], so that
+/// cookies handed out just before keep working.
+#[derive(Debug, Clone)]
+pub struct DnsQueryServerCookies {
+  secret: [u8; 16],
+  previous_secret: Option<[u8; 16]>,
+}
+
+/// Outcome of [`DnsQueryServerCookies::check`].
+///
+/// Every variant but `Missing` carries the COOKIE option for the response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DnsQueryServerCookieCheck {
+  /// No COOKIE option in the request
+  Missing,
+  /// Only a client cookie in the request
+  ClientOnly(DnsQueryEdnsOption),
+  /// A server cookie that does not verify
+  Invalid(DnsQueryEdnsOption),
+  /// A server cookie that verifies
+  Valid(DnsQueryEdnsOption),
+}
+
+/*
+Server cookie format
+
+ 15 14 13 12 11 10  9  8  7  6  5  4  3  2  1  0
+  0  1  2  3  4  5  6  7  0  1  2  3  4  5  6  7
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+|        version        |                       |
++--+--+--+--+--+--+--+--+       reserved        |
+|                                               |
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+|                   timestamp                   |
+|                                               |
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+|                                               |
+|                     hash                      |
+|                                               |
+|                                               |
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+*/
+impl Default for DnsQueryServerCookies {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl DnsQueryServerCookies {
+  /// With a random server secret.
+  pub fn new() -> Self {
+    Self::with_secret(random_secret())
+  }
+
+  /// With `secret`, which all servers of an anycast group should share.
+  pub const fn with_secret(secret: [u8; 16]) -> Self {
+    Self { secret, previous_secret: None }
+  }
+
+  /// Switch to `secret`, still accepting cookies of the current one.
+  pub fn rotate(&mut self, secret: [u8; 16]) {
+    self.previous_secret = Some(self.secret);
+    self.secret = secret;
+  }
+
+  /// Server cookie for `client` at `client_ip`, dated `now` in seconds
+  /// since epoch.
+  pub fn server_cookie(&self, client: &[u8; 8], client_ip: IpAddr, now: u32) -> [u8; 16] {
+    Self::create(&self.secret, client, client_ip, now)
+  }
+
+  fn create(secret: &[u8; 16], client: &[u8; 8], client_ip: IpAddr, now: u32) -> [u8; 16] {
+    let mut cookie = [0; 16];
+    cookie[0] = 1;
+    cookie[4..8].copy_from_slice(&now.to_be_bytes());
+
+    let mut hasher = SipHasher24::new_with_key(secret);
+    hasher.write(client);
+    hasher.write(&cookie[..8]);
+    hasher.write(&ip_octets(client_ip));
+    cookie[8..].copy_from_slice(&hasher.finish().to_le_bytes());
+    cookie
+  }
+
+  /// Whether `server` is a cookie created for `client` at `client_ip`, with
+  /// either secret, that has not expired at `now`.
+  pub fn verify(&self, client: &[u8; 8], server: &[u8], client_ip: IpAddr, now: u32) -> bool {
+    if server.len() != 16 || server[0] != 1 {
+      return false;
+    }
+
+    let timestamp = u32::from_be_bytes([server[4], server[5], server[6], server[7]]);
+    let age = now.wrapping_sub(timestamp);
+    if age > COOKIE_MAX_AGE && timestamp.wrapping_sub(now) > COOKIE_MAX_SKEW {
+      return false;
+    }
+
+    [Some(self.secret), self.previous_secret].iter().flatten().any(|secret| {
+      /* Compare in constant time */
+      let expected = Self::create(secret, client, client_ip, timestamp);
+      expected.iter().zip(server).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+    })
+  }
+
+  /// Check the COOKIE option of `request` from `client_ip` at `now`, see
+  /// RFC 7873 §5.2.
+  ///
+  /// A valid server cookie is echoed until it is half an hour old.
+  pub fn check(&self, request: &DnsRespondQuery<'_>, client_ip: IpAddr, now: u32
+  ) -> DnsQueryServerCookieCheck {
+    let edns = request.edns();
+    let (client, server) = match edns.as_ref().and_then(DnsQueryEdns::cookie) {
+      Some(cookie) => cookie,
+      None => return DnsQueryServerCookieCheck::Missing,
+    };
+
+    let option = |server: &[u8]| DnsQueryEdnsOption::Cookie {
+      client: *client,
+      server: server.to_vec(),
+    };
+    let fresh = option(&self.server_cookie(client, client_ip, now));
+
+    if server.is_empty() {
+      DnsQueryServerCookieCheck::ClientOnly(fresh)
+    } else if !self.verify(client, server, client_ip, now) {
+      DnsQueryServerCookieCheck::Invalid(fresh)
+    } else {
+      let timestamp = u32::from_be_bytes([server[4], server[5], server[6], server[7]]);
+      if now.wrapping_sub(timestamp) < COOKIE_REFRESH_AGE {
+        DnsQueryServerCookieCheck::Valid(option(server))
+      } else {
+        DnsQueryServerCookieCheck::Valid(fresh)
+      }
+    }
+  }
+}
+
+impl DnsQueryServerCookieCheck {
+  /// COOKIE option for the response.
+  pub const fn option(&self) -> Option<&DnsQueryEdnsOption> {
+    match self {
+      Self::Missing => None,
+      Self::ClientOnly(option) | Self::Invalid(option) | Self::Valid(option) => Some(option),
+    }
+  }
+
+  /// Add the COOKIE option to `response`, along with an OPT record if it has
+  /// none yet.
+  pub fn apply(&self, response: &mut DnsRespondQuery<'_>) {
+    if let Some(option) = self.option() {
+      let mut edns = response.edns()
+        .unwrap_or_else(|| DnsQueryEdns::new(DEFAULT_UDP_PAYLOAD_SIZE));
+      edns.set_option(option.clone());
+      response.set_edns(&edns);
+    }
+  }
+
+  /// Turn `response` into a BADCOOKIE one carrying a fresh cookie, for a
+  /// server that requires valid cookies, see RFC 7873 §5.2.3.
+  pub fn reject(&self, response: &mut DnsRespondQuery<'_>) {
+    if self.option().is_none() {
+      return;
+    }
+    response.answer.clear();
+    response.authority.clear();
+    self.apply(response);
+
+    if let Some(mut edns) = response.edns() {
+      response.header.flags.r_code = edns.set_r_code(DnsQueryRcode::BadCookie);
+      response.set_edns(&edns);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use data_encoding::HEXLOWER;
+  use std::convert::TryFrom;
+
+  fn hex<const N: usize>(s: &str) -> [u8; N] {
+    <[u8; N]>::try_from(HEXLOWER.decode(s.as_bytes()).unwrap()).unwrap()
+  }
+
+  #[test]
+  fn server_cookie_rfc9018() {
+    /* RFC 9018 Appendix A */
+    for (secret, client, client_ip, now, expected) in [
+      ("e5e973e5a6b2a43f48e7dc849e37bfcf", "2464c4abcf10c957", "198.51.100.100", 1_559_731_985,
+       "010000005cf79f111f8130c3eee29480"),
+      ("e5e973e5a6b2a43f48e7dc849e37bfcf", "2464c4abcf10c957", "198.51.100.100", 1_559_734_385,
+       "010000005cf7a871d4a564a1442aca77"),
+      ("dd3bdf9344b678b185a6f5cb60fca715", "22681ab97d52c298", "2001:db8:220:1:59de:d0f4:8769:82b8",
+       1_559_741_817, "010000005cf7c57926556bd0934c72f8"),
+    ] {
+      let cookies = DnsQueryServerCookies::with_secret(hex(secret));
+      let client = hex(client);
+      let client_ip = client_ip.parse().unwrap();
+      let cookie = cookies.server_cookie(&client, client_ip, now);
+      assert_eq!(HEXLOWER.encode(&cookie), expected);
+      assert!(cookies.verify(&client, &cookie, client_ip, now + COOKIE_MAX_AGE));
+      assert!(!cookies.verify(&client, &cookie, client_ip, now + COOKIE_MAX_AGE + 1));
+    }
+  }
+
+  /// Exchange of `request`, carrying the client cookies, with `server`,
+  /// rejecting it if `reject` is set.
+  fn exchange(cookies: &DnsQueryClientCookies, server: &DnsQueryServerCookies, reject: bool,
+              client_ip: IpAddr, server_ip: IpAddr, now: u32) -> Vec<u8> {
+    let mut request = DnsRequestQuery::from_url("example.com".into());
+    cookies.apply(server_ip, &mut request);
+    let bytes = request.to_vec(false).unwrap();
+
+    let mut response = DnsRespondQuery::try_from(&mut bytes.iter()).unwrap();
+    let check = server.check(&response, client_ip, now);
+    if reject {
+      check.reject(&mut response);
+    } else {
+      check.apply(&mut response);
+    }
+    response.to_vec(false).unwrap()
+  }
+
+  #[test]
+  fn client_retries_once_then_over_tcp() {
+    let client_ip: IpAddr = "198.51.100.100".parse().unwrap();
+    let server_ip: IpAddr = "192.0.2.53".parse().unwrap();
+    let server = DnsQueryServerCookies::with_secret([1; 16]);
+    let mut cookies = DnsQueryClientCookies::with_secret([2; 16]);
+    let now = 1_559_731_985;
+
+    let on_response = |reject: bool, cookies: &mut DnsQueryClientCookies| {
+      let bytes = exchange(cookies, &server, reject, client_ip, server_ip, now);
+      let response = DnsRespondQuery::try_from(&mut bytes.iter()).unwrap();
+      cookies.on_response(server_ip, &response)
+    };
+
+    assert_eq!(on_response(false, &mut cookies), DnsQueryCookieAction::Accept);
+    assert_eq!(cookies.server_cookie(server_ip),
+               Some(&server.server_cookie(&cookies.client_cookie(server_ip), client_ip, now)[..]));
+
+    assert_eq!(on_response(true, &mut cookies), DnsQueryCookieAction::Retry);
+    assert_eq!(on_response(true, &mut cookies), DnsQueryCookieAction::RetryTcp);
+    /* A later query may be retried again */
+    assert_eq!(on_response(true, &mut cookies), DnsQueryCookieAction::Retry);
+    assert_eq!(on_response(false, &mut cookies), DnsQueryCookieAction::Accept);
+    assert_eq!(on_response(true, &mut cookies), DnsQueryCookieAction::Retry);
+  }
+
+  #[test]
+  fn client_discards_missing_cookies() {
+    let server_ip: IpAddr = "192.0.2.53".parse().unwrap();
+    let mut cookies = DnsQueryClientCookies::with_secret([2; 16]);
+
+    let bytes = DnsRequestQuery::from_url("example.com".into()).to_vec(false).unwrap();
+    let response = DnsRespondQuery::try_from(&mut bytes.iter()).unwrap();
+    assert_eq!(cookies.on_response(server_ip, &response), DnsQueryCookieAction::Accept);
+
+    /* Once the server is known to support cookies */
+    cookies.server_cookies.insert(server_ip, vec![0; 16]);
+    assert_eq!(cookies.on_response(server_ip, &response), DnsQueryCookieAction::Discard);
+    assert_eq!(cookies.on_response("192.0.2.54".parse().unwrap(), &response),
+               DnsQueryCookieAction::Accept);
+  }
+}
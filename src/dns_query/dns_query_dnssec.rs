@@ -0,0 +1,605 @@
+use crate::dns_query::dns_query_2_resource_record::DnsQueryResourceRecord;
+use crate::dns_query::dns_query_2_resource_record_rdata::DnsQueryResourceRecordRDataType;
+use crate::dns_query::dns_query_2_resource_record_dnssec::{DnsQueryDnsSecAlgorithm,
+                                                          DnsQueryDsDigestType,
+                                                          dnskey_key_tag,
+                                                          DNSKEY_FLAG_ZONE,
+                                                          DNSKEY_FLAG_REVOKE};
+use crate::dns_query::dns_query_error::DnsQueryDnsSecError;
+use crate::dns_query::utils::{str_to_canonical_vec, split_unescaped, unescape_char_str,
+                              DnsQueryType};
+use ring::{digest, signature};
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/*
+ *  Reference:
+ *  - [RFC 4034](https://tools.ietf.org/html/rfc4034)
+ *  - [RFC 4035](https://tools.ietf.org/html/rfc4035)
+ *  - [RFC 6840](https://tools.ietf.org/html/rfc6840)
+ */
+
+/// Seconds since epoch, truncated to 32 bits as in RRSIG times.
+pub(crate) fn now() -> u32 {
+  let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+  #[allow(clippy::cast_possible_truncation)]
+  let secs = secs as u32;
+  secs
+}
+
+/// `a <= b` in serial number arithmetic, see RFC 1982.
+pub(crate) const fn serial_le(a: u32, b: u32) -> bool {
+  b.wrapping_sub(a) < 1 << 31
+}
+
+/// Labels of `name`, still escaped, without the root label.
+pub(crate) fn name_labels(name: &str) -> Vec<&str> {
+  split_unescaped(name, b'.').into_iter().filter(|label| !label.is_empty()).collect()
+}
+
+/// Bytes of `label`, lowercased, for comparisons.
+fn label_key(label: &str) -> Vec<u8> {
+  let mut bytes = unescape_char_str(label).unwrap_or_else(|| label.as_bytes().to_vec());
+  bytes.make_ascii_lowercase();
+  bytes
+}
+
+/// Whether `name` is `ancestor` or below it, ignoring case.
+pub(crate) fn is_subdomain(name: &str, ancestor: &str) -> bool {
+  let name = name_labels(name);
+  let ancestor = name_labels(ancestor);
+
+  name.len() >= ancestor.len() && name.iter().rev().zip(ancestor.iter().rev())
+    .all(|(label, other)| label_key(label) == label_key(other))
+}
+
+/// Whether `a` and `b` are the same name, ignoring case.
+pub(crate) fn name_eq(a: &str, b: &str) -> bool {
+  let a = name_labels(a);
+  let b = name_labels(b);
+
+  a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| label_key(a) == label_key(b))
+}
+
+/// Canonical order of names, see RFC 4034 §6.1.
+pub(crate) fn name_cmp(a: &str, b: &str) -> Ordering {
+  let a = name_labels(a);
+  let b = name_labels(b);
+
+  for (a, b) in a.iter().rev().zip(b.iter().rev()) {
+    let ord = label_key(a).cmp(&label_key(b));
+    if ord != Ordering::Equal {
+      return ord;
+    }
+  }
+  a.len().cmp(&b.len())
+}
+
+/// Verify `rrsig` over `rrset` with one of `dnskeys`, at the current time.
+///
+/// See [`verify_rrset_at`].
+pub fn verify_rrset<'a>(rrset: &[&DnsQueryResourceRecord],
+                        rrsig: &DnsQueryResourceRecord,
+                        dnskeys: &[&'a DnsQueryResourceRecord]
+) -> Result<&'a DnsQueryResourceRecord, DnsQueryDnsSecError> {
+  verify_rrset_at(rrset, rrsig, dnskeys, now())
+}
+
+/// Verify `rrsig` over `rrset` with one of `dnskeys`, with `now` as seconds
+/// since epoch, and return the DNSKEY that verified it.
+///
+/// Only DNSKEYs owned by the signer name, with the zone flag, protocol 3 and
+/// the algorithm and key tag of the RRSIG are tried. The TTLs of `rrset` are
+/// ignored in favor of the original TTL of the RRSIG; capping them is left to
+/// the caller.
+pub fn verify_rrset_at<'a>(rrset: &[&DnsQueryResourceRecord],
+                           rrsig: &DnsQueryResourceRecord,
+                           dnskeys: &[&'a DnsQueryResourceRecord],
+                           now: u32
+) -> Result<&'a DnsQueryResourceRecord, DnsQueryDnsSecError> {
+  let data = signed_data(rrset, rrsig, now)?;
+
+  let mut result = Err(DnsQueryDnsSecError::NoMatchingKey);
+  for dnskey in dnskeys {
+    if key_matches(rrsig, dnskey) {
+      result = verify_signature(rrsig, dnskey, &data).map(|_| *dnskey);
+      if result.is_ok() {
+        break;
+      }
+    }
+  }
+  result
+}
+
+/// Verify `rrsig` over `rrset` with `dnskey` alone, with `now` as seconds
+/// since epoch.
+pub fn verify_rrset_with_key_at(rrset: &[&DnsQueryResourceRecord],
+                                rrsig: &DnsQueryResourceRecord,
+                                dnskey: &DnsQueryResourceRecord,
+                                now: u32
+) -> Result<(), DnsQueryDnsSecError> {
+  let data = signed_data(rrset, rrsig, now)?;
+
+  if !key_matches(rrsig, dnskey) {
+    return Err(DnsQueryDnsSecError::NoMatchingKey);
+  }
+  verify_signature(rrsig, dnskey, &data)
+}
+
+/// Check `rrsig` against `rrset` and `now`, and build the data it signs, see
+/// RFC 4034 §3.1.8.1 and RFC 4035 §5.3.1.
+fn signed_data(rrset: &[&DnsQueryResourceRecord], rrsig: &DnsQueryResourceRecord,
+               now: u32) -> Result<Vec<u8>, DnsQueryDnsSecError> {
+  use DnsQueryDnsSecError::*;
+
+  let (type_covered, labels, original_ttl, sig_expiration, sig_inception,
+    signer_name, signature) = match &rrsig.r_data {
+    DnsQueryResourceRecordRDataType::RrSig {
+      type_covered, labels, original_ttl, sig_expiration, sig_inception,
+      signer_name, signature, ..
+    } => (*type_covered, *labels, *original_ttl, *sig_expiration, *sig_inception,
+          signer_name, signature),
+    _ => return Err(RrSigMismatch),
+  };
+  /* SIG records share the RRSIG r_data */
+  if rrsig.type_ != DnsQueryType::RrSig {
+    return Err(RrSigMismatch);
+  }
+
+  /* Check RRset */
+  let first = rrset.first().ok_or(BadRrSet)?;
+  if rrset.iter().any(|record| !name_eq(&record.name, &first.name)
+    || record.class != first.class || record.type_ != first.type_) {
+    return Err(BadRrSet);
+  }
+
+  /* Check RRSIG against RRset */
+  let owner_labels = name_labels(&first.name);
+  let wildcard = owner_labels.first() == Some(&"*");
+  let owner_len = owner_labels.len() - if wildcard { 1 } else { 0 };
+  if !name_eq(&rrsig.name, &first.name) || rrsig.class != first.class
+    || type_covered != first.type_ || labels as usize > owner_len {
+    return Err(RrSigMismatch);
+  }
+  if !is_subdomain(&first.name, signer_name) {
+    return Err(BadSignerName);
+  }
+
+  /* Check validity period */
+  if !serial_le(sig_inception, now) {
+    return Err(NotYetValid);
+  }
+  if !serial_le(now, sig_expiration) {
+    return Err(Expired);
+  }
+
+  /* RRSIG r_data without signature */
+  let mut data = vec![];
+  rrsig.r_data.write_canonical_to(&mut data).map_err(|_| RrSigMismatch)?;
+  data.truncate(data.len() - signature.len());
+
+  /* Owner name, expanded back to the wildcard if needed */
+  let owner = {
+    let owner = owner_labels[owner_labels.len() - labels as usize..].join(".");
+    let owner = if (labels as usize) < owner_labels.len() {
+      format!("*.{}", owner)
+    } else {
+      owner
+    };
+
+    let mut vec = vec![];
+    str_to_canonical_vec(&owner, &mut vec).map_err(|_| BadRrSet)?;
+    vec
+  };
+
+  /* Records in canonical order, without duplicates */
+  let mut r_datas = rrset.iter()
+    .map(|record| {
+      let mut vec = vec![];
+      record.r_data.write_canonical_to(&mut vec).map(|_| vec)
+    })
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|_| BadRrSet)?;
+  r_datas.sort_unstable();
+  r_datas.dedup();
+
+  for r_data in r_datas {
+    let rd_length = u16::try_from(r_data.len()).map_err(|_| BadRrSet)?;
+
+    data.extend_from_slice(&owner);
+    data.extend_from_slice(&u16::from(&first.type_).to_be_bytes());
+    data.extend_from_slice(&u16::from(&first.class).to_be_bytes());
+    data.extend_from_slice(&original_ttl.to_be_bytes());
+    data.extend_from_slice(&rd_length.to_be_bytes());
+    data.extend_from_slice(&r_data);
+  }
+  Ok(data)
+}
+
+/// Whether signatures of `algorithm` can be verified.
+pub fn is_algorithm_supported(algorithm: DnsQueryDnsSecAlgorithm) -> bool {
+  use DnsQueryDnsSecAlgorithm::*;
+
+  matches!(algorithm, RsaSha1 | RsaSha1NSec3Sha1 | RsaSha256 | RsaSha512
+    | EcdsaP256Sha256 | EcdsaP384Sha384 | Ed25519)
+}
+
+/// Whether DS digests of `digest_type` can be computed.
+pub fn is_digest_type_supported(digest_type: DnsQueryDsDigestType) -> bool {
+  use DnsQueryDsDigestType::*;
+
+  matches!(digest_type, Sha1 | Sha256 | Sha384)
+}
+
+/// Whether `ds` is the digest of `dnskey`, see RFC 4034 §5.1.4.
+pub fn ds_matches(ds: &DnsQueryResourceRecord, dnskey: &DnsQueryResourceRecord) -> bool {
+  /* KEY and CDNSKEY records share the DNSKEY r_data */
+  if ds.type_ != DnsQueryType::Ds || dnskey.type_ != DnsQueryType::DnsKey {
+    return false;
+  }
+  let (key_tag, algorithm, digest_type, ds_digest) = match &ds.r_data {
+    DnsQueryResourceRecordRDataType::Ds { key_tag, algorithm, digest_type, digest } => {
+      (*key_tag, *algorithm, *digest_type, digest)
+    }
+    _ => return false,
+  };
+  let key_algorithm = match &dnskey.r_data {
+    DnsQueryResourceRecordRDataType::DnsKey { algorithm, .. } => *algorithm,
+    _ => return false,
+  };
+  if !name_eq(&ds.name, &dnskey.name) || algorithm != key_algorithm
+    || dnskey.r_data.key_tag() != Some(key_tag) {
+    return false;
+  }
+
+  let digest_algorithm = match digest_type {
+    DnsQueryDsDigestType::Sha1 => &digest::SHA1_FOR_LEGACY_USE_ONLY,
+    DnsQueryDsDigestType::Sha256 => &digest::SHA256,
+    DnsQueryDsDigestType::Sha384 => &digest::SHA384,
+    _ => return false,
+  };
+
+  let mut data = vec![];
+  if str_to_canonical_vec(&dnskey.name, &mut data).is_err()
+    || dnskey.r_data.write_canonical_to(&mut data).is_err() {
+    return false;
+  }
+  digest::digest(digest_algorithm, &data).as_ref() == ds_digest.as_slice()
+}
+
+/// Whether `dnskey` may have made `rrsig`, see RFC 4035 §5.3.1.
+///
+/// Revoked keys sign nothing but their own DNSKEY RRset, which must not be
+/// trusted for validation either, see RFC 5011 §2.1.
+fn key_matches(rrsig: &DnsQueryResourceRecord, dnskey: &DnsQueryResourceRecord) -> bool {
+  /* SIG, KEY and CDNSKEY records share the RRSIG and DNSKEY r_data */
+  if rrsig.type_ != DnsQueryType::RrSig || dnskey.type_ != DnsQueryType::DnsKey {
+    return false;
+  }
+
+  match (&rrsig.r_data, &dnskey.r_data) {
+    (DnsQueryResourceRecordRDataType::RrSig { algorithm, key_tag, signer_name, .. },
+      DnsQueryResourceRecordRDataType::DnsKey {
+        flags, protocol, algorithm: key_algorithm, public_key,
+      }) => {
+      name_eq(&dnskey.name, signer_name)
+        && flags & DNSKEY_FLAG_ZONE != 0
+        && flags & DNSKEY_FLAG_REVOKE == 0
+        && *protocol == 3
+        && algorithm == key_algorithm
+        && *key_tag == dnskey_key_tag(*flags, *protocol, *key_algorithm, public_key)
+    }
+    _ => false,
+  }
+}
+
+/// Verify the signature of `rrsig` over `data` with the public key of
+/// `dnskey`.
+pub(crate) fn verify_signature(rrsig: &DnsQueryResourceRecord,
+                               dnskey: &DnsQueryResourceRecord,
+                               data: &[u8]) -> Result<(), DnsQueryDnsSecError> {
+  use DnsQueryDnsSecAlgorithm::*;
+  use DnsQueryDnsSecError::{BadKey, BadSignature, UnsupportedAlgorithm};
+
+  let (algorithm, sig) = match &rrsig.r_data {
+    DnsQueryResourceRecordRDataType::RrSig { algorithm, signature, .. } => {
+      (*algorithm, signature)
+    }
+    _ => return Err(DnsQueryDnsSecError::RrSigMismatch),
+  };
+  let key = match &dnskey.r_data {
+    DnsQueryResourceRecordRDataType::DnsKey { public_key, .. } => public_key,
+    _ => return Err(BadKey),
+  };
+
+  /* RSA public key format, see RFC 3110 §2 */
+  let rsa = |params: &'static signature::RsaParameters| {
+    let (e_len, rest) = match key.as_slice() {
+      [0, high, low, rest @ ..] => (usize::from(u16::from_be_bytes([*high, *low])), rest),
+      [len, rest @ ..] if *len != 0 => (usize::from(*len), rest),
+      _ => return Err(BadKey),
+    };
+    if e_len == 0 || rest.len() <= e_len {
+      return Err(BadKey);
+    }
+    let (e, n) = rest.split_at(e_len);
+
+    signature::RsaPublicKeyComponents { n, e }
+      .verify(params, data, sig)
+      .map_err(|_| BadSignature)
+  };
+
+  /* ECDSA public key format, see RFC 6605 §4 */
+  let ecdsa = |params: &'static signature::EcdsaVerificationAlgorithm, len: usize| {
+    if key.len() != len {
+      return Err(BadKey);
+    }
+    let mut point = Vec::with_capacity(1 + len);
+    point.push(0x04);
+    point.extend_from_slice(key);
+
+    signature::UnparsedPublicKey::new(params, point)
+      .verify(data, sig)
+      .map_err(|_| BadSignature)
+  };
+
+  match algorithm {
+    RsaSha1 | RsaSha1NSec3Sha1 => rsa(&signature::RSA_PKCS1_1024_8192_SHA1_FOR_LEGACY_USE_ONLY),
+    RsaSha256 => rsa(&signature::RSA_PKCS1_1024_8192_SHA256_FOR_LEGACY_USE_ONLY),
+    RsaSha512 => rsa(&signature::RSA_PKCS1_1024_8192_SHA512_FOR_LEGACY_USE_ONLY),
+    EcdsaP256Sha256 => ecdsa(&signature::ECDSA_P256_SHA256_FIXED, 64),
+    EcdsaP384Sha384 => ecdsa(&signature::ECDSA_P384_SHA384_FIXED, 96),
+    /* Ed25519 public key format, see RFC 8080 §3 */
+    Ed25519 => {
+      if key.len() != 32 {
+        return Err(BadKey);
+      }
+      signature::UnparsedPublicKey::new(&signature::ED25519, key)
+        .verify(data, sig)
+        .map_err(|_| BadSignature)
+    }
+    _ => Err(UnsupportedAlgorithm(algorithm)),
+  }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use super::*;
+  use crate::dns_query::dns_query_sig0::DnsQuerySig0Key;
+  use crate::dns_query::utils::DnsQueryClass;
+
+  pub(crate) const NOW: u32 = 1_700_000_000;
+
+  pub(crate) fn record(name: &str, type_: DnsQueryType, r_data: &str) -> DnsQueryResourceRecord {
+    DnsQueryResourceRecord {
+      name: name.to_owned(),
+      type_,
+      class: DnsQueryClass::In,
+      ttl: 86400,
+      rd_length: 0,
+      r_data: DnsQueryResourceRecordRDataType::from_presentation(r_data, type_, DnsQueryClass::In)
+        .unwrap(),
+    }
+  }
+
+  /// Zone signing key of `zone` with `algorithm`.
+  pub(crate) fn zone_key(zone: &str, algorithm: DnsQueryDnsSecAlgorithm) -> DnsQuerySig0Key {
+    let pkcs8 = match algorithm {
+      DnsQueryDnsSecAlgorithm::RsaSha256 | DnsQueryDnsSecAlgorithm::RsaSha512 => {
+        include_bytes!("testdata/rsa2048.pk8").to_vec()
+      }
+      _ => DnsQuerySig0Key::generate_pkcs8(algorithm).unwrap(),
+    };
+    DnsQuerySig0Key::from_pkcs8(zone, algorithm, &pkcs8).unwrap()
+  }
+
+  /// DNSKEY of `key`, with the zone and SEP flags.
+  pub(crate) fn dnskey_of(key: &DnsQuerySig0Key) -> DnsQueryResourceRecord {
+    DnsQueryResourceRecord {
+      name: key.name().to_owned(),
+      type_: DnsQueryType::DnsKey,
+      class: DnsQueryClass::In,
+      ttl: 3600,
+      rd_length: 0,
+      r_data: DnsQueryResourceRecordRDataType::DnsKey {
+        flags: 257,
+        protocol: 3,
+        algorithm: key.algorithm(),
+        public_key: key.public_key().to_vec(),
+      },
+    }
+  }
+
+  /// RRSIG of `rrset` made with `key`, valid for a day either side of
+  /// [`NOW`].
+  pub(crate) fn sign(rrset: &[&DnsQueryResourceRecord], key: &DnsQuerySig0Key
+  ) -> DnsQueryResourceRecord {
+    let owner = &rrset[0].name;
+    let labels = name_labels(owner);
+    let labels = labels.len() - if labels.first() == Some(&"*") { 1 } else { 0 };
+
+    let mut rrsig = DnsQueryResourceRecord {
+      name: owner.clone(),
+      type_: DnsQueryType::RrSig,
+      class: DnsQueryClass::In,
+      ttl: 3600,
+      rd_length: 0,
+      r_data: DnsQueryResourceRecordRDataType::RrSig {
+        type_covered: rrset[0].type_,
+        algorithm: key.algorithm(),
+        labels: u8::try_from(labels).unwrap(),
+        original_ttl: 3600,
+        sig_expiration: NOW + 86400,
+        sig_inception: NOW - 86400,
+        key_tag: dnskey_of(key).r_data.key_tag().unwrap(),
+        signer_name: key.name().to_owned(),
+        signature: vec![],
+      },
+    };
+    let data = signed_data(rrset, &rrsig, NOW).unwrap();
+    if let DnsQueryResourceRecordRDataType::RrSig { signature, .. } = &mut rrsig.r_data {
+      *signature = key.sign(&data).unwrap();
+    }
+    rrsig
+  }
+
+  /// DNSKEY and DS of RFC 4034 §5.4.
+  fn rfc4034_dnskey_and_ds() -> (DnsQueryResourceRecord, DnsQueryResourceRecord) {
+    let dnskey = record("dskey.example.com", DnsQueryType::DnsKey, "256 3 5 \
+      AQOeiiR0GOMYkDshWoSKz9XzfwJr1AYtsmx3TGkJaNXVbfi/2pHm822aJ5iI9BMzNXxeYCmZ\
+      DRD99WYwYqUSdjMmmAphXdvxegXd/M5+X7OrzKBaMbCVdFLUUh6DhweJBjEVv5f2wwjM9Xzc\
+      nOf+EPbtG9DMBmADjFDc2w/rljwvFw==");
+    let ds = record("dskey.example.com", DnsQueryType::Ds,
+                    "60485 5 1 2BB183AF5F22588179A53B0A98631FAD1A292118");
+    (dnskey, ds)
+  }
+
+  #[test]
+  fn ds_digest_rfc4034() {
+    let (dnskey, ds) = rfc4034_dnskey_and_ds();
+    assert_eq!(dnskey.r_data.key_tag(), Some(60485));
+    assert!(ds_matches(&ds, &dnskey));
+
+    /* SHA-256 digest of RFC 4509 §2.3 */
+    let ds_sha256 = record("dskey.example.com", DnsQueryType::Ds, "60485 5 2 \
+      D4B7D520E7BB5F0F67674A0CCEB1E3E0614B93C4F9E99B8383F6A1E4469DA50A");
+    assert!(ds_matches(&ds_sha256, &dnskey));
+
+    let mut bad = ds.clone();
+    if let DnsQueryResourceRecordRDataType::Ds { digest, .. } = &mut bad.r_data {
+      digest[0] ^= 1;
+    }
+    assert!(!ds_matches(&bad, &dnskey));
+
+    /* KEY and CDNSKEY records share the r_data, but DS only digests DNSKEYs */
+    for type_ in [DnsQueryType::Key, DnsQueryType::CDnsKey] {
+      let mut key = dnskey.clone();
+      key.type_ = type_;
+      assert!(!ds_matches(&ds, &key));
+    }
+  }
+
+  #[test]
+  fn sig_is_no_rrsig() {
+    let (dnskey, _) = rfc4034_dnskey_and_ds();
+    let a = record("dskey.example.com", DnsQueryType::A, "192.0.2.1");
+    let mut rrsig = record("dskey.example.com", DnsQueryType::RrSig,
+                           "A 5 3 86400 20300101000000 20000101000000 60485 \
+                           dskey.example.com AAAA");
+
+    assert_eq!(verify_rrset_with_key_at(&[&a], &rrsig, &dnskey, NOW),
+               Err(DnsQueryDnsSecError::BadSignature));
+    rrsig.type_ = DnsQueryType::Sig;
+    assert_eq!(verify_rrset_with_key_at(&[&a], &rrsig, &dnskey, NOW),
+               Err(DnsQueryDnsSecError::RrSigMismatch));
+  }
+
+  #[test]
+  fn verify_signed_rrsets() {
+    use DnsQueryDnsSecAlgorithm::*;
+
+    for algorithm in [RsaSha256, RsaSha512, EcdsaP256Sha256, EcdsaP384Sha384, Ed25519] {
+      let key = zone_key("example.com", algorithm);
+      let dnskey = dnskey_of(&key);
+      let a = record("www.example.com", DnsQueryType::A, "192.0.2.1");
+      let b = record("www.example.com", DnsQueryType::A, "192.0.2.2");
+      let rrsig = sign(&[&a, &b], &key);
+
+      /* Order, case and TTLs of the RRset do not matter */
+      let mut upper = b.clone();
+      upper.name = "WWW.Example.COM".to_owned();
+      upper.ttl = 60;
+      let verified = verify_rrset_at(&[&upper, &a], &rrsig, &[&dnskey], NOW);
+      assert_eq!(verified.map(|key| key.r_data.key_tag()), Ok(dnskey.r_data.key_tag()),
+                 "{:?}", algorithm);
+
+      let mut tampered = b.clone();
+      tampered.r_data = record("www.example.com", DnsQueryType::A, "192.0.2.3").r_data;
+      assert_eq!(verify_rrset_with_key_at(&[&a, &tampered], &rrsig, &dnskey, NOW),
+                 Err(DnsQueryDnsSecError::BadSignature), "{:?}", algorithm);
+    }
+  }
+
+  #[test]
+  fn verify_failures() {
+    use DnsQueryDnsSecError::*;
+
+    let key = zone_key("example.com", DnsQueryDnsSecAlgorithm::Ed25519);
+    let dnskey = dnskey_of(&key);
+    let a = record("www.example.com", DnsQueryType::A, "192.0.2.1");
+    let rrsig = sign(&[&a], &key);
+    let verify = |rrset: &[&DnsQueryResourceRecord], rrsig, dnskey, now| {
+      verify_rrset_at(rrset, rrsig, &[dnskey], now).map(|_| ())
+    };
+    assert_eq!(verify(&[&a], &rrsig, &dnskey, NOW), Ok(()));
+
+    /* Validity period */
+    assert_eq!(verify(&[&a], &rrsig, &dnskey, NOW + 86401), Err(Expired));
+    assert_eq!(verify(&[&a], &rrsig, &dnskey, NOW - 86401), Err(NotYetValid));
+
+    /* Another key, or the same without the zone flag */
+    let other = dnskey_of(&zone_key("example.com", DnsQueryDnsSecAlgorithm::Ed25519));
+    assert_eq!(verify(&[&a], &rrsig, &other, NOW), Err(NoMatchingKey));
+    let mut no_zone = dnskey.clone();
+    if let DnsQueryResourceRecordRDataType::DnsKey { flags, .. } = &mut no_zone.r_data {
+      *flags = 1;
+    }
+    assert_eq!(verify(&[&a], &rrsig, &no_zone, NOW), Err(NoMatchingKey));
+
+    /* A revoked key, even with a signature made for its new key tag */
+    let mut revoked = dnskey.clone();
+    if let DnsQueryResourceRecordRDataType::DnsKey { flags, .. } = &mut revoked.r_data {
+      *flags |= DNSKEY_FLAG_REVOKE;
+    }
+    let mut revoked_rrsig = rrsig.clone();
+    if let DnsQueryResourceRecordRDataType::RrSig { key_tag, .. } = &mut revoked_rrsig.r_data {
+      *key_tag = revoked.r_data.key_tag().unwrap();
+    }
+    let data = signed_data(&[&a], &revoked_rrsig, NOW).unwrap();
+    if let DnsQueryResourceRecordRDataType::RrSig { signature, .. } = &mut revoked_rrsig.r_data {
+      *signature = key.sign(&data).unwrap();
+    }
+    assert_eq!(verify(&[&a], &revoked_rrsig, &revoked, NOW), Err(NoMatchingKey));
+
+    /* Another RRset */
+    let mut other_name = a.clone();
+    other_name.name = "mail.example.com".to_owned();
+    assert_eq!(verify(&[&other_name], &rrsig, &dnskey, NOW), Err(RrSigMismatch));
+    let aaaa = record("www.example.com", DnsQueryType::Aaaa, "2001:db8::1");
+    assert_eq!(verify(&[&aaaa], &rrsig, &dnskey, NOW), Err(RrSigMismatch));
+    assert_eq!(verify(&[&a, &aaaa], &rrsig, &dnskey, NOW), Err(BadRrSet));
+    assert_eq!(verify(&[], &rrsig, &dnskey, NOW), Err(BadRrSet));
+
+    /* A signer that is no ancestor of the RRset */
+    let mut outside = a.clone();
+    outside.name = "www.example.net".to_owned();
+    let mut rrsig = rrsig.clone();
+    rrsig.name = outside.name.clone();
+    assert_eq!(verify(&[&outside], &rrsig, &dnskey, NOW), Err(BadSignerName));
+  }
+
+  #[test]
+  fn verify_wildcard_expansion() {
+    let key = zone_key("example.com", DnsQueryDnsSecAlgorithm::EcdsaP256Sha256);
+    let dnskey = dnskey_of(&key);
+    let wildcard = record("*.example.com", DnsQueryType::A, "192.0.2.1");
+    let rrsig = sign(&[&wildcard], &key);
+
+    for name in ["*.example.com", "www.example.com", "a.b.example.com"] {
+      let mut a = wildcard.clone();
+      a.name = name.to_owned();
+      let mut rrsig = rrsig.clone();
+      rrsig.name = name.to_owned();
+      assert!(verify_rrset_at(&[&a], &rrsig, &[&dnskey], NOW).is_ok(), "{}", name);
+    }
+
+    /* Fewer labels than the RRSIG */
+    let mut a = wildcard.clone();
+    a.name = "com".to_owned();
+    let mut rrsig = rrsig.clone();
+    rrsig.name = a.name.clone();
+    assert_eq!(verify_rrset_at(&[&a], &rrsig, &[&dnskey], NOW).map(|_| ()),
+               Err(DnsQueryDnsSecError::RrSigMismatch));
+  }
+}
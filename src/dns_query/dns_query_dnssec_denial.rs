@@ -0,0 +1,556 @@
+use crate::dns_query::dns_query_2_resource_record::DnsQueryResourceRecord;
+use crate::dns_query::dns_query_2_resource_record_rdata::DnsQueryResourceRecordRDataType;
+use crate::dns_query::dns_query_2_resource_record_dnssec::NSEC3_FLAG_OPT_OUT;
+use crate::dns_query::dns_query_dnssec::{name_cmp, name_eq, name_labels, is_subdomain};
+use crate::dns_query::dns_query_dnssec_validator::DnsQueryDnsSecStatus;
+use crate::dns_query::utils::{str_to_canonical_vec, DnsQueryType};
+use data_encoding::BASE32HEX_NOPAD;
+use ring::digest;
+use std::cmp::Ordering;
+
+/*
+ *  Reference:
+ *  - [RFC 4035](https://tools.ietf.org/html/rfc4035) §5.4
+ *  - [RFC 5155](https://tools.ietf.org/html/rfc5155) §8
+ *  - [RFC 7129](https://tools.ietf.org/html/rfc7129)
+ *  - [RFC 9276](https://tools.ietf.org/html/rfc9276) §3.2
+ */
+
+/// Limits on NSEC3 iterations, above which proofs are not trusted.
+///
+/// RFC 9276 lets validators treat responses with costly NSEC3 parameters as
+/// insecure, or as bogus, instead of hashing names that many times.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DnsQueryNSec3Limits {
+  /// Proofs with more iterations are insecure
+  pub insecure_above: u16,
+  /// Proofs with more iterations are bogus
+  pub bogus_above: u16,
+}
+
+impl Default for DnsQueryNSec3Limits {
+  fn default() -> Self {
+    Self { insecure_above: 100, bogus_above: 150 }
+  }
+}
+
+/// NSEC3 hash of `name`, see RFC 5155 §5, or `None` for an unknown
+/// `hash_algorithm`.
+pub fn nsec3_hash(name: &str, hash_algorithm: u8, salt: &[u8], iterations: u16
+) -> Option<Vec<u8>> {
+  /* Only SHA-1 is defined */
+  if hash_algorithm != 1 {
+    return None;
+  }
+
+  let mut hash = vec![];
+  str_to_canonical_vec(name, &mut hash).ok()?;
+  for _ in 0..=iterations {
+    let mut ctx = digest::Context::new(&digest::SHA1_FOR_LEGACY_USE_ONLY);
+    ctx.update(&hash);
+    ctx.update(salt);
+    hash = ctx.finish().as_ref().to_vec();
+  }
+  Some(hash)
+}
+
+/// Check that NSEC or NSEC3 `records` prove that `q_name` has no `q_type`
+/// records, or does not exist at all when `nx_domain` is set.
+///
+/// `records` must already be validated. Proofs relying on an opt-out span
+/// or on too many NSEC3 iterations are insecure.
+pub fn prove_nonexistence(records: &[DnsQueryResourceRecord], q_name: &str,
+                          q_type: DnsQueryType, nx_domain: bool,
+                          limits: &DnsQueryNSec3Limits) -> DnsQueryDnsSecStatus {
+  let result = match NSec3s::new(records, limits) {
+    Some(nsec3s) => nsec3s.and_then(|nsec3s| nsec3s.nonexistence(q_name, q_type, nx_domain)),
+    None => NSecs::new(records).nonexistence(q_name, q_type, nx_domain),
+  };
+  result.err().unwrap_or(DnsQueryDnsSecStatus::Secure)
+}
+
+/// Check that NSEC or NSEC3 `records` prove that no name closer to `q_name`
+/// than the wildcard expanded into an answer exists, see RFC 4035 §5.3.4 and
+/// RFC 5155 §8.8.
+///
+/// `labels` is the labels field of the RRSIG of the answer.
+pub fn prove_wildcard_answer(records: &[DnsQueryResourceRecord], q_name: &str, labels: u8,
+                             limits: &DnsQueryNSec3Limits) -> DnsQueryDnsSecStatus {
+  let q_labels = name_labels(q_name);
+  let labels = labels as usize;
+  if labels >= q_labels.len() {
+    return DnsQueryDnsSecStatus::Bogus(format!("{} is not a wildcard expansion", q_name));
+  }
+  let next_closer = q_labels[q_labels.len() - labels - 1..].join(".");
+
+  let result = match NSec3s::new(records, limits) {
+    Some(nsec3s) => nsec3s.and_then(|nsec3s| {
+      let cover = nsec3s.covering(&next_closer)
+        .ok_or_else(|| not_proven(&next_closer, "nonexistence"))?;
+      nsec3s.opt_out(cover)
+    }),
+    None => NSecs::new(records).covering(q_name)
+      .map(|_| ())
+      .ok_or_else(|| not_proven(q_name, "nonexistence")),
+  };
+  result.err().unwrap_or(DnsQueryDnsSecStatus::Secure)
+}
+
+/// What NSEC or NSEC3 records prove about a name without DS records.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum DnsQueryNoDs {
+  /// The name exists, and is no zone cut
+  NoCut,
+  /// The name is a delegation without DS, or may be one under opt-out
+  Unsigned,
+  /// The name does not exist
+  NoName,
+}
+
+/// Check that NSEC or NSEC3 `records` prove that `name` has no DS records,
+/// and tell why, see RFC 4035 §5.2 and RFC 5155 §8.6.
+pub(crate) fn prove_no_ds(records: &[DnsQueryResourceRecord], name: &str,
+                          limits: &DnsQueryNSec3Limits
+) -> Result<DnsQueryNoDs, DnsQueryDnsSecStatus> {
+  let no_ds = |types: &[DnsQueryType]| {
+    if types.contains(&DnsQueryType::Ds) {
+      Err(DnsQueryDnsSecStatus::Bogus(format!("{} DS withheld", name)))
+    } else if is_delegation(types) {
+      Ok(DnsQueryNoDs::Unsigned)
+    } else {
+      Ok(DnsQueryNoDs::NoCut)
+    }
+  };
+
+  match NSec3s::new(records, limits) {
+    Some(nsec3s) => {
+      let nsec3s = nsec3s?;
+      if let Some(nsec3) = nsec3s.matching(name) {
+        return no_ds(nsec3.types);
+      }
+      let (_, cover) = nsec3s.closest_encloser(name)?;
+      Ok(if cover.flags & NSEC3_FLAG_OPT_OUT != 0 {
+        DnsQueryNoDs::Unsigned
+      } else {
+        DnsQueryNoDs::NoName
+      })
+    }
+    None => {
+      let nsecs = NSecs::new(records);
+      if let Some(nsec) = nsecs.matching(name) {
+        return no_ds(nsec.types);
+      }
+      if nsecs.nonexistence(name, DnsQueryType::Ds, false).is_ok() {
+        return Ok(DnsQueryNoDs::NoCut);
+      }
+      nsecs.nonexistence(name, DnsQueryType::Ds, true)?;
+      Ok(DnsQueryNoDs::NoName)
+    }
+  }
+}
+
+/// Bogus status for a missing proof of `what` about `name`.
+fn not_proven(name: &str, what: &str) -> DnsQueryDnsSecStatus {
+  DnsQueryDnsSecStatus::Bogus(format!("{} of {} not proven", what, name))
+}
+
+/// Whether `types` are those of a delegation point, seen from the parent.
+fn is_delegation(types: &[DnsQueryType]) -> bool {
+  types.contains(&DnsQueryType::Ns) && !types.contains(&DnsQueryType::Soa)
+}
+
+/// Whether `types` prove that a name has no `q_type`, nor a CNAME instead.
+fn lacks_type(types: &[DnsQueryType], q_type: DnsQueryType) -> bool {
+  !types.contains(&q_type) && !types.contains(&DnsQueryType::CName)
+    /* NSEC and NSEC3 from the parent side of a delegation only prove DS */
+    && (q_type == DnsQueryType::Ds || !is_delegation(types))
+}
+
+/// An NSEC record.
+struct NSec<'a> {
+  owner: &'a str,
+  next: &'a str,
+  types: &'a [DnsQueryType],
+}
+
+/// The NSEC records of a response.
+struct NSecs<'a>(Vec<NSec<'a>>);
+
+impl<'a> NSecs<'a> {
+  fn new(records: &'a [DnsQueryResourceRecord]) -> Self {
+    Self(records.iter()
+      .filter_map(|record| match &record.r_data {
+        DnsQueryResourceRecordRDataType::NSec { next_domain_name, type_bit_maps } => {
+          Some(NSec { owner: &record.name, next: next_domain_name, types: type_bit_maps })
+        }
+        _ => None,
+      })
+      .collect())
+  }
+
+  /// NSEC owned by `name`.
+  fn matching(&self, name: &str) -> Option<&NSec<'a>> {
+    self.0.iter().find(|nsec| name_eq(nsec.owner, name))
+  }
+
+  /// NSEC whose span covers `name`, and may be used to deny it.
+  fn covering(&self, name: &str) -> Option<&NSec<'a>> {
+    self.0.iter().find(|nsec| {
+      let after_owner = name_cmp(nsec.owner, name) == Ordering::Less;
+      let before_next = if name_cmp(nsec.owner, nsec.next) == Ordering::Less {
+        name_cmp(name, nsec.next) == Ordering::Less
+      } else {
+        /* The last NSEC of a zone points back to its apex */
+        is_subdomain(name, nsec.next)
+      };
+
+      /* Names below a delegation or DNAME are not in this zone */
+      let below_cut = is_subdomain(name, nsec.owner)
+        && (is_delegation(nsec.types) || nsec.types.contains(&DnsQueryType::DName));
+
+      after_owner && before_next && !below_cut
+    })
+  }
+
+  fn nonexistence(&self, q_name: &str, q_type: DnsQueryType, nx_domain: bool
+  ) -> Result<(), DnsQueryDnsSecStatus> {
+    /* NODATA */
+    if let Some(nsec) = self.matching(q_name) {
+      return if !nx_domain && lacks_type(nsec.types, q_type) {
+        Ok(())
+      } else {
+        Err(not_proven(q_name, "nonexistence"))
+      };
+    }
+
+    let cover = self.covering(q_name).ok_or_else(|| not_proven(q_name, "nonexistence"))?;
+
+    /* Names below `q_name` make it an empty non-terminal, which exists */
+    if is_subdomain(cover.owner, q_name) || is_subdomain(cover.next, q_name) {
+      return if nx_domain {
+        Err(not_proven(q_name, "nonexistence"))
+      } else {
+        Ok(())
+      };
+    }
+
+    /* The closest encloser is the longest ancestor shared with the span */
+    let closest_encloser = [cover.owner, cover.next].iter()
+      .map(|name| common_ancestor(q_name, name))
+      .max_by_key(|name| name_labels(name).len())
+      .unwrap_or_default();
+    if name_labels(&closest_encloser).len() >= name_labels(q_name).len() {
+      return Err(not_proven(q_name, "closest encloser"));
+    }
+    let wildcard = format!("*.{}", closest_encloser);
+
+    if nx_domain {
+      self.covering(&wildcard).map(|_| ()).ok_or_else(|| not_proven(&wildcard, "nonexistence"))
+    } else {
+      /* NODATA of a wildcard */
+      match self.matching(&wildcard) {
+        Some(nsec) if lacks_type(nsec.types, q_type) => Ok(()),
+        _ => Err(not_proven(q_name, "nonexistence")),
+      }
+    }
+  }
+}
+
+/// Longest common ancestor of `a` and `b`.
+fn common_ancestor(a: &str, b: &str) -> String {
+  let a = name_labels(a);
+  let b = name_labels(b);
+
+  let len = a.iter().rev().zip(b.iter().rev())
+    .take_while(|(a, b)| a.eq_ignore_ascii_case(b))
+    .count();
+  a[a.len() - len..].join(".")
+}
+
+/// An NSEC3 record.
+struct NSec3<'a> {
+  hash: Vec<u8>,
+  zone: String,
+  next: &'a [u8],
+  flags: u8,
+  types: &'a [DnsQueryType],
+}
+
+/// The NSEC3 records of a response, sharing the same parameters.
+struct NSec3s<'a> {
+  nsec3s: Vec<NSec3<'a>>,
+  hash_algorithm: u8,
+  salt: &'a [u8],
+  iterations: u16,
+}
+
+impl<'a> NSec3s<'a> {
+  /// `None` without NSEC3 records, and an error when they are not usable.
+  fn new(records: &'a [DnsQueryResourceRecord], limits: &DnsQueryNSec3Limits
+  ) -> Option<Result<Self, DnsQueryDnsSecStatus>> {
+    let mut params = None;
+    let mut nsec3s = vec![];
+
+    for record in records {
+      if let DnsQueryResourceRecordRDataType::NSec3 {
+        hash_algorithm, flags, iterations, salt, next_hashed_owner_name, type_bit_maps,
+      } = &record.r_data {
+        /* Proofs only use NSEC3 records with the parameters of the first one */
+        let (first_algorithm, first_iterations, first_salt) =
+          *params.get_or_insert((*hash_algorithm, *iterations, salt.as_slice()));
+        if (*hash_algorithm, *iterations, salt.as_slice())
+          != (first_algorithm, first_iterations, first_salt) {
+          continue;
+        }
+
+        let mut labels = record.name.splitn(2, '.');
+        let hash = labels.next()
+          .and_then(|label| BASE32HEX_NOPAD.decode(label.to_ascii_uppercase().as_bytes()).ok());
+        let zone = labels.next().unwrap_or_default().to_owned();
+        if let Some(hash) = hash {
+          nsec3s.push(NSec3 {
+            hash, zone, next: next_hashed_owner_name, flags: *flags, types: type_bit_maps,
+          });
+        }
+      }
+    }
+
+    let (hash_algorithm, iterations, salt) = params?;
+    Some(if hash_algorithm != 1 {
+      Err(DnsQueryDnsSecStatus::Insecure(
+        format!("unknown NSEC3 hash algorithm {}", hash_algorithm)))
+    } else if iterations > limits.bogus_above {
+      Err(DnsQueryDnsSecStatus::Bogus(format!("{} NSEC3 iterations", iterations)))
+    } else if iterations > limits.insecure_above {
+      Err(DnsQueryDnsSecStatus::Insecure(format!("{} NSEC3 iterations", iterations)))
+    } else {
+      Ok(Self { nsec3s, hash_algorithm, salt, iterations })
+    })
+  }
+
+  fn hash(&self, name: &str) -> Vec<u8> {
+    nsec3_hash(name, self.hash_algorithm, self.salt, self.iterations).unwrap_or_default()
+  }
+
+  /// NSEC3 whose hashed owner name is the hash of `name`.
+  fn matching(&self, name: &str) -> Option<&NSec3<'a>> {
+    let hash = self.hash(name);
+    self.nsec3s.iter()
+      .find(|nsec3| nsec3.hash == hash && is_subdomain(name, &nsec3.zone))
+  }
+
+  /// NSEC3 whose span covers the hash of `name`.
+  fn covering(&self, name: &str) -> Option<&NSec3<'a>> {
+    let hash = self.hash(name);
+    self.nsec3s.iter().find(|nsec3| {
+      let in_span = if nsec3.hash.as_slice() < nsec3.next {
+        nsec3.hash < hash && hash.as_slice() < nsec3.next
+      } else {
+        /* The last NSEC3 of a zone wraps around */
+        nsec3.hash < hash || hash.as_slice() < nsec3.next
+      };
+      in_span && is_subdomain(name, &nsec3.zone)
+    })
+  }
+
+  /// Insecure status if `cover` is an opt-out span, see RFC 5155 §9.2.
+  fn opt_out(&self, cover: &NSec3<'a>) -> Result<(), DnsQueryDnsSecStatus> {
+    if cover.flags & NSEC3_FLAG_OPT_OUT != 0 {
+      Err(DnsQueryDnsSecStatus::Insecure("NSEC3 opt-out span".to_owned()))
+    } else {
+      Ok(())
+    }
+  }
+
+  /// Closest encloser of `name`, a strict ancestor of it, along with the
+  /// NSEC3 covering the next closer name, see RFC 5155 §8.3.
+  fn closest_encloser(&self, name: &str) -> Result<(String, &NSec3<'a>), DnsQueryDnsSecStatus> {
+    let labels = name_labels(name);
+
+    for i in 1..=labels.len() {
+      let encloser = labels[i..].join(".");
+      let matching = match self.matching(&encloser) {
+        Some(matching) => matching,
+        None => continue,
+      };
+
+      /* Names below a delegation or DNAME are not in this zone */
+      if is_delegation(matching.types) || matching.types.contains(&DnsQueryType::DName) {
+        break;
+      }
+
+      let next_closer = labels[i - 1..].join(".");
+      return match self.covering(&next_closer) {
+        Some(cover) => Ok((encloser, cover)),
+        None => Err(not_proven(&next_closer, "nonexistence")),
+      };
+    }
+    Err(not_proven(name, "closest encloser"))
+  }
+
+  fn nonexistence(&self, q_name: &str, q_type: DnsQueryType, nx_domain: bool
+  ) -> Result<(), DnsQueryDnsSecStatus> {
+    /* NODATA */
+    if let Some(nsec3) = self.matching(q_name) {
+      return if !nx_domain && lacks_type(nsec3.types, q_type) {
+        Ok(())
+      } else {
+        Err(not_proven(q_name, "nonexistence"))
+      };
+    }
+
+    /* An empty non-terminal has a matching NSEC3, so `q_name` is not one */
+    let (closest_encloser, cover) = self.closest_encloser(q_name)?;
+    let wildcard = format!("*.{}", closest_encloser);
+
+    /* NODATA of DS at an opt-out delegation */
+    if !nx_domain && q_type == DnsQueryType::Ds {
+      return self.opt_out(cover).and(Err(not_proven(q_name, "nonexistence")));
+    }
+
+    if nx_domain {
+      self.covering(&wildcard).ok_or_else(|| not_proven(&wildcard, "nonexistence"))?;
+      self.opt_out(cover)
+    } else {
+      /* NODATA of a wildcard */
+      match self.matching(&wildcard) {
+        Some(nsec3) if lacks_type(nsec3.types, q_type) => Ok(()),
+        _ => Err(not_proven(q_name, "nonexistence")),
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::dns_query::utils::DnsQueryClass;
+  use data_encoding::HEXLOWER;
+
+  fn record(name: &str, type_: DnsQueryType, r_data: DnsQueryResourceRecordRDataType
+  ) -> DnsQueryResourceRecord {
+    DnsQueryResourceRecord {
+      name: name.to_owned(), type_, class: DnsQueryClass::In, ttl: 3600, rd_length: 0, r_data,
+    }
+  }
+
+  fn nsec(owner: &str, next: &str, types: &[DnsQueryType]) -> DnsQueryResourceRecord {
+    record(owner, DnsQueryType::NSec, DnsQueryResourceRecordRDataType::NSec {
+      next_domain_name: next.to_owned(), type_bit_maps: types.to_vec(),
+    })
+  }
+
+  const SALT: [u8; 4] = [0xaa, 0xbb, 0xcc, 0xdd];
+
+  /// NSEC3 chain of `zone`, with the names and types of `names`.
+  fn nsec3_chain(zone: &str, names: &[(&str, &[DnsQueryType])]) -> Vec<DnsQueryResourceRecord> {
+    let mut hashes: Vec<_> = names.iter()
+      .map(|(name, types)| (nsec3_hash(name, 1, &SALT, 12).unwrap(), types.to_vec()))
+      .collect();
+    hashes.sort_by(|a, b| a.0.cmp(&b.0));
+
+    (0..hashes.len())
+      .map(|i| {
+        let (hash, types) = &hashes[i];
+        let next = &hashes[(i + 1) % hashes.len()].0;
+        let owner = format!("{}.{}", BASE32HEX_NOPAD.encode(hash).to_ascii_lowercase(), zone);
+        record(&owner, DnsQueryType::NSec3, DnsQueryResourceRecordRDataType::NSec3 {
+          hash_algorithm: 1, flags: 0, iterations: 12, salt: SALT.to_vec(),
+          next_hashed_owner_name: next.clone(), type_bit_maps: types.clone(),
+        })
+      })
+      .collect()
+  }
+
+  fn prove(records: &[DnsQueryResourceRecord], q_name: &str, q_type: DnsQueryType,
+           nx_domain: bool) -> DnsQueryDnsSecStatus {
+    prove_nonexistence(records, q_name, q_type, nx_domain, &DnsQueryNSec3Limits::default())
+  }
+
+  fn is_bogus(status: &DnsQueryDnsSecStatus) -> bool {
+    matches!(status, DnsQueryDnsSecStatus::Bogus(_))
+  }
+
+  #[test]
+  fn nsec3_hash_rfc5155() {
+    /* RFC 5155 Appendix A */
+    for (name, hash) in [
+      ("example", "0p9mhaveqvm6t7vbl5lop2u3t2rp3tom"),
+      ("a.example", "35mthgpgcu1qg68fab165klnsnk3dpvl"),
+      ("ai.example", "gjeqe526plbf1g8mklp59enfd789njgi"),
+      ("ns1.example", "2t7b4g4vsa5smi47k61mv5bv1a22bojr"),
+      ("ns2.example", "q04jkcevqvmu85r014c7dkba38o0ji5r"),
+      ("w.example", "k8udemvp1j2f7eg6jebps17vp3n8i58h"),
+      ("*.w.example", "r53bq7cc2uvmubfu5ocmm6pers9tk9en"),
+      ("x.w.example", "b4um86eghhds6nea196smvmlo4ors995"),
+      ("y.w.example", "ji6neoaepv8b5o6k4ev33abha8ht9fgc"),
+      ("x.y.w.example", "2vptu5timamqttgl4luu9kg21e0aor3s"),
+      ("xx.example", "t644ebqk9bibcna874givr6joj62mlhv"),
+    ] {
+      let expected = BASE32HEX_NOPAD.decode(hash.to_ascii_uppercase().as_bytes()).unwrap();
+      assert_eq!(nsec3_hash(name, 1, &SALT, 12), Some(expected), "{}", name);
+    }
+
+    /* Case does not matter, unknown algorithms do */
+    assert_eq!(nsec3_hash("A.EXAMPLE", 1, &SALT, 12), nsec3_hash("a.example", 1, &SALT, 12));
+    assert_eq!(nsec3_hash("example", 2, &SALT, 12), None);
+
+    /* Without salt nor extra iterations, the SHA-1 of the wire name */
+    assert_eq!(
+      nsec3_hash("example", 1, &[], 0).map(|hash| HEXLOWER.encode(&hash)),
+      Some("1db8efa7dcb348bda7893fca1d8badfdb6996b01".to_owned()),
+    );
+  }
+
+  #[test]
+  fn nsec_name_error() {
+    let records = [
+      nsec("example", "a.example", &[DnsQueryType::Soa, DnsQueryType::Ns]),
+      nsec("a.example", "c.example", &[DnsQueryType::A]),
+    ];
+    assert_eq!(prove(&records, "b.example", DnsQueryType::A, true), DnsQueryDnsSecStatus::Secure);
+    assert!(is_bogus(&prove(&records, "d.example", DnsQueryType::A, true)));
+    /* Without the NSEC covering the wildcard */
+    assert!(is_bogus(&prove(&records[1..], "b.example", DnsQueryType::A, true)));
+  }
+
+  #[test]
+  fn nsec_empty_non_terminal() {
+    /* b.example only exists as the parent of x.b.example */
+    let records = [
+      nsec("example", "a.example", &[DnsQueryType::Soa, DnsQueryType::Ns]),
+      nsec("a.example", "x.b.example", &[DnsQueryType::A]),
+    ];
+    assert!(is_bogus(&prove(&records, "b.example", DnsQueryType::A, true)));
+    assert_eq!(prove(&records, "b.example", DnsQueryType::A, false), DnsQueryDnsSecStatus::Secure);
+
+    /* The span still denies names next to b.example */
+    assert_eq!(prove(&records, "aa.example", DnsQueryType::A, true), DnsQueryDnsSecStatus::Secure);
+  }
+
+  #[test]
+  fn nsec3_name_error_and_empty_non_terminal() {
+    let names: [(&str, &[DnsQueryType]); 4] = [
+      ("example", &[DnsQueryType::Soa, DnsQueryType::Ns]),
+      ("a.example", &[DnsQueryType::A]),
+      ("b.example", &[]),
+      ("x.b.example", &[DnsQueryType::A]),
+    ];
+    let records = nsec3_chain("example", &names);
+
+    assert_eq!(prove(&records, "c.example", DnsQueryType::A, true), DnsQueryDnsSecStatus::Secure);
+    assert_eq!(prove(&records, "y.b.example", DnsQueryType::A, true), DnsQueryDnsSecStatus::Secure);
+    assert!(is_bogus(&prove(&records, "b.example", DnsQueryType::A, true)));
+    assert_eq!(prove(&records, "b.example", DnsQueryType::A, false), DnsQueryDnsSecStatus::Secure);
+    assert!(is_bogus(&prove(&records, "a.example", DnsQueryType::A, false)));
+
+    /* Leaving out the NSEC3 of b.example does not turn it into a name error */
+    let b_hash = BASE32HEX_NOPAD.encode(&nsec3_hash("b.example", 1, &SALT, 12).unwrap());
+    let without_b: Vec<_> = records.iter()
+      .filter(|record| !record.name.eq_ignore_ascii_case(&format!("{}.example", b_hash)))
+      .cloned()
+      .collect();
+    assert_eq!(without_b.len(), 3);
+    assert!(is_bogus(&prove(&without_b, "b.example", DnsQueryType::A, true)));
+  }
+}
@@ -0,0 +1,614 @@
+use crate::dns_query::dns_query_struct::DnsRespondQuery;
+use crate::dns_query::dns_query_0_header::DnsQueryHeaderFlagsRcode;
+use crate::dns_query::dns_query_2_resource_record::DnsQueryResourceRecord;
+use crate::dns_query::dns_query_2_resource_record_rdata::DnsQueryResourceRecordRDataType;
+use crate::dns_query::dns_query_dnssec_denial::{prove_nonexistence, prove_wildcard_answer,
+                                                prove_no_ds, DnsQueryNoDs,
+                                                DnsQueryNSec3Limits};
+use crate::dns_query::dns_query_dnssec::{verify_rrset_at, ds_matches,
+                                         is_algorithm_supported,
+                                         is_digest_type_supported,
+                                         name_eq, name_labels, is_subdomain, now};
+use crate::dns_query::dns_query_error::DnsQueryDnsSecError;
+use crate::dns_query::utils::DnsQueryType;
+use std::fmt::{Display, Formatter};
+
+/*
+ *  Reference:
+ *  - [RFC 4035](https://tools.ietf.org/html/rfc4035)
+ */
+
+/// Source of responses for [`DnsQueryValidator`].
+///
+/// Usually a resolver that sends its queries with the DO bit set, or a
+/// stand-in serving canned responses.
+pub trait DnsQueryDnsSecResolver {
+  /// Response to a query for `name` and `type_` in class IN, or `None` if
+  /// none could be had.
+  fn resolve(&mut self, name: &str, type_: DnsQueryType) -> Option<DnsRespondQuery<'static>>;
+}
+
+/// Security status of a response, see RFC 4035 §4.3.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DnsQueryDnsSecStatus {
+  /// Every RRset chains up to a trust anchor
+  Secure,
+  /// The answer is below a delegation proven to be unsigned
+  Insecure(String),
+  /// A signature or proof is missing or does not validate
+  Bogus(String),
+  /// No trust anchor covers the answer, or a response could not be had
+  Indeterminate(String),
+}
+
+impl DnsQueryDnsSecStatus {
+  /// Rank used to combine the status of several RRsets, worst last.
+  const fn rank(&self) -> u8 {
+    match self {
+      Self::Secure => 0,
+      Self::Insecure(_) => 1,
+      Self::Indeterminate(_) => 2,
+      Self::Bogus(_) => 3,
+    }
+  }
+}
+
+impl Display for DnsQueryDnsSecStatus {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::Secure => write!(f, "secure"),
+      Self::Insecure(reason) => write!(f, "insecure: {}", reason),
+      Self::Bogus(reason) => write!(f, "bogus: {}", reason),
+      Self::Indeterminate(reason) => write!(f, "indeterminate: {}", reason),
+    }
+  }
+}
+
+/// Result of a validation step, with every status but `Secure` as error.
+type Step<T> = Result<T, DnsQueryDnsSecStatus>;
+
+/// Validator of responses, walking DS and DNSKEY records down from its trust
+/// anchors instead of trusting the `ad` flag of the upstream.
+#[derive(Debug)]
+pub struct DnsQueryValidator<R: DnsQueryDnsSecResolver> {
+  resolver: R,
+  trust_anchors: Vec<DnsQueryResourceRecord>,
+  nsec3_limits: DnsQueryNSec3Limits,
+}
+
+impl<R: DnsQueryDnsSecResolver> DnsQueryValidator<R> {
+  pub fn new(resolver: R) -> Self {
+    Self { resolver, trust_anchors: Vec::new(), nsec3_limits: DnsQueryNSec3Limits::default() }
+  }
+
+  /// Add a DS or DNSKEY record as trust anchor for the zone that owns it.
+  pub fn trust_anchor(mut self, record: DnsQueryResourceRecord) -> Self {
+    self.trust_anchors.push(record);
+    self
+  }
+
+  /// Replace the default NSEC3 iteration limits of RFC 9276.
+  pub fn nsec3_limits(mut self, nsec3_limits: DnsQueryNSec3Limits) -> Self {
+    self.nsec3_limits = nsec3_limits;
+    self
+  }
+
+  pub fn resolver_mut(&mut self) -> &mut R {
+    &mut self.resolver
+  }
+
+  /// Validate `response` at the current time.
+  pub fn validate(&mut self, response: &DnsRespondQuery<'_>) -> DnsQueryDnsSecStatus {
+    self.validate_at(response, now())
+  }
+
+  /// Validate `response`, with `now` as seconds since epoch.
+  ///
+  /// Every RRset of the answer section is validated on its own, and the
+  /// worst status is returned. Without answers, the authority section has to
+  /// prove that the question has no answer.
+  pub fn validate_at(&mut self, response: &DnsRespondQuery<'_>, now: u32
+  ) -> DnsQueryDnsSecStatus {
+    let question = match response.question.first() {
+      Some(question) => question,
+      None => return DnsQueryDnsSecStatus::Indeterminate("no question".to_owned()),
+    };
+
+    let rrsets = rrsets(&response.answer);
+    if rrsets.is_empty() {
+      return self.validate_denial(response, &question.q_name, question.q_type, now)
+        .err().unwrap_or(DnsQueryDnsSecStatus::Secure);
+    }
+
+    let mut status = DnsQueryDnsSecStatus::Secure;
+    for (name, type_) in rrsets {
+      if let Err(rrset_status) = self.validate_rrset(response, name, type_, now) {
+        if rrset_status.rank() > status.rank() {
+          status = rrset_status;
+        }
+      }
+    }
+    status
+  }
+
+  /// Validate the RRset of the answer section of `response` with `name` and
+  /// `type_`, along with the proof in the authority section if it was
+  /// expanded from a wildcard.
+  fn validate_rrset(&mut self, response: &DnsRespondQuery<'_>, name: &str,
+                    type_: DnsQueryType, now: u32) -> Step<()> {
+    let answer = &response.answer;
+    let (zone, keys) = self.zone_of(name, type_, now)?;
+
+    /* Only the zone holding the RRset may sign it */
+    let rrset = rrset(answer, name, type_);
+    let rrsigs: Vec<&DnsQueryResourceRecord> = rrsigs(answer, name, type_).into_iter()
+      .filter(|rrsig| signer_name(rrsig).is_some_and(|signer| name_eq(signer, &zone)))
+      .collect();
+    let rrsig = verify_any(&rrset, &rrsigs, &keys, now).map_err(|err| bogus(name, type_, err))?;
+
+    /* Wildcard expansion, see RFC 4035 §5.3.4 */
+    if let DnsQueryResourceRecordRDataType::RrSig { labels, .. } = rrsig.r_data {
+      let owner_labels = name_labels(name).iter().filter(|label| **label != "*").count();
+      if (labels as usize) < owner_labels {
+        verify_all(&response.authority, &keys, now)?;
+        match prove_wildcard_answer(&response.authority, name, labels, &self.nsec3_limits) {
+          DnsQueryDnsSecStatus::Secure => {}
+          status => return Err(status),
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Validate the proof in the authority section of `response` that `q_name`
+  /// has no `q_type` records.
+  fn validate_denial(&mut self, response: &DnsRespondQuery<'_>, q_name: &str,
+                     q_type: DnsQueryType, now: u32) -> Step<()> {
+    let authority = &response.authority;
+
+    /* The proof must come from the zone holding `q_name` */
+    let (_, keys) = self.zone_of(q_name, q_type, now)?;
+    if authority.iter().all(|record| signer_name(record).is_none()) {
+      return Err(bogus(q_name, q_type, DnsQueryDnsSecError::NoRrSig));
+    }
+    verify_all(authority, &keys, now)?;
+
+    let nx_domain = response.header.flags.r_code == DnsQueryHeaderFlagsRcode::NameErr;
+    match prove_nonexistence(authority, q_name, q_type, nx_domain, &self.nsec3_limits) {
+      DnsQueryDnsSecStatus::Secure => Ok(()),
+      status => Err(status),
+    }
+  }
+
+  /// Zone holding the RRset of `name` and `type_`, with its validated
+  /// DNSKEYs. DS records belong to the parent side of a zone cut.
+  fn zone_of(&mut self, name: &str, type_: DnsQueryType, now: u32
+  ) -> Step<(String, Vec<DnsQueryResourceRecord>)> {
+    let labels = name_labels(name);
+    if type_ == DnsQueryType::Ds && !labels.is_empty() {
+      self.walk(&labels[1..].join("."), now)
+    } else {
+      self.walk(name, now)
+    }
+  }
+
+  /// Walk from the closest trust anchor down to `name`, one label at a time,
+  /// and return the deepest zone on the way with its validated DNSKEYs.
+  fn walk(&mut self, name: &str, now: u32) -> Step<(String, Vec<DnsQueryResourceRecord>)> {
+    let mut zone = self.trust_anchors.iter()
+      .map(|anchor| anchor.name.as_str())
+      .filter(|anchor| is_subdomain(name, anchor))
+      .max_by_key(|anchor| name_labels(anchor).len())
+      .ok_or_else(|| DnsQueryDnsSecStatus::Indeterminate(
+        format!("no trust anchor for {}", name)))?
+      .to_owned();
+
+    let anchors: Vec<DnsQueryResourceRecord> = self.trust_anchors.iter()
+      .filter(|anchor| name_eq(&anchor.name, &zone))
+      .cloned()
+      .collect();
+    let mut keys = self.dnskeys(&zone, &anchors.iter().collect::<Vec<_>>(), now)?;
+
+    let labels = name_labels(name);
+    for depth in name_labels(&zone).len() + 1..=labels.len() {
+      let child = labels[labels.len() - depth..].join(".");
+
+      let response = self.resolver.resolve(&child, DnsQueryType::Ds)
+        .ok_or_else(|| DnsQueryDnsSecStatus::Indeterminate(
+          format!("no response for {} DS", child)))?;
+
+      /* Secure delegation */
+      let ds = rrset(&response.answer, &child, DnsQueryType::Ds);
+      if !ds.is_empty() {
+        verify_any(&ds, &rrsigs(&response.answer, &child, DnsQueryType::Ds), &keys, now)
+          .map_err(|err| bogus(&child, DnsQueryType::Ds, err))?;
+        keys = self.dnskeys(&child, &ds, now)?;
+        zone = child;
+        continue;
+      }
+
+      /* No DS, proven by NSEC or NSEC3 records of the parent zone */
+      let authority = &response.authority;
+      verify_all(authority, &keys, now)?;
+
+      match prove_no_ds(authority, &child, &self.nsec3_limits)? {
+        DnsQueryNoDs::NoCut => {}
+        DnsQueryNoDs::Unsigned => {
+          return Err(DnsQueryDnsSecStatus::Insecure(
+            format!("unsigned delegation to {}", child)));
+        }
+        /* Nothing exists further down */
+        DnsQueryNoDs::NoName => break,
+      }
+    }
+
+    Ok((zone, keys))
+  }
+
+  /// Validated DNSKEYs of `zone`, which are trusted through `trusted`, a set
+  /// of DS or DNSKEY records, see RFC 4035 §5.2.
+  fn dnskeys(&mut self, zone: &str, trusted: &[&DnsQueryResourceRecord], now: u32
+  ) -> Step<Vec<DnsQueryResourceRecord>> {
+    let trusted: Vec<&DnsQueryResourceRecord> = trusted.iter()
+      .filter(|record| match &record.r_data {
+        DnsQueryResourceRecordRDataType::Ds { algorithm, digest_type, .. } => {
+          is_algorithm_supported(*algorithm) && is_digest_type_supported(*digest_type)
+        }
+        DnsQueryResourceRecordRDataType::DnsKey { algorithm, .. } => {
+          is_algorithm_supported(*algorithm)
+        }
+        _ => false,
+      })
+      .copied()
+      .collect();
+    if trusted.is_empty() {
+      return Err(DnsQueryDnsSecStatus::Insecure(
+        format!("no supported algorithm for {}", zone)));
+    }
+
+    let response = self.resolver.resolve(zone, DnsQueryType::DnsKey)
+      .ok_or_else(|| DnsQueryDnsSecStatus::Indeterminate(
+        format!("no response for {} DNSKEY", zone)))?;
+
+    let dnskeys = rrset(&response.answer, zone, DnsQueryType::DnsKey);
+    let entry_keys: Vec<&DnsQueryResourceRecord> = dnskeys.iter()
+      .filter(|dnskey| trusted.iter().any(|record| {
+        ds_matches(record, dnskey) || same_key(record, dnskey)
+      }))
+      .copied()
+      .collect();
+    if entry_keys.is_empty() {
+      return Err(DnsQueryDnsSecStatus::Bogus(
+        format!("no DNSKEY of {} matches its trust anchor or DS", zone)));
+    }
+
+    let rrsigs = rrsigs(&response.answer, zone, DnsQueryType::DnsKey);
+    let mut result = Err(DnsQueryDnsSecError::NoRrSig);
+    for rrsig in rrsigs {
+      result = verify_rrset_at(&dnskeys, rrsig, &entry_keys, now).map(|_| ());
+      if result.is_ok() {
+        break;
+      }
+    }
+    result.map_err(|err| bogus(zone, DnsQueryType::DnsKey, err))?;
+
+    Ok(dnskeys.into_iter().cloned().collect())
+  }
+}
+
+/// Bogus status of the RRset with `name` and `type_`, because of `err`.
+fn bogus(name: &str, type_: DnsQueryType, err: DnsQueryDnsSecError) -> DnsQueryDnsSecStatus {
+  DnsQueryDnsSecStatus::Bogus(format!("{} {}: {}", name, type_, err))
+}
+
+/// Name and type of each RRset of `records`, without RRSIGs.
+fn rrsets(records: &[DnsQueryResourceRecord]) -> Vec<(&str, DnsQueryType)> {
+  let mut result: Vec<(&str, DnsQueryType)> = vec![];
+  for record in records {
+    if record.type_ != DnsQueryType::RrSig && !result.iter()
+      .any(|(name, type_)| *type_ == record.type_ && name_eq(name, &record.name)) {
+      result.push((&record.name, record.type_));
+    }
+  }
+  result
+}
+
+/// Records of `records` with `name` and `type_`.
+fn rrset<'a>(records: &'a [DnsQueryResourceRecord], name: &str, type_: DnsQueryType
+) -> Vec<&'a DnsQueryResourceRecord> {
+  records.iter()
+    .filter(|record| record.type_ == type_ && name_eq(&record.name, name))
+    .collect()
+}
+
+/// RRSIGs of `records` covering the RRset with `name` and `type_`.
+fn rrsigs<'a>(records: &'a [DnsQueryResourceRecord], name: &str, type_: DnsQueryType
+) -> Vec<&'a DnsQueryResourceRecord> {
+  records.iter()
+    .filter(|record| record.type_ == DnsQueryType::RrSig && name_eq(&record.name, name)
+      && matches!(&record.r_data,
+        DnsQueryResourceRecordRDataType::RrSig { type_covered, .. } if *type_covered == type_))
+    .collect()
+}
+
+/// Signer name of `record`, if it is an RRSIG.
+fn signer_name(record: &DnsQueryResourceRecord) -> Option<&str> {
+  match &record.r_data {
+    /* SIG records share the RRSIG r_data */
+    DnsQueryResourceRecordRDataType::RrSig { signer_name, .. }
+      if record.type_ == DnsQueryType::RrSig => Some(signer_name),
+    _ => None,
+  }
+}
+
+/// Whether `anchor` is a DNSKEY with the same owner and r_data as `dnskey`.
+fn same_key(anchor: &DnsQueryResourceRecord, dnskey: &DnsQueryResourceRecord) -> bool {
+  let (mut a, mut b) = (vec![], vec![]);
+  anchor.type_ == DnsQueryType::DnsKey && name_eq(&anchor.name, &dnskey.name)
+    && anchor.r_data.write_canonical_to(&mut a).is_ok()
+    && dnskey.r_data.write_canonical_to(&mut b).is_ok()
+    && a == b
+}
+
+/// Verify `rrset` with any of `rrsigs` and `keys`, and return the RRSIG that
+/// verified it.
+fn verify_any<'a>(rrset: &[&DnsQueryResourceRecord], rrsigs: &[&'a DnsQueryResourceRecord],
+                  keys: &[DnsQueryResourceRecord], now: u32
+) -> Result<&'a DnsQueryResourceRecord, DnsQueryDnsSecError> {
+  let keys: Vec<&DnsQueryResourceRecord> = keys.iter().collect();
+
+  let mut result = Err(DnsQueryDnsSecError::NoRrSig);
+  for rrsig in rrsigs {
+    result = verify_rrset_at(rrset, rrsig, &keys, now).map(|_| *rrsig);
+    if result.is_ok() {
+      break;
+    }
+  }
+  result
+}
+
+/// Verify every RRset of `records` with `keys`.
+fn verify_all(records: &[DnsQueryResourceRecord], keys: &[DnsQueryResourceRecord], now: u32
+) -> Step<()> {
+  for (name, type_) in rrsets(records) {
+    verify_any(&rrset(records, name, type_), &rrsigs(records, name, type_), keys, now)
+      .map_err(|err| bogus(name, type_, err))?;
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::dns_query::dns_query_2_resource_record_dnssec::DnsQueryDnsSecAlgorithm;
+  use crate::dns_query::dns_query_dnssec::tests::{dnskey_of, record, sign, zone_key, NOW};
+  use crate::dns_query::dns_query_sig0::DnsQuerySig0Key;
+  use crate::dns_query::dns_query_struct::DnsRequestQuery;
+  use crate::dns_query::utils::{str_to_canonical_vec, DnsQueryClass};
+  use data_encoding::HEXUPPER;
+  use ring::digest;
+  use std::collections::HashMap;
+
+  /// Resolver serving canned answer and authority sections.
+  #[derive(Default)]
+  struct Canned {
+    answers: HashMap<(String, DnsQueryType), (Vec<DnsQueryResourceRecord>,
+                                              Vec<DnsQueryResourceRecord>)>,
+    /// Signed NSEC chain of each zone, proving that other names have no DS
+    nsec_chains: Vec<(String, Vec<DnsQueryResourceRecord>)>,
+  }
+
+  impl DnsQueryDnsSecResolver for Canned {
+    fn resolve(&mut self, name: &str, type_: DnsQueryType) -> Option<DnsRespondQuery<'static>> {
+      if let Some((answer, authority)) = self.answers.get(&(name.to_owned(), type_)) {
+        return Some(respond(name, type_, answer.clone(), authority.clone()));
+      }
+      let (_, chain) = self.nsec_chains.iter()
+        .filter(|(zone, _)| type_ == DnsQueryType::Ds && is_subdomain(name, zone))
+        .max_by_key(|(zone, _)| name_labels(zone).len())?;
+      Some(respond(name, type_, vec![], chain.clone()))
+    }
+  }
+
+  fn respond(name: &str, type_: DnsQueryType, answer: Vec<DnsQueryResourceRecord>,
+              authority: Vec<DnsQueryResourceRecord>) -> DnsRespondQuery<'static> {
+    let query = DnsRequestQuery::builder()
+      .question(name.to_owned(), type_, DnsQueryClass::In)
+      .build();
+    DnsRespondQuery {
+      header: query.header, question: vec![query.question], answer, authority,
+      additional: vec![],
+    }
+  }
+
+  /// `rrset` followed by its RRSIG made with `key`.
+  fn signed(rrset: Vec<DnsQueryResourceRecord>, key: &DnsQuerySig0Key
+  ) -> Vec<DnsQueryResourceRecord> {
+    let rrsig = sign(&rrset.iter().collect::<Vec<_>>(), key);
+    let mut records = rrset;
+    records.push(rrsig);
+    records
+  }
+
+  /// SHA-256 DS of `dnskey`.
+  fn ds_of(dnskey: &DnsQueryResourceRecord) -> DnsQueryResourceRecord {
+    let mut data = vec![];
+    str_to_canonical_vec(&dnskey.name, &mut data).unwrap();
+    dnskey.r_data.write_canonical_to(&mut data).unwrap();
+    let digest = HEXUPPER.encode(digest::digest(&digest::SHA256, &data).as_ref());
+    record(&dnskey.name, DnsQueryType::Ds, &format!(
+      "{} {} 2 {}", dnskey.r_data.key_tag().unwrap(), dnskey_algorithm(dnskey), digest))
+  }
+
+  fn dnskey_algorithm(dnskey: &DnsQueryResourceRecord) -> u8 {
+    match &dnskey.r_data {
+      DnsQueryResourceRecordRDataType::DnsKey { algorithm, .. } => u8::from(algorithm),
+      _ => unreachable!(),
+    }
+  }
+
+  /// `example`, trusted through its DNSKEY, with a signed delegation to
+  /// `sub.example` and an unsigned one to `insecure.example`.
+  struct Zones {
+    example: DnsQuerySig0Key,
+    sub: DnsQuerySig0Key,
+    validator: DnsQueryValidator<Canned>,
+  }
+
+  fn zones() -> Zones {
+    let example = zone_key("example", DnsQueryDnsSecAlgorithm::Ed25519);
+    let sub = zone_key("sub.example", DnsQueryDnsSecAlgorithm::EcdsaP256Sha256);
+    let mut canned = Canned::default();
+
+    canned.answers.insert(("example".to_owned(), DnsQueryType::DnsKey),
+                          (signed(vec![dnskey_of(&example)], &example), vec![]));
+    canned.answers.insert(("sub.example".to_owned(), DnsQueryType::Ds),
+                          (signed(vec![ds_of(&dnskey_of(&sub))], &example), vec![]));
+    canned.answers.insert(("sub.example".to_owned(), DnsQueryType::DnsKey),
+                          (signed(vec![dnskey_of(&sub)], &sub), vec![]));
+
+    let chain = |zone: &str, nsecs: &[(&str, &str)], key: &DnsQuerySig0Key| {
+      let records = nsecs.iter()
+        .flat_map(|(owner, r_data)| signed(vec![record(owner, DnsQueryType::NSec, r_data)], key))
+        .collect();
+      (zone.to_owned(), records)
+    };
+    canned.nsec_chains.push(chain("example", &[
+      ("example", "insecure.example SOA NS NSEC DNSKEY RRSIG"),
+      ("insecure.example", "sub.example NS NSEC RRSIG"),
+      ("sub.example", "www.example NS DS NSEC RRSIG"),
+      ("www.example", "example A NSEC RRSIG"),
+    ], &example));
+    canned.nsec_chains.push(chain("sub.example", &[
+      ("sub.example", "www.sub.example SOA NS NSEC DNSKEY RRSIG"),
+      ("www.sub.example", "sub.example A NSEC RRSIG"),
+    ], &sub));
+
+    let validator = DnsQueryValidator::new(canned).trust_anchor(dnskey_of(&example));
+    Zones { example, sub, validator }
+  }
+
+  fn is_bogus(status: &DnsQueryDnsSecStatus) -> bool {
+    matches!(status, DnsQueryDnsSecStatus::Bogus(_))
+  }
+
+  #[test]
+  fn secure_answer() {
+    let Zones { sub, mut validator, .. } = zones();
+    let answer = signed(vec![record("www.sub.example", DnsQueryType::A, "192.0.2.1")], &sub);
+    let response = respond("www.sub.example", DnsQueryType::A, answer.clone(), vec![]);
+    assert_eq!(validator.validate_at(&response, NOW), DnsQueryDnsSecStatus::Secure);
+
+    /* Expired, tampered, unsigned or signed with SIG instead of RRSIG */
+    assert!(is_bogus(&validator.validate_at(&response, NOW + 2 * 86400)));
+
+    let mut tampered = answer.clone();
+    tampered[0].r_data = record("www.sub.example", DnsQueryType::A, "192.0.2.2").r_data;
+    let response = respond("www.sub.example", DnsQueryType::A, tampered, vec![]);
+    assert!(is_bogus(&validator.validate_at(&response, NOW)));
+
+    let response = respond(
+      "www.sub.example", DnsQueryType::A, answer[..1].to_vec(), vec![]);
+    assert!(is_bogus(&validator.validate_at(&response, NOW)));
+
+    let mut sig = answer;
+    sig[1].type_ = DnsQueryType::Sig;
+    let response = respond("www.sub.example", DnsQueryType::A, sig, vec![]);
+    assert!(is_bogus(&validator.validate_at(&response, NOW)));
+  }
+
+  #[test]
+  fn broken_chain() {
+    let Zones { example, mut validator, .. } = zones();
+
+    /* sub.example serves a DNSKEY its DS does not match */
+    let other = zone_key("sub.example", DnsQueryDnsSecAlgorithm::EcdsaP256Sha256);
+    validator.resolver_mut().answers.insert(("sub.example".to_owned(), DnsQueryType::DnsKey),
+                                            (signed(vec![dnskey_of(&other)], &other), vec![]));
+    let answer = signed(vec![record("www.sub.example", DnsQueryType::A, "192.0.2.1")], &other);
+    let response = respond("www.sub.example", DnsQueryType::A, answer, vec![]);
+    assert!(is_bogus(&validator.validate_at(&response, NOW)));
+
+    /* Answers from example are still fine */
+    let answer = signed(vec![record("www.example", DnsQueryType::A, "192.0.2.1")], &example);
+    let response = respond("www.example", DnsQueryType::A, answer, vec![]);
+    assert_eq!(validator.validate_at(&response, NOW), DnsQueryDnsSecStatus::Secure);
+  }
+
+  #[test]
+  fn insecure_and_indeterminate() {
+    let Zones { mut validator, .. } = zones();
+
+    let answer = vec![record("www.insecure.example", DnsQueryType::A, "192.0.2.1")];
+    let response = respond("www.insecure.example", DnsQueryType::A, answer, vec![]);
+    assert!(matches!(validator.validate_at(&response, NOW), DnsQueryDnsSecStatus::Insecure(_)));
+
+    let answer = vec![record("www.example.net", DnsQueryType::A, "192.0.2.1")];
+    let response = respond("www.example.net", DnsQueryType::A, answer, vec![]);
+    assert!(matches!(validator.validate_at(&response, NOW),
+                     DnsQueryDnsSecStatus::Indeterminate(_)));
+  }
+
+  #[test]
+  fn denial() {
+    let Zones { sub, mut validator, .. } = zones();
+    let name_error = |authority: Vec<DnsQueryResourceRecord>, q_name: &str| {
+      let mut response = respond(q_name, DnsQueryType::A, vec![], authority);
+      response.header.flags.r_code = DnsQueryHeaderFlagsRcode::NameErr;
+      response
+    };
+
+    let authority = signed(vec![record("sub.example", DnsQueryType::NSec,
+                                       "www.sub.example SOA NS NSEC DNSKEY RRSIG")], &sub);
+    let response = name_error(authority.clone(), "nope.sub.example");
+    assert_eq!(validator.validate_at(&response, NOW), DnsQueryDnsSecStatus::Secure);
+    let response = name_error(authority.clone(), "yyy.sub.example");
+    assert!(is_bogus(&validator.validate_at(&response, NOW)));
+
+    /* An NSEC stretched after signing */
+    let mut tampered = authority;
+    tampered[0].r_data = record("sub.example", DnsQueryType::NSec, "zzz.sub.example NSEC").r_data;
+    let response = name_error(tampered, "yyy.sub.example");
+    assert!(is_bogus(&validator.validate_at(&response, NOW)));
+
+    /* b.sub.example exists as an empty non-terminal */
+    let authority = signed(vec![record("sub.example", DnsQueryType::NSec,
+                                       "x.b.sub.example SOA NS NSEC DNSKEY RRSIG")], &sub);
+    let response = name_error(authority, "b.sub.example");
+    assert!(is_bogus(&validator.validate_at(&response, NOW)));
+  }
+
+  #[test]
+  fn ancestor_key_below_delegation() {
+    let Zones { example, mut validator, .. } = zones();
+
+    /* Below a secure delegation, only sub.example may sign */
+    let answer = signed(vec![record("www.sub.example", DnsQueryType::A, "192.0.2.1")], &example);
+    let response = respond("www.sub.example", DnsQueryType::A, answer, vec![]);
+    assert!(is_bogus(&validator.validate_at(&response, NOW)));
+
+    /* Below an insecure one, nothing is signed */
+    let answer =
+      signed(vec![record("www.insecure.example", DnsQueryType::A, "192.0.2.1")], &example);
+    let response = respond("www.insecure.example", DnsQueryType::A, answer, vec![]);
+    assert!(matches!(validator.validate_at(&response, NOW), DnsQueryDnsSecStatus::Insecure(_)));
+
+    /* Nor may example deny names of sub.example */
+    let authority = signed(vec![record("sub.example", DnsQueryType::NSec,
+                                       "zzz.sub.example SOA NS NSEC DNSKEY RRSIG")], &example);
+    let mut response = respond("nope.sub.example", DnsQueryType::A, vec![], authority);
+    response.header.flags.r_code = DnsQueryHeaderFlagsRcode::NameErr;
+    assert!(is_bogus(&validator.validate_at(&response, NOW)));
+  }
+
+  #[test]
+  fn ds_belongs_to_the_parent() {
+    let Zones { example, sub, mut validator } = zones();
+    let ds = || vec![ds_of(&dnskey_of(&sub))];
+
+    let response = respond("sub.example", DnsQueryType::Ds, signed(ds(), &example), vec![]);
+    assert_eq!(validator.validate_at(&response, NOW), DnsQueryDnsSecStatus::Secure);
+    let response = respond("sub.example", DnsQueryType::Ds, signed(ds(), &sub), vec![]);
+    assert!(is_bogus(&validator.validate_at(&response, NOW)));
+  }
+}
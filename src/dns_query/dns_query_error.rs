@@ -0,0 +1,335 @@
+use crate::dns_query::dns_query_2_resource_record_dnssec::DnsQueryDnsSecAlgorithm;
+use crate::dns_query::dns_query_2_resource_record_opt::DnsQueryRcode;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::num::TryFromIntError;
+use std::slice::Iter;
+
+/// Error returned when a message cannot be parsed.
+///
+/// `offset` is the position in the message, counted from its first byte, at
+/// which the problem was detected.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DnsQueryParseError {
+  pub offset: usize,
+  pub kind: DnsQueryParseErrorKind,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DnsQueryParseErrorKind {
+  /// The message ends in the middle of a field
+  Truncated,
+  /// A label length byte uses one of the reserved `0b01` or `0b10` prefixes
+  BadLabelLength(u8),
+  /// A domain name is longer than 255 bytes
+  NameTooLong,
+  /// A compression pointer does not point before the previous one
+  PointerLoop,
+  /// Too many compression pointers are followed in a single domain name
+  TooManyPointers,
+  /// `rd_length` does not match the length of the record data
+  BadRdLength,
+  /// Bytes are left after every section counted in the header
+  TrailingBytes,
+  /// A SvcParam of an SVCB or HTTPS record is malformed or out of order
+  BadSvcParam,
+  /// A field of the record data is malformed
+  BadRData,
+  /// An option of an OPT record is malformed
+  BadEdnsOption,
+}
+
+impl DnsQueryParseError {
+  /// Error of `kind` at the current position of `iter` within `msg`.
+  pub(crate) fn at(msg: &[u8], iter: &Iter<u8>, kind: DnsQueryParseErrorKind) -> Self {
+    Self { offset: msg.len() - iter.as_slice().len(), kind }
+  }
+}
+
+impl Display for DnsQueryParseError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    use DnsQueryParseErrorKind::*;
+
+    match self.kind {
+      Truncated => write!(f, "message truncated")?,
+      BadLabelLength(byte) => write!(f, "bad label length 0x{:02x}", byte)?,
+      NameTooLong => write!(f, "domain name too long")?,
+      PointerLoop => write!(f, "compression pointer loop")?,
+      TooManyPointers => write!(f, "too many compression pointers")?,
+      BadRdLength => write!(f, "rd_length does not match record data")?,
+      TrailingBytes => write!(f, "trailing bytes after last section")?,
+      BadSvcParam => write!(f, "bad SvcParam")?,
+      BadRData => write!(f, "bad record data")?,
+      BadEdnsOption => write!(f, "bad EDNS option")?,
+    }
+    write!(f, " at offset {}", self.offset)
+  }
+}
+
+impl Error for DnsQueryParseError {}
+
+/// Error returned when a message cannot be serialized.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DnsQueryWriteError {
+  /// A length or count does not fit in its field
+  Overflow,
+  /// A label of a name is empty or longer than 63 bytes
+  BadLabelLength,
+  /// A name is longer than 255 bytes in wire format
+  NameTooLong,
+  /// A name has a malformed escape sequence
+  BadEscape,
+  /// A message ending in a TSIG or SIG(0) record cannot be padded, as the
+  /// signature would not cover the padding
+  SignedPadding,
+}
+
+impl From<TryFromIntError> for DnsQueryWriteError {
+  fn from(_: TryFromIntError) -> Self {
+    Self::Overflow
+  }
+}
+
+impl Display for DnsQueryWriteError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    use DnsQueryWriteError::*;
+
+    match self {
+      Overflow => write!(f, "length out of range"),
+      BadLabelLength => write!(f, "bad label length"),
+      NameTooLong => write!(f, "name too long"),
+      BadEscape => write!(f, "bad escape in name"),
+      SignedPadding => write!(f, "cannot pad a signed message"),
+    }
+  }
+}
+
+impl Error for DnsQueryWriteError {}
+
+/// Error returned when the presentation form of a record cannot be parsed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DnsQueryPresentationError {
+  pub reason: &'static str,
+}
+
+impl Display for DnsQueryPresentationError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "bad presentation form: {}", self.reason)
+  }
+}
+
+impl Error for DnsQueryPresentationError {}
+
+/// Error returned when a TSIG key file cannot be parsed.
+///
+/// `line` is the line of the file, counted from 1, at which the problem was
+/// detected.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DnsQueryKeyFileError {
+  pub line: usize,
+  pub reason: &'static str,
+}
+
+impl Display for DnsQueryKeyFileError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "bad key file: {} at line {}", self.reason, self.line)
+  }
+}
+
+impl Error for DnsQueryKeyFileError {}
+
+/// Error returned when an RRset cannot be validated with its RRSIG.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DnsQueryDnsSecError {
+  /// The RRset is empty, or its records differ in name, class or type
+  BadRrSet,
+  /// No RRSIG covers the RRset
+  NoRrSig,
+  /// The RRSIG is not an RRSIG, or does not cover the RRset
+  RrSigMismatch,
+  /// The signer name is neither the owner name nor one of its ancestors
+  BadSignerName,
+  /// The signature inception is later than now
+  NotYetValid,
+  /// The signature expiration is earlier than now
+  Expired,
+  /// No DNSKEY matches the signer name, algorithm and key tag of the RRSIG
+  NoMatchingKey,
+  /// The algorithm of the RRSIG is not supported
+  UnsupportedAlgorithm(DnsQueryDnsSecAlgorithm),
+  /// The public key of the DNSKEY is malformed for its algorithm
+  BadKey,
+  /// The signature does not match the RRset
+  BadSignature,
+}
+
+impl Display for DnsQueryDnsSecError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    use DnsQueryDnsSecError::*;
+
+    match self {
+      BadRrSet => write!(f, "bad RRset"),
+      NoRrSig => write!(f, "no RRSIG"),
+      RrSigMismatch => write!(f, "RRSIG does not cover the RRset"),
+      BadSignerName => write!(f, "bad RRSIG signer name"),
+      NotYetValid => write!(f, "RRSIG not yet valid"),
+      Expired => write!(f, "RRSIG expired"),
+      NoMatchingKey => write!(f, "no matching DNSKEY"),
+      UnsupportedAlgorithm(algorithm) => {
+        write!(f, "unsupported algorithm {}", u8::from(algorithm))
+      }
+      BadKey => write!(f, "bad DNSKEY public key"),
+      BadSignature => write!(f, "bad signature"),
+    }
+  }
+}
+
+impl Error for DnsQueryDnsSecError {}
+
+/// Error returned when a message cannot be signed or verified with TSIG.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DnsQueryTsigError {
+  /// The message, or its TSIG record, cannot be parsed
+  Malformed,
+  /// The message has no TSIG record as its last record
+  NoTsig,
+  /// The key name or algorithm of the TSIG does not match the key
+  BadKey,
+  /// The MAC does not match the message
+  BadSig,
+  /// The time signed is further from now than the fudge allows
+  BadTime,
+  /// The MAC is truncated further than the key allows
+  BadTrunc,
+  /// More unsigned messages in a row than RFC 8945 §5.3.1 allows
+  TooManyUnsigned,
+  /// The other side reported an error in its TSIG record
+  Rejected(DnsQueryRcode),
+}
+
+impl DnsQueryTsigError {
+  /// RCODE to answer a request failing with this error, see RFC 8945 §5.2.
+  pub const fn r_code(&self) -> DnsQueryRcode {
+    match self {
+      Self::Malformed | Self::NoTsig | Self::TooManyUnsigned => DnsQueryRcode::FormatErr,
+      Self::Rejected(_) => DnsQueryRcode::SvrFail,
+      /* NOTAUTH, which TSIG reuses for "not authorized" */
+      _ => DnsQueryRcode::ZoneNotAuth,
+    }
+  }
+
+  /// Error for the TSIG record of the answer, see RFC 8945 §5.2.
+  pub const fn tsig_error(&self) -> DnsQueryRcode {
+    match self {
+      Self::BadKey => DnsQueryRcode::BadKey,
+      Self::BadSig => DnsQueryRcode::BAD_SIG,
+      Self::BadTime => DnsQueryRcode::BadTime,
+      Self::BadTrunc => DnsQueryRcode::BadTrunc,
+      _ => DnsQueryRcode::NoErr,
+    }
+  }
+}
+
+impl Display for DnsQueryTsigError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    use DnsQueryTsigError::*;
+
+    match self {
+      Malformed => write!(f, "malformed message"),
+      NoTsig => write!(f, "no TSIG"),
+      BadKey => write!(f, "bad TSIG key"),
+      BadSig => write!(f, "bad TSIG signature"),
+      BadTime => write!(f, "TSIG time out of window"),
+      BadTrunc => write!(f, "TSIG MAC truncated too much"),
+      TooManyUnsigned => write!(f, "too many unsigned messages"),
+      Rejected(error) => write!(f, "TSIG rejected with error {}", u16::from(error)),
+    }
+  }
+}
+
+impl Error for DnsQueryTsigError {}
+
+/// Error returned when a message cannot be signed or verified with SIG(0).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DnsQuerySig0Error {
+  /// The message, or its SIG(0) record, cannot be parsed
+  Malformed,
+  /// The message has no SIG(0) record as its last record
+  NoSig,
+  /// The signature cannot be made or does not verify
+  DnsSec(DnsQueryDnsSecError),
+}
+
+impl From<DnsQueryDnsSecError> for DnsQuerySig0Error {
+  fn from(err: DnsQueryDnsSecError) -> Self {
+    Self::DnsSec(err)
+  }
+}
+
+impl Display for DnsQuerySig0Error {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    use DnsQuerySig0Error::*;
+
+    match self {
+      Malformed => write!(f, "malformed message"),
+      NoSig => write!(f, "no SIG(0)"),
+      DnsSec(err) => write!(f, "SIG(0): {}", err),
+    }
+  }
+}
+
+impl Error for DnsQuerySig0Error {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::DnsQueryParseErrorKind::*;
+  use crate::dns_query::dns_query_struct::DnsRespondQuery;
+  use data_encoding::HEXLOWER;
+  use std::convert::TryFrom;
+
+  /// Error parsing the response made of a header with one question, and
+  /// then `rest`.
+  fn error(rest: &str) -> Option<DnsQueryParseError> {
+    let bytes = HEXLOWER.decode(format!("000081800001000000000000{}", rest).as_bytes()).unwrap();
+    DnsRespondQuery::try_from(&mut bytes.iter()).err()
+  }
+
+  #[test]
+  fn truncated() {
+    let bytes = HEXLOWER.decode(b"0000818000").unwrap();
+    assert_eq!(DnsRespondQuery::try_from(&mut bytes.iter()).err(),
+               Some(DnsQueryParseError { offset: 4, kind: Truncated }));
+
+    /* In a label, at the end of a name, and in the question type */
+    assert_eq!(error("076578616d"), Some(DnsQueryParseError { offset: 13, kind: Truncated }));
+    assert_eq!(error("076578616d706c65"), Some(DnsQueryParseError { offset: 20, kind: Truncated }));
+    assert_eq!(error("076578616d706c650000"),
+               Some(DnsQueryParseError { offset: 21, kind: Truncated }));
+  }
+
+  #[test]
+  fn bad_label_length() {
+    assert_eq!(error("40"), Some(DnsQueryParseError { offset: 12, kind: BadLabelLength(0x40) }));
+    assert_eq!(error("01618000010001"),
+               Some(DnsQueryParseError { offset: 14, kind: BadLabelLength(0x80) }));
+  }
+
+  #[test]
+  fn pointers() {
+    /* To itself, and forward */
+    assert_eq!(error("c00c"), Some(DnsQueryParseError { offset: 12, kind: PointerLoop }));
+    assert_eq!(error("c00e00"), Some(DnsQueryParseError { offset: 12, kind: PointerLoop }));
+
+    /* Back to the label just before the pointer, and so to the pointer again */
+    assert_eq!(error("0161c00c"), Some(DnsQueryParseError { offset: 14, kind: PointerLoop }));
+  }
+
+  #[test]
+  fn display() {
+    let err = DnsQueryParseError { offset: 12, kind: BadLabelLength(0x40) };
+    assert_eq!(err.to_string(), "bad label length 0x40 at offset 12");
+    let err = DnsQueryParseError { offset: 29, kind: TrailingBytes };
+    assert_eq!(err.to_string(), "trailing bytes after last section at offset 29");
+  }
+}
@@ -0,0 +1,411 @@
+use crate::dns_query::dns_query_2_resource_record::DnsQueryResourceRecord;
+use crate::dns_query::dns_query_2_resource_record_rdata::DnsQueryResourceRecordRDataType;
+use crate::dns_query::dns_query_2_resource_record_dnssec::{DnsQueryDnsSecAlgorithm,
+                                                          dnskey_key_tag};
+use crate::dns_query::dns_query_dnssec::{name_eq, now, serial_le, verify_signature};
+use crate::dns_query::dns_query_error::{DnsQueryDnsSecError, DnsQuerySig0Error};
+use crate::dns_query::dns_query_struct::last_record;
+use crate::dns_query::utils::{trim_root, DnsQueryClass, DnsQueryType};
+use ring::rand::SystemRandom;
+use ring::rsa::PublicKeyComponents;
+use ring::signature::{self, EcdsaKeyPair, Ed25519KeyPair, KeyPair, RsaKeyPair};
+use std::convert::TryFrom;
+
+/*
+ *  Reference:
+ *  - [RFC 2931](https://tools.ietf.org/html/rfc2931)
+ *  - [RFC 3445](https://tools.ietf.org/html/rfc3445)
+ *  - [RFC 8624](https://tools.ietf.org/html/rfc8624)
+ */
+
+/*
+Data signed by SIG(0)
+
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/     SIG r_data, without signature             /
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/     request, with its SIG(0), if a response   /
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/     message, without SIG(0)                   /
++--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+*/
+
+/// How long a signature is valid either side of the time it was made.
+const VALIDITY: u32 = 300;
+
+/// KEY flag of a key owned by a host rather than a zone or user, see
+/// RFC 2535 §3.1.2.
+pub const KEY_FLAG_HOST: u16 = 0b0000_0010_0000_0000;
+
+#[derive(Debug)]
+enum Sig0KeyPair {
+  Rsa(RsaKeyPair),
+  Ecdsa(EcdsaKeyPair),
+  Ed25519(Ed25519KeyPair),
+}
+
+/// Private key signing messages with SIG(0), whose public key is published
+/// in a KEY record named after the key.
+#[derive(Debug)]
+pub struct DnsQuerySig0Key {
+  name: String,
+  algorithm: DnsQueryDnsSecAlgorithm,
+  key_pair: Sig0KeyPair,
+  /// In DNSKEY format
+  public_key: Vec<u8>,
+}
+
+impl DnsQuerySig0Key {
+  /// New private key for `algorithm` in PKCS#8 form, for Ed25519 and ECDSA.
+  pub fn generate_pkcs8(algorithm: DnsQueryDnsSecAlgorithm
+  ) -> Result<Vec<u8>, DnsQueryDnsSecError> {
+    use DnsQueryDnsSecAlgorithm::*;
+
+    let rng = SystemRandom::new();
+    let pkcs8 = match algorithm {
+      EcdsaP256Sha256 => {
+        EcdsaKeyPair::generate_pkcs8(&signature::ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+      }
+      EcdsaP384Sha384 => {
+        EcdsaKeyPair::generate_pkcs8(&signature::ECDSA_P384_SHA384_FIXED_SIGNING, &rng)
+      }
+      Ed25519 => Ed25519KeyPair::generate_pkcs8(&rng),
+      _ => return Err(DnsQueryDnsSecError::UnsupportedAlgorithm(algorithm)),
+    };
+    pkcs8.map(|pkcs8| pkcs8.as_ref().to_vec()).map_err(|_| DnsQueryDnsSecError::BadKey)
+  }
+
+  /// Key `name` with `algorithm` and the private key `pkcs8`.
+  ///
+  /// RSA keys sign with SHA-256 or SHA-512 only, as SHA-1 must no longer be
+  /// used for signing, see RFC 8624 §3.1.
+  pub fn from_pkcs8(name: &str, algorithm: DnsQueryDnsSecAlgorithm, pkcs8: &[u8]
+  ) -> Result<Self, DnsQueryDnsSecError> {
+    use DnsQueryDnsSecAlgorithm::*;
+    use DnsQueryDnsSecError::{BadKey, UnsupportedAlgorithm};
+
+    let rng = SystemRandom::new();
+    let key_pair = match algorithm {
+      RsaSha256 | RsaSha512 => {
+        Sig0KeyPair::Rsa(RsaKeyPair::from_pkcs8(pkcs8).map_err(|_| BadKey)?)
+      }
+      EcdsaP256Sha256 => Sig0KeyPair::Ecdsa(EcdsaKeyPair::from_pkcs8(
+        &signature::ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8, &rng).map_err(|_| BadKey)?),
+      EcdsaP384Sha384 => Sig0KeyPair::Ecdsa(EcdsaKeyPair::from_pkcs8(
+        &signature::ECDSA_P384_SHA384_FIXED_SIGNING, pkcs8, &rng).map_err(|_| BadKey)?),
+      Ed25519 => Sig0KeyPair::Ed25519(Ed25519KeyPair::from_pkcs8(pkcs8).map_err(|_| BadKey)?),
+      _ => return Err(UnsupportedAlgorithm(algorithm)),
+    };
+
+    let public_key = match &key_pair {
+      /* RSA public key format, see RFC 3110 §2 */
+      Sig0KeyPair::Rsa(key_pair) => {
+        let PublicKeyComponents::<Vec<u8>> { n, e } = key_pair.public().into();
+        let mut public_key = match u8::try_from(e.len()) {
+          Ok(len) => vec![len],
+          Err(_) => {
+            let len = u16::try_from(e.len()).map_err(|_| BadKey)?;
+            [&[0], &len.to_be_bytes()[..]].concat()
+          }
+        };
+        public_key.extend_from_slice(&e);
+        public_key.extend_from_slice(&n);
+        public_key
+      }
+      /* ECDSA public key format, without the leading 0x04, see RFC 6605 §4 */
+      Sig0KeyPair::Ecdsa(key_pair) => key_pair.public_key().as_ref()[1..].to_vec(),
+      Sig0KeyPair::Ed25519(key_pair) => key_pair.public_key().as_ref().to_vec(),
+    };
+
+    Ok(Self {
+      name: trim_root(name).to_owned(),
+      algorithm,
+      key_pair,
+      public_key,
+    })
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  pub const fn algorithm(&self) -> DnsQueryDnsSecAlgorithm {
+    self.algorithm
+  }
+
+  /// Public key, in DNSKEY format.
+  pub fn public_key(&self) -> &[u8] {
+    &self.public_key
+  }
+
+  pub fn key_tag(&self) -> u16 {
+    dnskey_key_tag(KEY_FLAG_HOST, 3, self.algorithm, &self.public_key)
+  }
+
+  /// KEY record publishing the public key, with `ttl`.
+  pub fn key_record(&self, ttl: u32) -> DnsQueryResourceRecord {
+    DnsQueryResourceRecord {
+      name: self.name.clone(),
+      type_: DnsQueryType::Key,
+      class: DnsQueryClass::In,
+      ttl,
+      rd_length: 0,
+      r_data: DnsQueryResourceRecordRDataType::DnsKey {
+        flags: KEY_FLAG_HOST,
+        protocol: 3,
+        algorithm: self.algorithm,
+        public_key: self.public_key.clone(),
+      },
+    }
+  }
+
+  /// Signature of `data`, in RRSIG format.
+  pub(crate) fn sign(&self, data: &[u8]) -> Result<Vec<u8>, DnsQueryDnsSecError> {
+    let rng = SystemRandom::new();
+    match &self.key_pair {
+      Sig0KeyPair::Rsa(key_pair) => {
+        let padding: &'static dyn signature::RsaEncoding =
+          if self.algorithm == DnsQueryDnsSecAlgorithm::RsaSha512 {
+            &signature::RSA_PKCS1_SHA512
+          } else {
+            &signature::RSA_PKCS1_SHA256
+          };
+        let mut sig = vec![0; key_pair.public().modulus_len()];
+        key_pair.sign(padding, &rng, data, &mut sig)
+          .map_err(|_| DnsQueryDnsSecError::BadKey)?;
+        Ok(sig)
+      }
+      Sig0KeyPair::Ecdsa(key_pair) => key_pair.sign(&rng, data)
+        .map(|sig| sig.as_ref().to_vec())
+        .map_err(|_| DnsQueryDnsSecError::BadKey),
+      Sig0KeyPair::Ed25519(key_pair) => Ok(key_pair.sign(data).as_ref().to_vec()),
+    }
+  }
+}
+
+/// Sign `msg` at the current time, see [`sign_message_at`].
+pub fn sign_message(msg: &[u8], key: &DnsQuerySig0Key, request: Option<&[u8]>
+) -> Result<Vec<u8>, DnsQuerySig0Error> {
+  sign_message_at(msg, key, request, now())
+}
+
+/// Append a SIG(0) record to `msg`, a serialized message, valid for five
+/// minutes either side of `now` as seconds since epoch.
+///
+/// `request` is the signed request `msg` answers, if any, which is covered
+/// by the signature too.
+pub fn sign_message_at(msg: &[u8], key: &DnsQuerySig0Key, request: Option<&[u8]>, now: u32
+) -> Result<Vec<u8>, DnsQuerySig0Error> {
+  if msg.len() < 12 {
+    return Err(DnsQuerySig0Error::Malformed);
+  }
+
+  let mut record = DnsQueryResourceRecord {
+    name: String::new(),
+    type_: DnsQueryType::Sig,
+    class: DnsQueryClass::QClsAny,
+    ttl: 0,
+    rd_length: 0,
+    r_data: DnsQueryResourceRecordRDataType::RrSig {
+      type_covered: 0.into(),
+      algorithm: key.algorithm,
+      labels: 0,
+      original_ttl: 0,
+      sig_expiration: now.wrapping_add(VALIDITY),
+      sig_inception: now.wrapping_sub(VALIDITY),
+      key_tag: key.key_tag(),
+      signer_name: key.name.clone(),
+      signature: vec![],
+    },
+  };
+
+  let data = signed_data(&record.r_data, request, msg)?;
+  let sig = key.sign(&data)?;
+  if let DnsQueryResourceRecordRDataType::RrSig { signature, .. } = &mut record.r_data {
+    *signature = sig;
+  }
+
+  let mut result = msg.to_vec();
+  record.write_to(&mut result, &mut None).map_err(|_| DnsQuerySig0Error::Malformed)?;
+
+  let ar_count = u16::from_be_bytes([result[10], result[11]]).checked_add(1)
+    .ok_or(DnsQuerySig0Error::Malformed)?;
+  result[10..12].copy_from_slice(&ar_count.to_be_bytes());
+  Ok(result)
+}
+
+/// Verify `msg` with one of `keys` at the current time, see
+/// [`verify_message_at`].
+pub fn verify_message<'a>(msg: &[u8], keys: &[&'a DnsQueryResourceRecord],
+                          request: Option<&[u8]>
+) -> Result<&'a DnsQueryResourceRecord, DnsQuerySig0Error> {
+  verify_message_at(msg, keys, request, now())
+}
+
+/// Verify the SIG(0) record ending `msg`, a serialized message, with one of
+/// `keys`, with `now` as seconds since epoch, and return the KEY that
+/// verified it.
+///
+/// Only KEY records owned by the signer name, with protocol 3 and the
+/// algorithm and key tag of the SIG are tried. `request` is the request `msg` answers, if
+/// it was signed.
+pub fn verify_message_at<'a>(msg: &[u8], keys: &[&'a DnsQueryResourceRecord],
+                             request: Option<&[u8]>, now: u32
+) -> Result<&'a DnsQueryResourceRecord, DnsQuerySig0Error> {
+  use DnsQueryDnsSecError::*;
+
+  let (start, record) = match last_record(msg).map_err(|_| DnsQuerySig0Error::Malformed)? {
+    Some((start, record)) if record.type_ == DnsQueryType::Sig => (start, record),
+    _ => return Err(DnsQuerySig0Error::NoSig),
+  };
+
+  /* Check SIG(0), see RFC 2931 §3 */
+  let (algorithm, key_tag, signer_name) = match &record.r_data {
+    DnsQueryResourceRecordRDataType::RrSig {
+      type_covered, algorithm, labels: 0, original_ttl: 0, sig_expiration, sig_inception,
+      key_tag, signer_name, ..
+    } if u16::from(type_covered) == 0 && record.name.is_empty()
+      && record.class == DnsQueryClass::QClsAny && record.ttl == 0 => {
+      if !serial_le(*sig_inception, now) {
+        return Err(NotYetValid.into());
+      }
+      if !serial_le(now, *sig_expiration) {
+        return Err(Expired.into());
+      }
+      (*algorithm, *key_tag, signer_name)
+    }
+    _ => return Err(RrSigMismatch.into()),
+  };
+
+  /* Message as it was signed, the SIG(0) being the last additional record */
+  let mut unsigned_msg = msg[..start].to_vec();
+  let ar_count = u16::from_be_bytes([msg[10], msg[11]]).checked_sub(1)
+    .ok_or(DnsQuerySig0Error::Malformed)?;
+  unsigned_msg[10..12].copy_from_slice(&ar_count.to_be_bytes());
+  let data = signed_data(&record.r_data, request, &unsigned_msg)?;
+
+  let mut result = Err(NoMatchingKey);
+  for key in keys {
+    let matches = match &key.r_data {
+      /* DNSKEY and CDNSKEY records share the KEY r_data */
+      DnsQueryResourceRecordRDataType::DnsKey {
+        flags, protocol, algorithm: key_algorithm, public_key,
+      } if key.type_ == DnsQueryType::Key => {
+        name_eq(&key.name, signer_name)
+          && *protocol == 3
+          && algorithm == *key_algorithm
+          && key_tag == dnskey_key_tag(*flags, *protocol, *key_algorithm, public_key)
+      }
+      _ => false,
+    };
+    if matches {
+      result = verify_signature(&record, key, &data).map(|_| *key);
+      if result.is_ok() {
+        break;
+      }
+    }
+  }
+  Ok(result?)
+}
+
+/// Data signed by the SIG(0) with `r_data`, for `msg` answering `request`.
+fn signed_data(r_data: &DnsQueryResourceRecordRDataType, request: Option<&[u8]>, msg: &[u8]
+) -> Result<Vec<u8>, DnsQuerySig0Error> {
+  let signature_len = match r_data {
+    DnsQueryResourceRecordRDataType::RrSig { signature, .. } => signature.len(),
+    _ => return Err(DnsQuerySig0Error::Malformed),
+  };
+
+  let mut data = vec![];
+  r_data.write_canonical_to(&mut data).map_err(|_| DnsQuerySig0Error::Malformed)?;
+  data.truncate(data.len() - signature_len);
+  data.extend_from_slice(request.unwrap_or_default());
+  data.extend_from_slice(msg);
+  Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::dns_query::dns_query_struct::DnsRequestQuery;
+
+  const NOW: u32 = 1_700_000_000;
+
+  fn key(name: &str, algorithm: DnsQueryDnsSecAlgorithm) -> DnsQuerySig0Key {
+    let pkcs8 = match algorithm {
+      DnsQueryDnsSecAlgorithm::RsaSha256 | DnsQueryDnsSecAlgorithm::RsaSha512 => {
+        include_bytes!("testdata/rsa2048.pk8").to_vec()
+      }
+      _ => DnsQuerySig0Key::generate_pkcs8(algorithm).unwrap(),
+    };
+    DnsQuerySig0Key::from_pkcs8(name, algorithm, &pkcs8).unwrap()
+  }
+
+  fn query() -> Vec<u8> {
+    DnsRequestQuery::from_url("example.com".into()).to_vec(false).unwrap()
+  }
+
+  #[test]
+  fn sign_and_verify() {
+    use DnsQueryDnsSecAlgorithm::*;
+
+    for algorithm in [RsaSha256, RsaSha512, EcdsaP256Sha256, EcdsaP384Sha384, Ed25519] {
+      let key = key("host.example.", algorithm);
+      assert_eq!(key.name(), "host.example");
+      let key_record = key.key_record(3600);
+
+      let msg = sign_message_at(&query(), &key, None, NOW).unwrap();
+      assert_eq!(&msg[10..12], &[0, 1]);
+      let verified = verify_message_at(&msg, &[&key_record], None, NOW + VALIDITY);
+      assert_eq!(verified.map(|key| &key.name), Ok(&key_record.name), "{:?}", algorithm);
+
+      /* Every byte is covered */
+      let mut tampered = msg.clone();
+      tampered[13] ^= 0x20;
+      assert_eq!(verify_message_at(&tampered, &[&key_record], None, NOW).err(),
+                 Some(DnsQueryDnsSecError::BadSignature.into()), "{:?}", algorithm);
+    }
+  }
+
+  #[test]
+  fn verify_response() {
+    let key = key("host.example", DnsQueryDnsSecAlgorithm::Ed25519);
+    let key_record = key.key_record(3600);
+
+    let request = sign_message_at(&query(), &key, None, NOW).unwrap();
+    let mut response = query();
+    response[2] |= 0x80;
+    let response = sign_message_at(&response, &key, Some(&request), NOW).unwrap();
+
+    assert!(verify_message_at(&response, &[&key_record], Some(&request), NOW).is_ok());
+    assert_eq!(verify_message_at(&response, &[&key_record], None, NOW).err(),
+               Some(DnsQueryDnsSecError::BadSignature.into()));
+  }
+
+  #[test]
+  fn verify_failures() {
+    use DnsQueryDnsSecError::*;
+
+    let key = key("host.example", DnsQueryDnsSecAlgorithm::EcdsaP256Sha256);
+    let key_record = key.key_record(3600);
+    let msg = sign_message_at(&query(), &key, None, NOW).unwrap();
+
+    assert_eq!(verify_message_at(&query(), &[&key_record], None, NOW).err(),
+               Some(DnsQuerySig0Error::NoSig));
+    assert_eq!(verify_message_at(&msg, &[&key_record], None, NOW + VALIDITY + 1).err(),
+               Some(Expired.into()));
+    assert_eq!(verify_message_at(&msg, &[&key_record], None, NOW - VALIDITY - 1).err(),
+               Some(NotYetValid.into()));
+
+    /* Another key, or the right one published as a DNSKEY */
+    let other = DnsQuerySig0Key::generate_pkcs8(DnsQueryDnsSecAlgorithm::EcdsaP256Sha256)
+      .and_then(|pkcs8| DnsQuerySig0Key::from_pkcs8(
+        "host.example", DnsQueryDnsSecAlgorithm::EcdsaP256Sha256, &pkcs8))
+      .unwrap();
+    let mut dnskey = key_record.clone();
+    dnskey.type_ = DnsQueryType::DnsKey;
+    assert_eq!(verify_message_at(&msg, &[&other.key_record(3600)], None, NOW).err(),
+               Some(NoMatchingKey.into()));
+    assert_eq!(verify_message_at(&msg, &[&dnskey], None, NOW).err(), Some(NoMatchingKey.into()));
+  }
+}
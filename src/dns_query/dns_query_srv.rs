@@ -0,0 +1,160 @@
+use crate::dns_query::dns_query_2_resource_record::DnsQueryResourceRecord;
+use crate::dns_query::dns_query_2_resource_record_rdata::DnsQueryResourceRecordRDataType;
+use ring::rand::{SecureRandom, SystemRandom};
+
+/*
+ *  Reference:
+ *  - [RFC 2782](https://tools.ietf.org/html/rfc2782)
+ */
+
+/// Order SRV `records` in which their targets should be contacted.
+///
+/// Records are sorted by ascending priority, and records of the same
+/// priority are ordered by the weighted random selection of RFC 2782.
+/// Records that are not SRV are skipped, and a single SRV record with target
+/// `.` means the service is decidedly not available, which gives no targets.
+pub fn srv_target_order(records: &[DnsQueryResourceRecord]) -> Vec<&DnsQueryResourceRecord> {
+  let rng = SystemRandom::new();
+  srv_target_order_with(records, |max| random_up_to(max, || {
+    let mut bytes = [0; 4];
+    /* The system RNG only fails if the OS has none */
+    rng.fill(&mut bytes).expect("no system random number generator");
+    u32::from_be_bytes(bytes)
+  }))
+}
+
+/// Uniformly distributed number in `0..=max`, from uniformly distributed
+/// 32-bit numbers drawn by `draw`.
+fn random_up_to<F: FnMut() -> u32>(max: u32, mut draw: F) -> u32 {
+  let range = u64::from(max) + 1;
+  /* Draws from this bound up would favor low numbers, so they are rejected */
+  let bound = (1 << 32) - (1 << 32) % range;
+
+  loop {
+    let val = u64::from(draw());
+    if val < bound {
+      #[allow(clippy::cast_possible_truncation)]
+      let val = (val % range) as u32;
+      return val;
+    }
+  }
+}
+
+/// Same as [`srv_target_order`], with `random(max)` as source of uniformly
+/// distributed numbers in `0..=max`.
+pub fn srv_target_order_with<F: FnMut(u32) -> u32>(records: &[DnsQueryResourceRecord],
+                                                   mut random: F
+) -> Vec<&DnsQueryResourceRecord> {
+  let mut srv: Vec<(u16, u16, &DnsQueryResourceRecord)> = records.iter()
+    .filter_map(|record| match &record.r_data {
+      DnsQueryResourceRecordRDataType::Srv { priority, weight, .. } => {
+        Some((*priority, *weight, record))
+      }
+      _ => None,
+    })
+    .collect();
+
+  /* Service not available */ {
+    if let [(_, _, record)] = srv.as_slice() {
+      if let DnsQueryResourceRecordRDataType::Srv { target, .. } = &record.r_data {
+        if target.is_empty() || target == "." {
+          return vec![];
+        }
+      }
+    }
+  }
+
+  /* Lowest priority first, and zero weight first within each priority */
+  srv.sort_by_key(|(priority, weight, _)| (*priority, *weight != 0));
+
+  let mut result = Vec::with_capacity(srv.len());
+  let mut srv = srv.as_mut_slice();
+
+  while !srv.is_empty() {
+    let priority = srv[0].0;
+    let len = srv.iter().take_while(|(p, _, _)| *p == priority).count();
+    let (group, rest) = srv.split_at_mut(len);
+
+    for i in 0..group.len() {
+      let remaining = &mut group[i..];
+      let total: u32 = remaining.iter().map(|(_, weight, _)| u32::from(*weight)).sum();
+      let target = random(total);
+
+      let mut running = 0;
+      let mut chosen = remaining.len() - 1;
+      for (j, (_, weight, _)) in remaining.iter().enumerate() {
+        running += u32::from(*weight);
+        if running >= target {
+          chosen = j;
+          break;
+        }
+      }
+
+      /* Keep the unordered rest in their order, zero weights first */
+      remaining[..=chosen].rotate_right(1);
+      result.push(remaining[0].2);
+    }
+
+    srv = rest;
+  }
+
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::dns_query::utils::{DnsQueryClass, DnsQueryType};
+
+  fn srv(priority: u16, weight: u16, target: &str) -> DnsQueryResourceRecord {
+    DnsQueryResourceRecord {
+      name: "_sip._tcp.example.com".to_owned(),
+      type_: DnsQueryType::Srv,
+      class: DnsQueryClass::In,
+      ttl: 300,
+      rd_length: 0,
+      r_data: DnsQueryResourceRecordRDataType::Srv {
+        priority, weight, port: 5060, target: target.to_owned(),
+      },
+    }
+  }
+
+  fn targets<'a>(records: &[&'a DnsQueryResourceRecord]) -> Vec<&'a str> {
+    records.iter()
+      .map(|record| match &record.r_data {
+        DnsQueryResourceRecordRDataType::Srv { target, .. } => target.as_str(),
+        _ => unreachable!(),
+      })
+      .collect()
+  }
+
+  #[test]
+  fn order_by_priority_then_weight() {
+    let records = [srv(20, 0, "c"), srv(10, 10, "a"), srv(10, 30, "b"), srv(10, 0, "z")];
+
+    /* Always draw the highest number, i.e. the last running sum */
+    let order = srv_target_order_with(&records, |max| max);
+    assert_eq!(targets(&order), ["b", "a", "z", "c"]);
+
+    /* Always draw 0, which picks the zero weight record first */
+    let order = srv_target_order_with(&records, |_| 0);
+    assert_eq!(targets(&order), ["z", "a", "b", "c"]);
+  }
+
+  #[test]
+  fn service_not_available() {
+    assert!(srv_target_order(&[srv(0, 0, "")]).is_empty());
+    assert_eq!(srv_target_order(&[srv(0, 0, "a"), srv(0, 0, "")]).len(), 2);
+  }
+
+  #[test]
+  fn random_rejects_biased_draws() {
+    /* With a range of 3, draws from 0xffff_ffff on would be biased */
+    let mut draws = vec![7, 0xffff_fffe, u32::MAX];
+    assert_eq!(random_up_to(2, || draws.pop().unwrap()), 2);
+    assert_eq!(draws, [7]);
+
+    assert_eq!(random_up_to(u32::MAX, || u32::MAX), u32::MAX);
+    assert_eq!(random_up_to(0, || 12345), 0);
+  }
+}
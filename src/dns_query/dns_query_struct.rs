@@ -11,26 +11,44 @@ use crate::dns_query::dns_query_0_header::{DnsQueryHeader,
                                            DnsQueryHeaderFlagsRcode};
 use crate::dns_query::dns_query_1_question::DnsQueryQuestion;
 use crate::dns_query::dns_query_2_resource_record::DnsQueryResourceRecord;
+use crate::dns_query::dns_query_2_resource_record_opt::{DnsQueryEdns, DnsQueryEdnsOption,
+                                                       DnsQueryEdnsOptionCode,
+                                                       DnsQueryExtendedError,
+                                                       DnsQueryRcode,
+                                                       DEFAULT_UDP_PAYLOAD_SIZE};
+use crate::dns_query::dns_query_builder::DnsRequestQueryBuilder;
 use crate::dns_query::utils::{DnsQueryType, DnsQueryClass};
 use std::convert::{TryFrom, TryInto};
-use std::option::NoneError;
+use std::collections::HashMap;
+use crate::dns_query::dns_query_error::{DnsQueryParseError,
+                                       DnsQueryParseErrorKind,
+                                       DnsQueryWriteError};
 use std::slice::Iter;
-use std::num::TryFromIntError;
 use std::borrow::Cow;
 
 /*
  *  Reference:
  *  - [RFC 1035](https://tools.ietf.org/html/rfc1035)
  *  - [RFC 2535](https://tools.ietf.org/html/rfc2535)
+ *  - [RFC 6891](https://tools.ietf.org/html/rfc6891)
+ *  - [RFC 8467](https://tools.ietf.org/html/rfc8467)
+ *  - [RFC 9619](https://tools.ietf.org/html/rfc9619)
  */
 
 #[derive(Debug)]
 pub struct DnsRequestQuery<'a> {
   pub header: DnsQueryHeader,
   pub question: DnsQueryQuestion<'a>,
+  /// Questions after the first, which most servers refuse, see RFC 9619
+  pub extra_questions: Vec<DnsQueryQuestion<'a>>,
+  pub additional: Vec<DnsQueryResourceRecord>,
 }
 
 impl<'a> DnsRequestQuery<'a> {
+  pub fn builder() -> DnsRequestQueryBuilder<'a> {
+    DnsRequestQueryBuilder::new()
+  }
+
   pub const fn from_url(cow_str: Cow<'a, str>) -> Self {
     Self {
       header: DnsQueryHeader {
@@ -57,48 +75,473 @@ impl<'a> DnsRequestQuery<'a> {
         q_type: DnsQueryType::A,
         q_class: DnsQueryClass::In,
       },
+      extra_questions: Vec::new(),
+      additional: Vec::new(),
     }
   }
-}
 
-impl<'a> TryFrom<&DnsRequestQuery<'a>> for Vec<u8> {
-  type Error = TryFromIntError;
+  /// Every question, starting with `question`.
+  pub fn questions(&self) -> impl Iterator<Item = &DnsQueryQuestion<'a>> {
+    std::iter::once(&self.question).chain(&self.extra_questions)
+  }
 
-  fn try_from(query: &DnsRequestQuery<'a>) -> Result<Self, Self::Error> {
-    let mut result = Self::with_capacity(16);
+  /// EDNS view of the OPT record in the additional section.
+  pub fn edns(&self) -> Option<DnsQueryEdns> {
+    find_edns(&self.additional)
+  }
 
-    /* header */ {
-      let header: [u8; 12] = (&query.header).into();
-      result.extend(header.iter());
-    }
+  /// Replace the OPT record in the additional section with `edns`.
+  pub fn set_edns(&mut self, edns: &DnsQueryEdns) {
+    replace_edns(&mut self.additional, edns);
+  }
 
-    /* question */ {
-      let question: Self = (&query.question).try_into()?;
-      result.extend(question.iter());
-    }
+  /// Serialize this query to wire format.
+  ///
+  /// With `compress` set, repeated names are replaced by compression pointers
+  /// (RFC 1035 §4.1.4); otherwise every name is written in full, which is
+  /// the canonical form that DNSSEC relies on.
+  pub fn to_vec(&self, compress: bool) -> Result<Vec<u8>, DnsQueryWriteError> {
+    write_message(&self.header, &self.questions().collect::<Vec<_>>(),
+                  [&[], &[], &self.additional], compress, None)
+  }
+
+  /// Serialize this query to wire format, padded to a multiple of 128
+  /// bytes for encrypted transports, see RFC 8467 §4.1.
+  ///
+  /// An OPT record is added if there is none yet.
+  pub fn to_padded_vec(&self, compress: bool) -> Result<Vec<u8>, DnsQueryWriteError> {
+    write_message(&self.header, &self.questions().collect::<Vec<_>>(),
+                  [&[], &[], &self.additional], compress, Some(REQUEST_PADDING_BLOCK))
+  }
+}
+
+impl<'a> TryFrom<&DnsRequestQuery<'a>> for Vec<u8> {
+  type Error = DnsQueryWriteError;
 
-    result.shrink_to_fit();
-    Ok(result)
+  fn try_from(query: &DnsRequestQuery<'a>) -> Result<Self, Self::Error> {
+    query.to_vec(false)
   }
 }
 
 #[derive(Debug)]
 pub struct DnsRespondQuery<'a> {
   pub header: DnsQueryHeader,
-  pub question: DnsQueryQuestion<'a>,
-  pub answer: DnsQueryResourceRecord,
-  pub authority: DnsQueryResourceRecord,
-  pub additional: DnsQueryResourceRecord,
+  pub question: Vec<DnsQueryQuestion<'a>>,
+  pub answer: Vec<DnsQueryResourceRecord>,
+  pub authority: Vec<DnsQueryResourceRecord>,
+  pub additional: Vec<DnsQueryResourceRecord>,
 }
 
 impl<'a> TryFrom<&mut Iter<'_, u8>> for DnsRespondQuery<'a> {
-  type Error = NoneError;
+  type Error = DnsQueryParseError;
   fn try_from(iter: &mut Iter<'_, u8>) -> Result<Self, Self::Error> {
-    let header = iter.try_into()?;
-    let question = iter.try_into()?;
-    let answer = iter.try_into()?;
-    let authority = iter.try_into()?;
-    let additional = iter.try_into()?;
+    /* `iter` starts at the beginning of the message, which compressed names
+       refer back to */
+    let msg = iter.as_slice();
+
+    let header: DnsQueryHeader = (msg, &mut *iter).try_into()?;
+
+    let question = (0..header.qd_count)
+      .map(|_| (msg, &mut *iter).try_into())
+      .collect::<Result<_, _>>()?;
+
+    let answer = (0..header.an_count)
+      .map(|_| (msg, &mut *iter).try_into())
+      .collect::<Result<_, _>>()?;
+
+    let authority = (0..header.ns_count)
+      .map(|_| (msg, &mut *iter).try_into())
+      .collect::<Result<_, _>>()?;
+
+    let additional = (0..header.ar_count)
+      .map(|_| (msg, &mut *iter).try_into())
+      .collect::<Result<_, _>>()?;
+
+    /* Counts in header must cover the whole message */ {
+      if iter.len() != 0 {
+        return Err(DnsQueryParseError::at(msg, iter, DnsQueryParseErrorKind::TrailingBytes));
+      }
+    }
+
     Ok(Self { header, question, answer, authority, additional })
   }
-}
\ No newline at end of file
+}
+
+impl<'a> DnsRespondQuery<'a> {
+  /// EDNS view of the OPT record in the additional section.
+  pub fn edns(&self) -> Option<DnsQueryEdns> {
+    find_edns(&self.additional)
+  }
+
+  /// Replace the OPT record in the additional section with `edns`.
+  pub fn set_edns(&mut self, edns: &DnsQueryEdns) {
+    replace_edns(&mut self.additional, edns);
+  }
+
+  /// Extended DNS Errors telling why this response has its RCODE.
+  pub fn extended_errors(&self) -> Vec<DnsQueryExtendedError> {
+    self.edns().map_or_else(Vec::new, |edns| edns.extended_errors().cloned().collect())
+  }
+
+  /// Name server identifier, if the server sent one.
+  pub fn nsid(&self) -> Option<Vec<u8>> {
+    self.edns()?.nsid().map(<[u8]>::to_vec)
+  }
+
+  /// Attach `error`, along with an OPT record if there is none yet.
+  pub fn add_extended_error(&mut self, error: DnsQueryExtendedError) {
+    let mut edns = self.edns().unwrap_or_else(|| DnsQueryEdns::new(DEFAULT_UDP_PAYLOAD_SIZE));
+    edns.options.push(DnsQueryEdnsOption::ExtendedError(error));
+    self.set_edns(&edns);
+  }
+
+  /// Attach `nsid`, along with an OPT record if there is none yet.
+  pub fn set_nsid(&mut self, nsid: Vec<u8>) {
+    let mut edns = self.edns().unwrap_or_else(|| DnsQueryEdns::new(DEFAULT_UDP_PAYLOAD_SIZE));
+    edns.set_option(DnsQueryEdnsOption::Nsid(nsid));
+    self.set_edns(&edns);
+  }
+
+  /// 12-bit RCODE, taking the upper bits from the OPT record if any.
+  pub fn r_code(&self) -> DnsQueryRcode {
+    match self.edns() {
+      Some(edns) => edns.r_code(self.header.flags.r_code),
+      None => u16::from(u8::from(&self.header.flags.r_code)).into(),
+    }
+  }
+
+  /// Serialize this response to wire format.
+  ///
+  /// See [`DnsRequestQuery::to_vec`] for `compress`.
+  pub fn to_vec(&self, compress: bool) -> Result<Vec<u8>, DnsQueryWriteError> {
+    write_message(&self.header, &self.question.iter().collect::<Vec<_>>(),
+                  [&self.answer, &self.authority, &self.additional], compress, None)
+  }
+
+  /// Serialize this response to wire format, padded to a multiple of 468
+  /// bytes for encrypted transports, see RFC 8467 §4.1.
+  ///
+  /// Responses should only be padded when the query was. An OPT record is
+  /// added if there is none yet.
+  pub fn to_padded_vec(&self, compress: bool) -> Result<Vec<u8>, DnsQueryWriteError> {
+    write_message(&self.header, &self.question.iter().collect::<Vec<_>>(),
+                  [&self.answer, &self.authority, &self.additional], compress,
+                  Some(RESPONSE_PADDING_BLOCK))
+  }
+}
+
+impl<'a> TryFrom<&DnsRespondQuery<'a>> for Vec<u8> {
+  type Error = DnsQueryWriteError;
+
+  fn try_from(query: &DnsRespondQuery<'a>) -> Result<Self, Self::Error> {
+    query.to_vec(false)
+  }
+}
+
+/// Block length queries are padded to, see RFC 8467 §4.1.
+const REQUEST_PADDING_BLOCK: usize = 128;
+
+/// Block length responses are padded to, see RFC 8467 §4.1.
+const RESPONSE_PADDING_BLOCK: usize = 468;
+
+/// Serialize a message to wire format, see [`DnsRequestQuery::to_vec`].
+///
+/// With `padding_block` set, the OPT record is written last, with a Padding
+/// option sized from the length of everything before it, so that the whole
+/// message is a multiple of `padding_block` bytes. Signed messages cannot
+/// be padded, as the TSIG or SIG(0) record must stay last: pad first, then
+/// sign.
+fn write_message(header: &DnsQueryHeader, question: &[&DnsQueryQuestion<'_>],
+                 [answer, authority, additional]: [&[DnsQueryResourceRecord]; 3],
+                 compress: bool, padding_block: Option<usize>
+) -> Result<Vec<u8>, DnsQueryWriteError> {
+  let mut result = Vec::with_capacity(512);
+  let mut offsets = if compress { Some(HashMap::new()) } else { None };
+
+  /* The OPT record would follow the signature */ {
+    let signed = additional.last()
+      .is_some_and(|record| matches!(record.type_, DnsQueryType::TSig | DnsQueryType::Sig));
+    if padding_block.is_some() && signed {
+      return Err(DnsQueryWriteError::SignedPadding);
+    }
+  }
+
+  /* OPT record to pad, without its earlier Padding option */
+  let edns = padding_block.map(|_| {
+    let mut edns = find_edns(additional)
+      .unwrap_or_else(|| DnsQueryEdns::new(DEFAULT_UDP_PAYLOAD_SIZE));
+    edns.options.retain(|option| option.code() != DnsQueryEdnsOptionCode::Padding);
+    edns
+  });
+  let additional: Vec<&DnsQueryResourceRecord> = additional.iter()
+    .filter(|record| edns.is_none() || record.type_ != DnsQueryType::Opt)
+    .collect();
+
+  /* header */ {
+    write_header(header,
+                 [question.len(), answer.len(), authority.len(),
+                   additional.len() + usize::from(edns.is_some())],
+                 &mut result)?;
+  }
+
+  /* question */ {
+    for question in question {
+      question.write_to(&mut result, &mut offsets)?;
+    }
+  }
+
+  /* answer, authority and additional */ {
+    for record in answer.iter().chain(authority.iter()).chain(additional) {
+      record.write_to(&mut result, &mut offsets)?;
+    }
+  }
+
+  /* padded OPT record */ {
+    if let (Some(mut edns), Some(block)) = (edns, padding_block) {
+      let mut opt = vec![];
+      DnsQueryResourceRecord::from(&edns).write_to(&mut opt, &mut None)?;
+
+      /* The Padding option adds 4 bytes of code and length to its data */
+      let len = result.len() + opt.len() + 4;
+      let padding = u16::try_from((block - len % block) % block)?;
+      edns.options.push(DnsQueryEdnsOption::Padding(padding));
+      DnsQueryResourceRecord::from(&edns).write_to(&mut result, &mut offsets)?;
+    }
+  }
+
+  result.shrink_to_fit();
+  Ok(result)
+}
+
+/// EDNS view of the first OPT record of `additional`.
+fn find_edns(additional: &[DnsQueryResourceRecord]) -> Option<DnsQueryEdns> {
+  additional.iter().find_map(DnsQueryEdns::from_record)
+}
+
+/// Replace every OPT record of `additional` with `edns`.
+pub(crate) fn replace_edns(additional: &mut Vec<DnsQueryResourceRecord>, edns: &DnsQueryEdns) {
+  additional.retain(|record| record.type_ != DnsQueryType::Opt);
+  additional.push(edns.into());
+}
+
+/// Last record of `msg` along with its offset, `None` without records, for
+/// the TSIG and SIG(0) records that end a message.
+pub(crate) fn last_record(msg: &[u8]
+) -> Result<Option<(usize, DnsQueryResourceRecord)>, DnsQueryParseError> {
+  Ok(records(msg)?.pop())
+}
+
+/// Records of every section of `msg`, in order and along with their offsets.
+pub(crate) fn records(msg: &[u8]
+) -> Result<Vec<(usize, DnsQueryResourceRecord)>, DnsQueryParseError> {
+  let mut iter = msg.iter();
+
+  let header: DnsQueryHeader = (msg, &mut iter).try_into()?;
+  for _ in 0..header.qd_count {
+    DnsQueryQuestion::try_from((msg, &mut iter))?;
+  }
+
+  let count = header.an_count as usize + header.ns_count as usize + header.ar_count as usize;
+  let mut records = Vec::with_capacity(count);
+  for _ in 0..count {
+    let start = msg.len() - iter.len();
+    records.push((start, DnsQueryResourceRecord::try_from((msg, &mut iter))?));
+  }
+
+  if iter.len() != 0 {
+    return Err(DnsQueryParseError::at(msg, &iter, DnsQueryParseErrorKind::TrailingBytes));
+  }
+  Ok(records)
+}
+
+/// Append `header` to `vec`, with section counts taken from `counts` instead
+/// of the ones stored in `header`.
+fn write_header(header: &DnsQueryHeader, counts: [usize; 4], vec: &mut Vec<u8>
+) -> Result<(), DnsQueryWriteError> {
+  let mut bytes: [u8; 12] = header.into();
+  for (i, count) in counts.iter().enumerate() {
+    let count = u16::try_from(*count)?;
+    bytes[4 + 2 * i..6 + 2 * i].copy_from_slice(&count.to_be_bytes());
+  }
+  vec.extend_from_slice(&bytes);
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::dns_query::dns_query_2_resource_record_opt::DnsQueryExtendedErrorCode;
+  use crate::dns_query::dns_query_tsig::{DnsQueryTsig, DnsQueryTsigAlgorithm, DnsQueryTsigKey};
+  use data_encoding::HEXLOWER;
+
+  /// Response to `example.com A` with two answers, a name server and its
+  /// address, all names compressed.
+  const RESPONSE: &[u8] = b"123481800001000200010001\
+076578616d706c6503636f6d0000010001\
+c00c000100010000012c0004c0000201\
+c00c000100010000012c0004c0000202\
+c00c000200010000012c0006036e7331c00c\
+c049000100010000012c0004c0000235";
+
+  fn response() -> Vec<u8> {
+    HEXLOWER.decode(RESPONSE).unwrap()
+  }
+
+  fn parse(bytes: &[u8]) -> Result<DnsRespondQuery<'static>, DnsQueryParseError> {
+    DnsRespondQuery::try_from(&mut bytes.iter())
+  }
+
+  #[test]
+  fn every_section() {
+    let response = parse(&response()).unwrap();
+    assert_eq!(response.question.len(), 1);
+
+    let answer: Vec<String> = response.answer.iter()
+      .map(|record| format!("{} {}", record.name, record.r_data))
+      .collect();
+    assert_eq!(answer, ["example.com 192.0.2.1", "example.com 192.0.2.2"]);
+
+    assert_eq!(response.authority.len(), 1);
+    assert_eq!(response.authority[0].type_, DnsQueryType::Ns);
+    assert_eq!(response.authority[0].r_data.to_string(), "ns1.example.com.");
+
+    assert_eq!(response.additional.len(), 1);
+    assert_eq!(response.additional[0].name, "ns1.example.com");
+    assert_eq!(response.additional[0].r_data.to_string(), "192.0.2.53");
+  }
+
+  #[test]
+  fn empty_sections() {
+    let mut bytes = response();
+    bytes[6..12].copy_from_slice(&[0; 6]);
+    bytes.truncate(29);
+
+    let response = parse(&bytes).unwrap();
+    assert_eq!(response.question.len(), 1);
+    assert!(response.answer.is_empty());
+    assert!(response.authority.is_empty());
+    assert!(response.additional.is_empty());
+  }
+
+  #[test]
+  fn counts_must_match() {
+    /* One more additional record than the message holds */
+    let mut bytes = response();
+    bytes[11] = 2;
+    assert_eq!(parse(&bytes).err(),
+               Some(DnsQueryParseError { offset: 95, kind: DnsQueryParseErrorKind::Truncated }));
+
+    /* One fewer, leaving the last record unparsed */
+    bytes[11] = 0;
+    assert_eq!(parse(&bytes).err(),
+               Some(DnsQueryParseError { offset: 79,
+                                         kind: DnsQueryParseErrorKind::TrailingBytes }));
+
+    /* A stray byte after the last record */
+    let mut bytes = response();
+    bytes.push(0);
+    assert_eq!(parse(&bytes).err(),
+               Some(DnsQueryParseError { offset: 95,
+                                         kind: DnsQueryParseErrorKind::TrailingBytes }));
+  }
+
+  #[test]
+  fn round_trip() {
+    let bytes = response();
+    let response = parse(&bytes).unwrap();
+    assert_eq!(response.to_vec(true), Ok(bytes.clone()));
+
+    /* Every name in full */
+    let uncompressed = response.to_vec(false).unwrap();
+    assert_eq!(HEXLOWER.encode(&uncompressed), "123481800001000200010001\
+076578616d706c6503636f6d0000010001\
+076578616d706c6503636f6d00000100010000012c0004c0000201\
+076578616d706c6503636f6d00000100010000012c0004c0000202\
+076578616d706c6503636f6d00000200010000012c0011036e7331076578616d706c6503636f6d00\
+036e7331076578616d706c6503636f6d00000100010000012c0004c0000235");
+    let reparsed = parse(&uncompressed).unwrap();
+    assert_eq!(reparsed.to_vec(true), Ok(bytes));
+  }
+
+  /// Padding options of the message `bytes`.
+  fn padding(bytes: &[u8]) -> Vec<DnsQueryEdnsOption> {
+    parse(bytes).unwrap().edns().unwrap().options.into_iter()
+      .filter(|option| option.code() == DnsQueryEdnsOptionCode::Padding)
+      .collect()
+  }
+
+  #[test]
+  fn padded_query() {
+    /* An OPT record is added to a query without one: 29 bytes of query, 11
+       of OPT record and 4 of option code and length, padded to 128 */
+    let query = DnsRequestQuery::from_url("example.com".into());
+    assert!(query.edns().is_none());
+    for compress in [false, true] {
+      let bytes = query.to_padded_vec(compress).unwrap();
+      assert_eq!(bytes.len(), 128);
+      assert_eq!(padding(&bytes), [DnsQueryEdnsOption::Padding(128 - 29 - 11 - 4)]);
+    }
+
+    /* An earlier Padding option is replaced, other options are kept */
+    let mut edns = DnsQueryEdns::new(1232);
+    edns.options.push(DnsQueryEdnsOption::Padding(300));
+    edns.options.push(DnsQueryEdnsOption::Nsid(vec![]));
+    let mut query = DnsRequestQuery::from_url("example.com".into());
+    query.set_edns(&edns);
+    let bytes = query.to_padded_vec(false).unwrap();
+    assert_eq!(bytes.len(), 128);
+    assert_eq!(padding(&bytes), [DnsQueryEdnsOption::Padding(128 - 29 - 11 - 4 - 4)]);
+    let edns = parse(&bytes).unwrap().edns().unwrap();
+    assert_eq!(edns.udp_payload_size, 1232);
+    assert_eq!(edns.nsid(), Some(&[][..]));
+  }
+
+  #[test]
+  fn padded_response() {
+    let response = parse(&response()).unwrap();
+    for compress in [false, true] {
+      let bytes = response.to_padded_vec(compress).unwrap();
+      assert_eq!(bytes.len(), 468);
+      assert_eq!(padding(&bytes).len(), 1);
+
+      /* Every other record is kept, with the OPT record last */
+      let padded = parse(&bytes).unwrap();
+      assert_eq!(padded.answer.len(), 2);
+      assert_eq!(padded.additional.len(), 2);
+      assert_eq!(padded.additional[1].type_, DnsQueryType::Opt);
+    }
+  }
+
+  #[test]
+  fn signed_message_is_not_padded() {
+    let key = DnsQueryTsigKey::new("key.example.", DnsQueryTsigAlgorithm::HmacSha256, b"secret");
+    let query = DnsRequestQuery::from_url("example.com".into()).to_vec(false).unwrap();
+    let signed = DnsQueryTsig::new(&key).sign_at(&query, 1_000_000).unwrap();
+    let signed = parse(&signed).unwrap();
+    assert_eq!(signed.to_padded_vec(false), Err(DnsQueryWriteError::SignedPadding));
+    assert!(signed.to_vec(false).is_ok());
+  }
+
+  #[test]
+  fn extended_errors_and_nsid() {
+    let mut response = parse(&response()).unwrap();
+    assert!(response.extended_errors().is_empty());
+    assert_eq!(response.nsid(), None);
+
+    /* Several errors, and a single NSID, in one OPT record */
+    for (info_code, extra_text) in [(DnsQueryExtendedErrorCode::StaleAnswer, ""),
+                                    (DnsQueryExtendedErrorCode::NoReachableAuthority, "timeout")] {
+      response.add_extended_error(DnsQueryExtendedError {
+        info_code, extra_text: extra_text.to_owned(),
+      });
+    }
+    response.set_nsid(b"ns1".to_vec());
+    response.set_nsid(b"ns2".to_vec());
+
+    let response = parse(&response.to_vec(true).unwrap()).unwrap();
+    assert_eq!(response.additional.len(), 2);
+    let errors: Vec<String> = response.extended_errors().iter().map(ToString::to_string).collect();
+    assert_eq!(errors, ["3 (StaleAnswer)", "22 (NoReachableAuthority): timeout"]);
+    assert_eq!(response.nsid(), Some(b"ns2".to_vec()));
+  }
+}
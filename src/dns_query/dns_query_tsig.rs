@@ -0,0 +1,564 @@
+use crate::dns_query::dns_query_2_resource_record::DnsQueryResourceRecord;
+use crate::dns_query::dns_query_2_resource_record_rdata::DnsQueryResourceRecordRDataType;
+use crate::dns_query::dns_query_2_resource_record_opt::DnsQueryRcode;
+use crate::dns_query::dns_query_dnssec::name_eq;
+use crate::dns_query::dns_query_error::DnsQueryTsigError;
+use crate::dns_query::dns_query_struct::records;
+use crate::dns_query::utils::{str_to_canonical_vec, trim_root, DnsQueryClass, DnsQueryType};
+use ring::hmac;
+use std::convert::TryFrom;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/*
+ *  Reference:
+ *  - [RFC 8945](https://tools.ietf.org/html/rfc8945)
+ */
+
+/// Fudge of signed messages, in seconds.
+const DEFAULT_FUDGE: u16 = 300;
+
+/// Unsigned messages in a row accepted within a multi-message response.
+const MAX_UNSIGNED: usize = 99;
+
+/// HMAC algorithms of TSIG, see RFC 8945 §6.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DnsQueryTsigAlgorithm {
+  HmacSha256,
+  HmacSha384,
+  HmacSha512,
+}
+
+impl DnsQueryTsigAlgorithm {
+  /// Name of the algorithm, as sent in TSIG records.
+  pub const fn name(&self) -> &'static str {
+    match self {
+      Self::HmacSha256 => "hmac-sha256",
+      Self::HmacSha384 => "hmac-sha384",
+      Self::HmacSha512 => "hmac-sha512",
+    }
+  }
+
+  /// Algorithm with `name`, ignoring case.
+  pub fn from_name(name: &str) -> Option<Self> {
+    [Self::HmacSha256, Self::HmacSha384, Self::HmacSha512].iter()
+      .find(|algorithm| name_eq(algorithm.name(), name))
+      .copied()
+  }
+
+  /// Length of an untruncated MAC.
+  pub const fn mac_len(&self) -> usize {
+    match self {
+      Self::HmacSha256 => 32,
+      Self::HmacSha384 => 48,
+      Self::HmacSha512 => 64,
+    }
+  }
+
+  const fn hmac(&self) -> hmac::Algorithm {
+    match self {
+      Self::HmacSha256 => hmac::HMAC_SHA256,
+      Self::HmacSha384 => hmac::HMAC_SHA384,
+      Self::HmacSha512 => hmac::HMAC_SHA512,
+    }
+  }
+}
+
+/// Shared secret of a TSIG key.
+#[derive(Clone)]
+pub struct DnsQueryTsigKey {
+  name: String,
+  algorithm: DnsQueryTsigAlgorithm,
+  key: hmac::Key,
+  /// Length MACs are truncated to, and the shortest accepted
+  mac_len: usize,
+}
+
+impl std::fmt::Debug for DnsQueryTsigKey {
+  /* Keep the secret out of logs */
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("DnsQueryTsigKey")
+      .field("name", &self.name)
+      .field("algorithm", &self.algorithm)
+      .field("mac_len", &self.mac_len)
+      .finish()
+  }
+}
+
+impl DnsQueryTsigKey {
+  /// Key `name` with `algorithm` and `secret`, sending full MACs.
+  pub fn new(name: &str, algorithm: DnsQueryTsigAlgorithm, secret: &[u8]) -> Self {
+    Self {
+      name: trim_root(name).to_owned(),
+      algorithm,
+      key: hmac::Key::new(algorithm.hmac(), secret),
+      mac_len: algorithm.mac_len(),
+    }
+  }
+
+  /// Truncate MACs to `mac_len` bytes, and accept no shorter ones, see
+  /// RFC 8945 §5.2.2.1. `None` if that is shorter than the larger of 10
+  /// bytes and half the untruncated MAC, or longer than the latter.
+  pub fn truncated(mut self, mac_len: usize) -> Option<Self> {
+    let full = self.algorithm.mac_len();
+    if mac_len < 10.max(full / 2) || mac_len > full {
+      return None;
+    }
+    self.mac_len = mac_len;
+    Some(self)
+  }
+
+  pub fn name(&self) -> &str {
+    &self.name
+  }
+
+  pub const fn algorithm(&self) -> DnsQueryTsigAlgorithm {
+    self.algorithm
+  }
+}
+
+/// TSIG state of an exchange, from the request to the last message of its
+/// response, see RFC 8945 §5.3.
+///
+/// The same context signs or verifies the request, then every message of
+/// the response: the first answer covers the MAC of the request, and later
+/// ones of a zone transfer chain to the MAC before them.
+#[derive(Debug, Clone)]
+pub struct DnsQueryTsig<'a> {
+  key: &'a DnsQueryTsigKey,
+  fudge: u16,
+  /// MAC of the last message signed or verified
+  prior_mac: Option<Vec<u8>>,
+  /// Whether a message of the response has been signed or verified
+  chained: bool,
+  /// Unsigned messages since the last signed one, see RFC 8945 §5.3.1
+  unsigned: Vec<Vec<u8>>,
+  /// Time signed of the last message verified, echoed after BADTIME
+  time_signed: Option<u64>,
+}
+
+impl<'a> DnsQueryTsig<'a> {
+  pub const fn new(key: &'a DnsQueryTsigKey) -> Self {
+    Self {
+      key,
+      fudge: DEFAULT_FUDGE,
+      prior_mac: None,
+      chained: false,
+      unsigned: Vec::new(),
+      time_signed: None,
+    }
+  }
+
+  pub const fn fudge(mut self, fudge: u16) -> Self {
+    self.fudge = fudge;
+    self
+  }
+
+  /// Unsigned messages since the last signed one, which must be none after
+  /// the last message of a response.
+  pub fn unsigned_messages(&self) -> usize {
+    self.unsigned.len()
+  }
+
+  /// Sign `msg` at the current time, see [`Self::sign_at`].
+  pub fn sign(&mut self, msg: &[u8]) -> Result<Vec<u8>, DnsQueryTsigError> {
+    self.sign_at(msg, now())
+  }
+
+  /// Append a TSIG record to `msg`, a serialized message, with `now` as
+  /// seconds since epoch.
+  pub fn sign_at(&mut self, msg: &[u8], now: u64) -> Result<Vec<u8>, DnsQueryTsigError> {
+    self.sign_error_at(msg, now, DnsQueryRcode::NoErr)
+  }
+
+  /// Append a TSIG record reporting `error` to `msg`, the answer to a
+  /// request that failed verification, see RFC 8945 §5.3.2.
+  ///
+  /// The record is unsigned after BADKEY and BADSIG. After BADTIME, it
+  /// echoes the time signed of the request and carries `now` in its other
+  /// data, see RFC 8945 §5.2.3.
+  pub fn sign_error_at(&mut self, msg: &[u8], now: u64, error: DnsQueryRcode
+  ) -> Result<Vec<u8>, DnsQueryTsigError> {
+    if msg.len() < 12 {
+      return Err(DnsQueryTsigError::Malformed);
+    }
+    let original_id = u16::from_be_bytes([msg[0], msg[1]]);
+    let (time_signed, other_data) = match self.time_signed {
+      Some(time_signed) if error == DnsQueryRcode::BadTime => {
+        (time_signed, now.to_be_bytes()[2..].to_vec())
+      }
+      _ => (now, vec![]),
+    };
+
+    let mut tsig = Tsig {
+      algorithm: self.key.algorithm.name().to_owned(),
+      time_signed,
+      fudge: self.fudge,
+      mac: vec![],
+      original_id,
+      error,
+      other_data,
+    };
+    if error != DnsQueryRcode::BadKey && error != DnsQueryRcode::BAD_SIG {
+      let mut mac = self.mac(msg, &tsig)?;
+      mac.truncate(self.key.mac_len);
+      tsig.mac = mac;
+      self.chain(tsig.mac.clone());
+    }
+
+    let mut result = msg.to_vec();
+    DnsQueryResourceRecord {
+      name: self.key.name.clone(),
+      type_: DnsQueryType::TSig,
+      class: DnsQueryClass::QClsAny,
+      ttl: 0,
+      rd_length: 0,
+      r_data: tsig.into(),
+    }.write_to(&mut result, &mut None).map_err(|_| DnsQueryTsigError::Malformed)?;
+
+    let ar_count = u16::from_be_bytes([result[10], result[11]]).checked_add(1)
+      .ok_or(DnsQueryTsigError::Malformed)?;
+    result[10..12].copy_from_slice(&ar_count.to_be_bytes());
+    Ok(result)
+  }
+
+  /// Send `msg` of a multi-message response without TSIG, to be covered by
+  /// the next signed one. Up to 99 messages in a row may be unsigned.
+  pub fn add_unsigned(&mut self, msg: &[u8]) -> Result<(), DnsQueryTsigError> {
+    if !self.chained {
+      return Err(DnsQueryTsigError::NoTsig);
+    }
+    if self.unsigned.len() >= MAX_UNSIGNED {
+      return Err(DnsQueryTsigError::TooManyUnsigned);
+    }
+    self.unsigned.push(msg.to_vec());
+    Ok(())
+  }
+
+  /// Verify `msg` at the current time, see [`Self::verify_at`].
+  pub fn verify(&mut self, msg: &[u8]) -> Result<(), DnsQueryTsigError> {
+    self.verify_at(msg, now())
+  }
+
+  /// Verify the TSIG record ending `msg`, a serialized message, with `now`
+  /// as seconds since epoch, see RFC 8945 §5.2 and §5.4.
+  ///
+  /// Within a multi-message response, up to 99 messages in a row may come
+  /// without TSIG; they are covered by the next signed one.
+  pub fn verify_at(&mut self, msg: &[u8], now: u64) -> Result<(), DnsQueryTsigError> {
+    let mut records = records(msg).map_err(|_| DnsQueryTsigError::Malformed)?;
+    let last = records.pop();
+
+    /* A TSIG record anywhere but last is a FORMERR, see RFC 8945 §5.1 */
+    if records.iter().any(|(_, record)| record.type_ == DnsQueryType::TSig) {
+      return Err(DnsQueryTsigError::Malformed);
+    }
+
+    let (start, record) = match last {
+      Some((start, record)) if record.type_ == DnsQueryType::TSig => (start, record),
+      _ if self.chained => return self.add_unsigned(msg),
+      _ => return Err(DnsQueryTsigError::NoTsig),
+    };
+    let tsig = Tsig::try_from(record.r_data).map_err(|_| DnsQueryTsigError::Malformed)?;
+
+    if !name_eq(&record.name, &self.key.name)
+      || DnsQueryTsigAlgorithm::from_name(&tsig.algorithm) != Some(self.key.algorithm) {
+      return Err(DnsQueryTsigError::BadKey);
+    }
+    if tsig.error != DnsQueryRcode::NoErr && tsig.mac.is_empty() {
+      return Err(DnsQueryTsigError::Rejected(tsig.error));
+    }
+
+    /* Message as it was signed */
+    let mut unsigned_msg = msg[..start].to_vec();
+    unsigned_msg[..2].copy_from_slice(&tsig.original_id.to_be_bytes());
+    /* The TSIG record is the last of the additional section */
+    let ar_count = u16::from_be_bytes([msg[10], msg[11]]).checked_sub(1)
+      .ok_or(DnsQueryTsigError::Malformed)?;
+    unsigned_msg[10..12].copy_from_slice(&ar_count.to_be_bytes());
+
+    /* MAC, see RFC 8945 §5.2.2 */ {
+      let full = self.key.algorithm.mac_len();
+      if tsig.mac.len() > full || tsig.mac.len() < 10.max(full / 2) {
+        return Err(DnsQueryTsigError::Malformed);
+      }
+      let expected = self.mac(&unsigned_msg, &tsig)?;
+      /* Compare in constant time */
+      let diff = expected.iter().zip(&tsig.mac).fold(0, |diff, (a, b)| diff | (a ^ b));
+      if diff != 0 {
+        return Err(DnsQueryTsigError::BadSig);
+      }
+    }
+
+    /* The MAC is good, so an answer reporting a later error covers it */
+    let mac_len = tsig.mac.len();
+    self.time_signed = Some(tsig.time_signed);
+    self.chain(tsig.mac);
+
+    /* Time, see RFC 8945 §5.2.3 */ {
+      let skew = now.max(tsig.time_signed) - now.min(tsig.time_signed);
+      if skew > u64::from(tsig.fudge) {
+        return Err(DnsQueryTsigError::BadTime);
+      }
+    }
+
+    /* Truncation, see RFC 8945 §5.2.4 */ {
+      if mac_len < self.key.mac_len {
+        return Err(DnsQueryTsigError::BadTrunc);
+      }
+    }
+
+    if tsig.error != DnsQueryRcode::NoErr {
+      return Err(DnsQueryTsigError::Rejected(tsig.error));
+    }
+    Ok(())
+  }
+
+  /// Untruncated MAC of `msg`, a message without TSIG, with the variables
+  /// of `tsig`.
+  fn mac(&self, msg: &[u8], tsig: &Tsig) -> Result<Vec<u8>, DnsQueryTsigError> {
+    let mut ctx = hmac::Context::with_key(&self.key.key);
+
+    if let Some(prior_mac) = &self.prior_mac {
+      let len = u16::try_from(prior_mac.len()).map_err(|_| DnsQueryTsigError::Malformed)?;
+      ctx.update(&len.to_be_bytes());
+      ctx.update(prior_mac);
+    }
+    for unsigned in &self.unsigned {
+      ctx.update(unsigned);
+    }
+    ctx.update(msg);
+
+    let mut variables = vec![];
+    if !self.chained {
+      str_to_canonical_vec(&self.key.name, &mut variables)
+        .map_err(|_| DnsQueryTsigError::Malformed)?;
+      variables.extend_from_slice(&u16::from(&DnsQueryClass::QClsAny).to_be_bytes());
+      variables.extend_from_slice(&0_u32.to_be_bytes());
+      str_to_canonical_vec(&tsig.algorithm, &mut variables)
+        .map_err(|_| DnsQueryTsigError::Malformed)?;
+    }
+    /* Timers */
+    variables.extend_from_slice(&tsig.time_signed.to_be_bytes()[2..]);
+    variables.extend_from_slice(&tsig.fudge.to_be_bytes());
+    if !self.chained {
+      variables.extend_from_slice(&u16::from(&tsig.error).to_be_bytes());
+      let len = u16::try_from(tsig.other_data.len()).map_err(|_| DnsQueryTsigError::Malformed)?;
+      variables.extend_from_slice(&len.to_be_bytes());
+      variables.extend_from_slice(&tsig.other_data);
+    }
+    ctx.update(&variables);
+
+    Ok(ctx.sign().as_ref().to_vec())
+  }
+
+  /// Move on past a message signed with `mac`.
+  fn chain(&mut self, mac: Vec<u8>) {
+    /* Only answers chain, a request is covered in full */
+    self.chained = self.prior_mac.is_some();
+    self.prior_mac = Some(mac);
+    self.unsigned.clear();
+  }
+}
+
+/// Fields of TSIG r_data.
+struct Tsig {
+  algorithm: String,
+  time_signed: u64,
+  fudge: u16,
+  mac: Vec<u8>,
+  original_id: u16,
+  error: DnsQueryRcode,
+  other_data: Vec<u8>,
+}
+
+impl TryFrom<DnsQueryResourceRecordRDataType> for Tsig {
+  type Error = DnsQueryResourceRecordRDataType;
+
+  fn try_from(r_data: DnsQueryResourceRecordRDataType) -> Result<Self, Self::Error> {
+    match r_data {
+      DnsQueryResourceRecordRDataType::TSig {
+        algorithm, time_signed, fudge, mac, original_id, error, other_data,
+      } => Ok(Self { algorithm, time_signed, fudge, mac, original_id, error, other_data }),
+      r_data => Err(r_data),
+    }
+  }
+}
+
+impl From<Tsig> for DnsQueryResourceRecordRDataType {
+  fn from(tsig: Tsig) -> Self {
+    let Tsig { algorithm, time_signed, fudge, mac, original_id, error, other_data } = tsig;
+    Self::TSig { algorithm, time_signed, fudge, mac, original_id, error, other_data }
+  }
+}
+
+/// Seconds since epoch.
+fn now() -> u64 {
+  SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::dns_query::dns_query_struct::{last_record, DnsRequestQuery};
+  use data_encoding::HEXLOWER;
+
+  fn key() -> DnsQueryTsigKey {
+    DnsQueryTsigKey::new("key.example.", DnsQueryTsigAlgorithm::HmacSha256, b"secret")
+  }
+
+  fn query() -> Vec<u8> {
+    DnsRequestQuery::from_url("example.com".into()).to_vec(false).unwrap()
+  }
+
+  /// TSIG record ending `msg`.
+  fn tsig_of(msg: &[u8]) -> Tsig {
+    let (_, record) = last_record(msg).unwrap().unwrap();
+    Tsig::try_from(record.r_data).ok().unwrap()
+  }
+
+  #[test]
+  fn bad_time() {
+    let key = key();
+    let mut client = DnsQueryTsig::new(&key);
+    let mut server = DnsQueryTsig::new(&key);
+
+    let request = client.sign_at(&query(), 1_000_000).unwrap();
+    assert_eq!(server.verify_at(&request, 1_001_000), Err(DnsQueryTsigError::BadTime));
+
+    /* Signed over the request MAC, with both times */
+    let response = server.sign_error_at(&query(), 1_001_000, DnsQueryRcode::BadTime).unwrap();
+    let tsig = tsig_of(&response);
+    assert_eq!(tsig.time_signed, 1_000_000);
+    assert_eq!(tsig.other_data, 1_001_000_u64.to_be_bytes()[2..]);
+    assert_eq!(client.verify_at(&response, 1_000_001),
+               Err(DnsQueryTsigError::Rejected(DnsQueryRcode::BadTime)));
+  }
+
+  #[test]
+  fn mac_of_request() {
+    /* HMAC-SHA256 over the message and the TSIG variables of RFC 8945
+       §4.3.3, computed in Python with `msg` the bytes of `query()`:
+         var = b"\x03key\x07example\x00" + pack("!HI", 255, 0)
+             + b"\x0bhmac-sha256\x00" + (1_000_000).to_bytes(6, "big")
+             + pack("!HHH", 300, 0, 0)
+         hmac.new(b"secret", msg + var, hashlib.sha256).hexdigest() */
+    let key = key();
+    let request = DnsQueryTsig::new(&key).sign_at(&query(), 1_000_000).unwrap();
+    let tsig = tsig_of(&request);
+    assert_eq!(HEXLOWER.encode(&tsig.mac),
+               "098bdd96aef04604c58323a89fef3c609ede52e6cd9bed71eb68efa9a1041f26");
+    assert_eq!((tsig.time_signed, tsig.fudge, tsig.original_id), (1_000_000, 300, 0));
+    assert_eq!(&request[10..12], &[0, 1]);
+  }
+
+  #[test]
+  fn sign_and_verify() {
+    use DnsQueryTsigAlgorithm::*;
+
+    for algorithm in [HmacSha256, HmacSha384, HmacSha512] {
+      let key = DnsQueryTsigKey::new("key.example", algorithm, b"secret");
+      let mut client = DnsQueryTsig::new(&key);
+      let mut server = DnsQueryTsig::new(&key);
+
+      let request = client.sign_at(&query(), 1_000_000).unwrap();
+      assert_eq!(tsig_of(&request).mac.len(), algorithm.mac_len());
+      assert_eq!(server.verify_at(&request, 1_000_300), Ok(()), "{:?}", algorithm);
+
+      /* The answer covers the request MAC */
+      let response = server.sign_at(&query(), 1_000_300).unwrap();
+      assert_eq!(DnsQueryTsig::new(&key).verify_at(&response, 1_000_300),
+                 Err(DnsQueryTsigError::BadSig));
+      assert_eq!(client.verify_at(&response, 1_000_000), Ok(()), "{:?}", algorithm);
+    }
+  }
+
+  #[test]
+  fn verify_failures() {
+    use DnsQueryTsigError::*;
+
+    let key = key();
+    let request = DnsQueryTsig::new(&key).sign_at(&query(), 1_000_000).unwrap();
+    let verify = |key: &DnsQueryTsigKey, msg: &[u8]| {
+      DnsQueryTsig::new(key).verify_at(msg, 1_000_000)
+    };
+
+    assert_eq!(verify(&key, &query()), Err(NoTsig));
+    assert_eq!(verify(&DnsQueryTsigKey::new("other.example", DnsQueryTsigAlgorithm::HmacSha256,
+                                            b"secret"), &request), Err(BadKey));
+    assert_eq!(verify(&DnsQueryTsigKey::new("key.example", DnsQueryTsigAlgorithm::HmacSha512,
+                                            b"secret"), &request), Err(BadKey));
+    assert_eq!(verify(&DnsQueryTsigKey::new("KEY.example.", DnsQueryTsigAlgorithm::HmacSha256,
+                                            b"other"), &request), Err(BadSig));
+
+    let mut tampered = request.clone();
+    tampered[13] ^= 0x20;
+    assert_eq!(verify(&key, &tampered), Err(BadSig));
+
+    /* The ID may change on the way, the rest of the header may not */
+    let mut forwarded = request.clone();
+    forwarded[..2].copy_from_slice(&[0x12, 0x34]);
+    assert_eq!(verify(&key, &forwarded), Ok(()));
+    forwarded[2] ^= 0x01;
+    assert_eq!(verify(&key, &forwarded), Err(BadSig));
+
+    /* The TSIG record must come last */
+    let mut misplaced = request.clone();
+    misplaced[11] += 1;
+    misplaced.extend_from_slice(&HEXLOWER.decode(b"0000010001000000000004c0000201").unwrap());
+    assert_eq!(verify(&key, &misplaced), Err(Malformed));
+
+    /* Answers to failed requests */
+    let mut server = DnsQueryTsig::new(&key);
+    for error in [DnsQueryRcode::BadKey, DnsQueryRcode::BAD_SIG] {
+      let response = server.sign_error_at(&query(), 1_000_000, error).unwrap();
+      assert!(tsig_of(&response).mac.is_empty());
+      assert_eq!(DnsQueryTsig::new(&key).verify_at(&response, 1_000_000), Err(Rejected(error)));
+    }
+  }
+
+  #[test]
+  fn truncation() {
+    let key = key();
+    assert!(key.clone().truncated(15).is_none());
+    assert!(key.clone().truncated(33).is_none());
+    let truncated = key.clone().truncated(16).unwrap();
+
+    let request = DnsQueryTsig::new(&truncated).sign_at(&query(), 1_000_000).unwrap();
+    assert_eq!(tsig_of(&request).mac.len(), 16);
+    assert_eq!(DnsQueryTsig::new(&truncated).verify_at(&request, 1_000_000), Ok(()));
+    assert_eq!(DnsQueryTsig::new(&key).verify_at(&request, 1_000_000),
+               Err(DnsQueryTsigError::BadTrunc));
+  }
+
+  #[test]
+  fn multi_message_response() {
+    let key = key();
+    let mut client = DnsQueryTsig::new(&key);
+    let mut server = DnsQueryTsig::new(&key);
+
+    let request = client.sign_at(&query(), 1_000_000).unwrap();
+    server.verify_at(&request, 1_000_000).unwrap();
+
+    /* Signed, unsigned, then signed again */
+    let first = server.sign_at(&query(), 1_000_000).unwrap();
+    server.add_unsigned(&query()).unwrap();
+    assert_eq!(server.unsigned_messages(), 1);
+    let last = server.sign_at(&query(), 1_000_001).unwrap();
+    assert_eq!(server.unsigned_messages(), 0);
+
+    assert_eq!(client.verify_at(&first, 1_000_000), Ok(()));
+    assert_eq!(client.verify_at(&query(), 1_000_000), Ok(()));
+    assert_eq!(client.unsigned_messages(), 1);
+    assert_eq!(client.verify_at(&last, 1_000_001), Ok(()));
+
+    /* Messages may not be dropped, nor be unsigned for too long */
+    let _dropped = server.sign_at(&query(), 1_000_002).unwrap();
+    let after = server.sign_at(&query(), 1_000_002).unwrap();
+    assert_eq!(client.verify_at(&after, 1_000_002), Err(DnsQueryTsigError::BadSig));
+
+    for _ in 0..MAX_UNSIGNED {
+      server.add_unsigned(&query()).unwrap();
+    }
+    assert_eq!(server.add_unsigned(&query()), Err(DnsQueryTsigError::TooManyUnsigned));
+  }
+}
@@ -0,0 +1,420 @@
+use crate::dns_query::dns_query_2_resource_record_rdata::DnsQueryResourceRecordRDataType;
+use crate::dns_query::dns_query_dnssec::name_eq;
+use crate::dns_query::dns_query_error::{DnsQueryKeyFileError, DnsQueryTsigError};
+use crate::dns_query::dns_query_struct::DnsRespondQuery;
+use crate::dns_query::dns_query_tsig::{DnsQueryTsigAlgorithm, DnsQueryTsigKey};
+use crate::dns_query::utils::DnsQueryType;
+use data_encoding::BASE64;
+
+/*
+ *  Reference:
+ *  - [RFC 8945](https://tools.ietf.org/html/rfc8945)
+ *  - [BIND 9 `key` statement](https://bind9.readthedocs.io/en/latest/reference.html#key-block-grammar)
+ */
+
+/*
+Key file format, as written by `tsig-keygen`
+
+  # comment, also // comment and /* comment */
+  key "name" {
+    algorithm hmac-sha256;
+    secret "base64";
+  };
+
+`algorithm` may carry the MAC length in bits, as in `hmac-sha256-128`.
+*/
+
+/// TSIG keys, looked up by name and algorithm.
+#[derive(Debug, Clone, Default)]
+pub struct DnsQueryTsigKeyring {
+  keys: Vec<DnsQueryTsigKey>,
+}
+
+impl DnsQueryTsigKeyring {
+  pub const fn new() -> Self {
+    Self { keys: Vec::new() }
+  }
+
+  /// Keyring with the keys of `text`, see [`Self::add_key_file`].
+  pub fn from_key_file(text: &str) -> Result<Self, DnsQueryKeyFileError> {
+    let mut keyring = Self::new();
+    keyring.add_key_file(text)?;
+    Ok(keyring)
+  }
+
+  /// Add every `key` statement of `text`, a BIND key file, and return how
+  /// many there were. Nothing is added if any of them is malformed.
+  pub fn add_key_file(&mut self, text: &str) -> Result<usize, DnsQueryKeyFileError> {
+    let keys = parse_key_file(text)?;
+    let count = keys.len();
+    for key in keys {
+      self.insert(key);
+    }
+    Ok(count)
+  }
+
+  /// Add `key`, replacing the one with the same name and algorithm.
+  pub fn insert(&mut self, key: DnsQueryTsigKey) {
+    self.remove(key.name(), key.algorithm());
+    self.keys.push(key);
+  }
+
+  /// Remove the key `name` with `algorithm`.
+  pub fn remove(&mut self, name: &str, algorithm: DnsQueryTsigAlgorithm
+  ) -> Option<DnsQueryTsigKey> {
+    let index = self.keys.iter().position(|key| Self::matches(key, name, algorithm))?;
+    Some(self.keys.remove(index))
+  }
+
+  /// Key `name` with `algorithm`, ignoring case and a trailing dot in
+  /// `name`.
+  pub fn get(&self, name: &str, algorithm: DnsQueryTsigAlgorithm) -> Option<&DnsQueryTsigKey> {
+    self.keys.iter().find(|key| Self::matches(key, name, algorithm))
+  }
+
+  /// Keys `name` with any algorithm.
+  pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item=&'a DnsQueryTsigKey> + 'a {
+    self.keys.iter().filter(move |key| name_eq(key.name(), name))
+  }
+
+  /// Key of the TSIG record ending `query`, for a server to verify it and
+  /// sign the answer with.
+  ///
+  /// Fails with `NoTsig` if `query` is not signed, and with `BadKey` if the
+  /// key is unknown, in which case the answer carries BADKEY.
+  pub fn key_for(&self, query: &DnsRespondQuery<'_>) -> Result<&DnsQueryTsigKey, DnsQueryTsigError> {
+    let record = query.additional.last()
+      .filter(|record| record.type_ == DnsQueryType::TSig)
+      .ok_or(DnsQueryTsigError::NoTsig)?;
+    let algorithm = match &record.r_data {
+      DnsQueryResourceRecordRDataType::TSig { algorithm, .. } => algorithm,
+      _ => return Err(DnsQueryTsigError::Malformed),
+    };
+
+    DnsQueryTsigAlgorithm::from_name(algorithm)
+      .and_then(|algorithm| self.get(&record.name, algorithm))
+      .ok_or(DnsQueryTsigError::BadKey)
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item=&DnsQueryTsigKey> {
+    self.keys.iter()
+  }
+
+  pub fn len(&self) -> usize {
+    self.keys.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.keys.is_empty()
+  }
+
+  fn matches(key: &DnsQueryTsigKey, name: &str, algorithm: DnsQueryTsigAlgorithm) -> bool {
+    key.algorithm() == algorithm && name_eq(key.name(), name)
+  }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+  Word(String),
+  Quoted(String),
+  Open,
+  Close,
+  Semicolon,
+}
+
+/// Tokens of `text`, each with its line.
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, DnsQueryKeyFileError> {
+  let mut tokens = vec![];
+  let mut line = 1;
+  let mut chars = text.chars().peekable();
+
+  while let Some(c) = chars.next() {
+    match c {
+      '\n' => line += 1,
+      c if c.is_whitespace() => {}
+      '{' => tokens.push((line, Token::Open)),
+      '}' => tokens.push((line, Token::Close)),
+      ';' => tokens.push((line, Token::Semicolon)),
+
+      /* Comments */
+      '#' => while chars.peek().is_some_and(|&c| c != '\n') {
+        chars.next();
+      },
+      '/' if chars.peek() == Some(&'/') => while chars.peek().is_some_and(|&c| c != '\n') {
+        chars.next();
+      },
+      '/' if chars.peek() == Some(&'*') => {
+        let start = line;
+        chars.next();
+        let mut prev = ' ';
+        loop {
+          match chars.next() {
+            Some('/') if prev == '*' => break,
+            Some(c) => {
+              if c == '\n' {
+                line += 1;
+              }
+              prev = c;
+            }
+            None => return Err(DnsQueryKeyFileError { line: start, reason: "unterminated comment" }),
+          }
+        }
+      }
+
+      '"' => {
+        let start = line;
+        let mut s = String::new();
+        loop {
+          match chars.next() {
+            Some('"') => break,
+            Some('\\') => if let Some(c) = chars.next() {
+              s.push(c);
+            },
+            Some(c) => {
+              if c == '\n' {
+                line += 1;
+              }
+              s.push(c);
+            }
+            None => return Err(DnsQueryKeyFileError { line: start, reason: "unterminated string" }),
+          }
+        }
+        tokens.push((start, Token::Quoted(s)));
+      }
+
+      c => {
+        let mut s = c.to_string();
+        while let Some(&c) = chars.peek() {
+          if c.is_whitespace() || "{};\"#".contains(c) {
+            break;
+          }
+          s.push(c);
+          chars.next();
+        }
+        tokens.push((line, Token::Word(s)));
+      }
+    }
+  }
+
+  Ok(tokens)
+}
+
+/// Keys of the `key` statements of `text`.
+fn parse_key_file(text: &str) -> Result<Vec<DnsQueryTsigKey>, DnsQueryKeyFileError> {
+  let tokens = tokenize(text)?;
+  let last_line = tokens.last().map_or(1, |(line, _)| *line);
+  let mut tokens = tokens.into_iter();
+  let mut keys = vec![];
+
+  /* Next token, which must be there */
+  let mut next = |reason| tokens.next().ok_or(DnsQueryKeyFileError { line: last_line, reason });
+
+  loop {
+    let line = match next("expected `key`") {
+      Ok((line, Token::Word(word))) if word.eq_ignore_ascii_case("key") => line,
+      Ok((line, _)) => return Err(DnsQueryKeyFileError { line, reason: "expected `key`" }),
+      Err(_) => break,
+    };
+
+    let name = match next("missing key name")? {
+      (_, Token::Word(name)) | (_, Token::Quoted(name)) if !name.is_empty() => name,
+      (line, _) => return Err(DnsQueryKeyFileError { line, reason: "bad key name" }),
+    };
+    match next("missing `{`")? {
+      (_, Token::Open) => {}
+      (line, _) => return Err(DnsQueryKeyFileError { line, reason: "expected `{`" }),
+    }
+
+    let mut algorithm = None;
+    let mut secret = None;
+    loop {
+      let (line, clause) = match next("missing `}`")? {
+        (_, Token::Close) => break,
+        (line, Token::Word(clause)) => (line, clause.to_ascii_lowercase()),
+        (line, _) => return Err(DnsQueryKeyFileError { line, reason: "expected clause" }),
+      };
+      let value = match next("missing value")? {
+        (_, Token::Word(value)) | (_, Token::Quoted(value)) => value,
+        (line, _) => return Err(DnsQueryKeyFileError { line, reason: "missing value" }),
+      };
+      match next("missing `;`")? {
+        (_, Token::Semicolon) => {}
+        (line, _) => return Err(DnsQueryKeyFileError { line, reason: "expected `;`" }),
+      }
+
+      let slot = match clause.as_str() {
+        "algorithm" => &mut algorithm,
+        "secret" => &mut secret,
+        _ => return Err(DnsQueryKeyFileError { line, reason: "unknown clause" }),
+      };
+      if slot.replace((line, value)).is_some() {
+        return Err(DnsQueryKeyFileError { line, reason: "duplicate clause" });
+      }
+    }
+    match next("missing `;`")? {
+      (_, Token::Semicolon) => {}
+      (line, _) => return Err(DnsQueryKeyFileError { line, reason: "expected `;`" }),
+    }
+
+    let (algorithm_line, algorithm) = algorithm
+      .ok_or(DnsQueryKeyFileError { line, reason: "missing algorithm" })?;
+    let (secret_line, secret) = secret
+      .ok_or(DnsQueryKeyFileError { line, reason: "missing secret" })?;
+
+    let (algorithm, mac_bits) = parse_algorithm(&algorithm)
+      .ok_or(DnsQueryKeyFileError { line: algorithm_line, reason: "unsupported algorithm" })?;
+    let secret: String = secret.split_whitespace().collect();
+    let secret = BASE64.decode(secret.as_bytes())
+      .map_err(|_| DnsQueryKeyFileError { line: secret_line, reason: "bad secret" })?;
+
+    let key = DnsQueryTsigKey::new(&name, algorithm, &secret);
+    let key = match mac_bits {
+      None => key,
+      Some(bits) => key.truncated(bits / 8)
+        .ok_or(DnsQueryKeyFileError { line: algorithm_line, reason: "bad MAC length" })?,
+    };
+    keys.push(key);
+  }
+
+  Ok(keys)
+}
+
+/// Algorithm of `name`, along with the MAC length in bits of names such as
+/// `hmac-sha256-128`.
+fn parse_algorithm(name: &str) -> Option<(DnsQueryTsigAlgorithm, Option<usize>)> {
+  if let Some(algorithm) = DnsQueryTsigAlgorithm::from_name(name) {
+    return Some((algorithm, None));
+  }
+
+  let (name, bits) = name.rsplit_once('-')?;
+  let bits = bits.parse::<usize>().ok().filter(|bits| bits % 8 == 0)?;
+  Some((DnsQueryTsigAlgorithm::from_name(name)?, Some(bits)))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::dns_query::dns_query_struct::{last_record, DnsRequestQuery};
+  use crate::dns_query::dns_query_tsig::DnsQueryTsig;
+  use std::convert::TryFrom;
+
+  /* As written by `tsig-keygen -a hmac-sha256 tsig-key` */
+  const KEYGEN: &str = "key \"tsig-key\" {
+\talgorithm hmac-sha256;
+\tsecret \"2vgzGLzbV3Qi3q9u3xsH1dqdjj8tWEPMNzr6Jf9Gcjo=\";
+};
+";
+
+  fn error(text: &str) -> (usize, &'static str) {
+    let err = DnsQueryTsigKeyring::from_key_file(text).unwrap_err();
+    (err.line, err.reason)
+  }
+
+  /// Length of the MAC that `key` signs with.
+  fn mac_len(key: &DnsQueryTsigKey) -> usize {
+    let query = DnsRequestQuery::from_url("example.com".into()).to_vec(false).unwrap();
+    let signed = DnsQueryTsig::new(key).sign_at(&query, 1_000_000).unwrap();
+    match last_record(&signed).unwrap().unwrap().1.r_data {
+      DnsQueryResourceRecordRDataType::TSig { mac, .. } => mac.len(),
+      _ => unreachable!(),
+    }
+  }
+
+  #[test]
+  fn tsig_keygen_output() {
+    let keyring = DnsQueryTsigKeyring::from_key_file(KEYGEN).unwrap();
+    assert_eq!(keyring.len(), 1);
+    let key = keyring.get("TSIG-KEY.", DnsQueryTsigAlgorithm::HmacSha256).unwrap();
+    assert_eq!(key.name(), "tsig-key");
+    assert_eq!(mac_len(key), 32);
+  }
+
+  #[test]
+  fn comments_and_names() {
+    let text = "# hash comment
+// slash comment
+/* block
+   comment */ key first.example {
+  algorithm hmac-sha512; // trailing
+  secret \"c2VjcmV0\";
+};
+key \"second example.\" { secret \"c2Vj\ncmV0\"; algorithm HMAC-SHA256-128; };
+";
+    let keyring = DnsQueryTsigKeyring::from_key_file(text).unwrap();
+    assert_eq!(keyring.len(), 2);
+    assert!(keyring.get("first.example", DnsQueryTsigAlgorithm::HmacSha512).is_some());
+    assert!(keyring.get("first.example", DnsQueryTsigAlgorithm::HmacSha256).is_none());
+
+    /* Truncated to 128 bits */
+    let key = keyring.get("second example", DnsQueryTsigAlgorithm::HmacSha256).unwrap();
+    assert_eq!(mac_len(key), 16);
+  }
+
+  #[test]
+  fn errors_report_their_line() {
+    let key = |body: &str| format!("key k {{\n{}\n}};\n", body);
+
+    assert_eq!(error(&key("algorithm hmac-sha256;\nalgorithm hmac-sha256;\nsecret \"\";")),
+               (3, "duplicate clause"));
+    assert_eq!(error(&key("algorithm hmac-sha256;\nsecret \"\";\nsecret \"\";")),
+               (4, "duplicate clause"));
+    assert_eq!(error(&key("algorithm hmac-sha256;\nsecret \"\";\nowner me;")),
+               (4, "unknown clause"));
+    assert_eq!(error(&format!("\n{}", key("secret \"\";"))), (2, "missing algorithm"));
+    assert_eq!(error(&key("algorithm hmac-sha256;")), (1, "missing secret"));
+    assert_eq!(error(&key("algorithm hmac-md5;\nsecret \"\";")), (2, "unsupported algorithm"));
+    assert_eq!(error(&key("algorithm hmac-sha256-8;\nsecret \"\";")), (2, "bad MAC length"));
+    assert_eq!(error(&key("algorithm hmac-sha256;\n\nsecret \"not base64!\";")),
+               (4, "bad secret"));
+
+    /* Syntax */
+    assert_eq!(error("key k {\n  algorithm hmac-sha256\n};"), (3, "expected `;`"));
+    assert_eq!(error("key k {\n  secret \"open;\n};\n"), (2, "unterminated string"));
+    assert_eq!(error("\n/* open"), (2, "unterminated comment"));
+    assert_eq!(error("server 192.0.2.1 { };"), (1, "expected `key`"));
+  }
+
+  #[test]
+  fn malformed_file_adds_nothing() {
+    let mut keyring = DnsQueryTsigKeyring::new();
+    let text = format!("{}key k {{ secret \"\"; }};\n", KEYGEN);
+    assert_eq!(keyring.add_key_file(&text).map_err(|err| err.line), Err(5));
+    assert!(keyring.is_empty());
+    assert_eq!(keyring.add_key_file(KEYGEN), Ok(1));
+  }
+
+  #[test]
+  fn get_and_remove() {
+    let mut keyring = DnsQueryTsigKeyring::new();
+    keyring.insert(DnsQueryTsigKey::new("k.example", DnsQueryTsigAlgorithm::HmacSha256, b"a"));
+    keyring.insert(DnsQueryTsigKey::new("k.example", DnsQueryTsigAlgorithm::HmacSha384, b"b"));
+    /* Replaces the key with the same name and algorithm */
+    keyring.insert(DnsQueryTsigKey::new("K.Example.", DnsQueryTsigAlgorithm::HmacSha256, b"c"));
+    assert_eq!(keyring.len(), 2);
+    assert_eq!(keyring.get_all("k.example").count(), 2);
+
+    let removed = keyring.remove("k.example.", DnsQueryTsigAlgorithm::HmacSha256).unwrap();
+    assert_eq!(removed.name(), "K.Example");
+    assert!(keyring.remove("k.example", DnsQueryTsigAlgorithm::HmacSha256).is_none());
+    assert!(keyring.get("k.example", DnsQueryTsigAlgorithm::HmacSha256).is_none());
+    assert_eq!(keyring.iter().map(DnsQueryTsigKey::algorithm).collect::<Vec<_>>(),
+               [DnsQueryTsigAlgorithm::HmacSha384]);
+  }
+
+  #[test]
+  fn key_for_query() {
+    let keyring = DnsQueryTsigKeyring::from_key_file(KEYGEN).unwrap();
+    let query = DnsRequestQuery::from_url("example.com".into()).to_vec(false).unwrap();
+    let parse = |msg: &[u8]| DnsRespondQuery::try_from(&mut msg.iter()).unwrap();
+
+    let known = keyring.get("tsig-key", DnsQueryTsigAlgorithm::HmacSha256).unwrap();
+    let signed = DnsQueryTsig::new(known).sign_at(&query, 1_000_000).unwrap();
+    assert_eq!(keyring.key_for(&parse(&signed)).map(DnsQueryTsigKey::name), Ok("tsig-key"));
+
+    let unknown = DnsQueryTsigKey::new("other", DnsQueryTsigAlgorithm::HmacSha256, b"secret");
+    let signed = DnsQueryTsig::new(&unknown).sign_at(&query, 1_000_000).unwrap();
+    assert_eq!(keyring.key_for(&parse(&signed)).err(), Some(DnsQueryTsigError::BadKey));
+
+    assert_eq!(keyring.key_for(&parse(&query)).err(), Some(DnsQueryTsigError::NoTsig));
+  }
+}
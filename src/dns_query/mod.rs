@@ -2,4 +2,18 @@ pub mod dns_query_struct;
 pub mod dns_query_0_header;
 pub mod dns_query_1_question;
 pub mod dns_query_2_resource_record;
-pub mod utils;
\ No newline at end of file
+pub mod dns_query_2_resource_record_rdata;
+pub mod dns_query_2_resource_record_svcb;
+pub mod dns_query_2_resource_record_opt;
+pub mod dns_query_2_resource_record_dnssec;
+pub mod dns_query_error;
+pub mod dns_query_builder;
+pub mod dns_query_srv;
+pub mod dns_query_cookie;
+pub mod dns_query_tsig;
+pub mod dns_query_tsig_keyring;
+pub mod dns_query_sig0;
+pub mod dns_query_dnssec;
+pub mod dns_query_dnssec_validator;
+pub mod dns_query_dnssec_denial;
+pub mod utils;
@@ -1,53 +1,202 @@
 #![allow(dead_code)]
 
 use std::slice::Iter;
-use std::mem::transmute;
-use std::option::NoneError;
+use crate::dns_query::dns_query_error::{DnsQueryParseError,
+                                       DnsQueryParseErrorKind::*,
+                                       DnsQueryPresentationError,
+                                       DnsQueryWriteError};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 use std::convert::TryFrom;
 use std::num::TryFromIntError;
+use std::collections::HashMap;
 
+/// Upper bound of compression pointers followed while decoding a single name.
+const MAX_NAME_POINTERS: usize = 64;
+
+/// Maximum length of a domain name in wire format, see RFC 1035 §2.3.4.
+const MAX_NAME_LEN: usize = 255;
+
+/// Maximum length of a label, see RFC 1035 §2.3.4.
+const MAX_LABEL_LEN: usize = 63;
+
+/// Decode a (possibly compressed) domain name starting at `iter`, in
+/// presentation form without the trailing dot.
+///
+/// `msg` must be the whole message that `iter` walks over, so that
+/// compression pointers (RFC 1035 §4.1.4) can be resolved against it.
+/// Every pointer must point strictly before the previous one, which rules out
+/// loops, and at most `MAX_NAME_POINTERS` pointers are followed.
+///
+/// On return, `iter` is advanced past the name as it appears in place, i.e.
+/// past the terminating `\0` or past the first pointer.
 #[inline]
-pub(crate) fn iter_to_str(iter: &mut Iter<u8>, str: &mut String) {
-  while let Some(byte) = iter.next() {
-    /* Stop when reaching '\0' */ {
-      if *byte == 0 {
-        return;
-      }
-    }
+pub(crate) fn iter_to_str(msg: &[u8], iter: &mut Iter<u8>, str: &mut String
+) -> Result<(), DnsQueryParseError> {
+  let start = msg.len() - iter.as_slice().len();
+  let mut cursor = msg[start..].iter();
+  let mut end = None;
+  let mut pointers = 0;
+  let mut limit = msg.len();
+  let mut name_len = 0;
+
+  loop {
+    let byte = iter_to_u8(msg, &mut cursor)?;
+
+    match byte & 0b1100_0000 {
+      /* Normal label */
+      0b0000_0000 => {
+        let len = byte as usize;
+
+        name_len += 1 + len;
+        if name_len > MAX_NAME_LEN {
+          return Err(DnsQueryParseError::at(msg, &cursor, NameTooLong));
+        }
 
-    /* Do real parsing */ {
-      if !str.is_empty() {
-        str.push('.');
+        /* Stop when reaching '\0' */ {
+          if len == 0 {
+            break;
+          }
+        }
+
+        /* Do real parsing */ {
+          let label = iter_to_slice(msg, &mut cursor, len)?;
+          if !str.is_empty() {
+            str.push('.');
+          }
+          str.push_str(&escape_label(label));
+        }
       }
-      for _ in 0..*byte {
-        let u8_arr = &[*iter.next().unwrap()];
 
-        #[cfg(debug_assertions)] {
-          str.push_str(&String::from_utf8_lossy(u8_arr));
+      /* Compression pointer */
+      0b1100_0000 => {
+        let offset = (((byte & 0b0011_1111) as usize) << 8)
+          | iter_to_u8(msg, &mut cursor)? as usize;
+
+        /* Loop detection */ {
+          let pos = msg.len() - cursor.as_slice().len() - 2;
+          limit = limit.min(pos);
+          if offset >= limit {
+            return Err(DnsQueryParseError { offset: pos, kind: PointerLoop });
+          }
+          limit = offset;
+
+          pointers += 1;
+          if pointers > MAX_NAME_POINTERS {
+            return Err(DnsQueryParseError { offset: pos, kind: TooManyPointers });
+          }
         }
-        #[cfg(not(debug_assertions))] {
-          str.push_str(unsafe { from_utf8_unchecked(u8_arr) });
+
+        if end.is_none() {
+          end = Some(msg.len() - cursor.as_slice().len());
         }
+        cursor = msg[offset..].iter();
+      }
+
+      /* 0b01 and 0b10 label types are reserved */
+      _ => {
+        let pos = msg.len() - cursor.as_slice().len() - 1;
+        return Err(DnsQueryParseError { offset: pos, kind: BadLabelLength(byte) });
       }
     }
   }
+
+  /* Skip the name in place */ {
+    let end = end.unwrap_or(msg.len() - cursor.as_slice().len());
+    *iter = iter.as_slice()[end - start..].iter();
+  }
+  Ok(())
+}
+
+/// Escape `label` for the presentation form of a name (RFC 1035 §5.1), so
+/// that dots within it and bytes that are not printable survive a round
+/// trip through `str_to_vec`.
+pub(crate) fn escape_label(label: &[u8]) -> String {
+  escape_char_str(label, b". ;()@$")
+}
+
+/// Labels of `str`, a name in presentation form, unescaped and without the
+/// root label. `None` for a malformed escape sequence.
+pub(crate) fn name_to_labels(str: &str) -> Option<Vec<Vec<u8>>> {
+  /* The root is written as `.` in presentation form */
+  if str.is_empty() || str == "." {
+    return Some(vec![]);
+  }
+
+  let mut labels = split_unescaped(str, b'.');
+  if labels.last() == Some(&"") {
+    labels.pop();
+  }
+  labels.into_iter().map(unescape_char_str).collect()
 }
 
+/// `name` without its trailing dot, unless that dot is escaped.
+pub(crate) fn trim_root(name: &str) -> &str {
+  match name.strip_suffix('.') {
+    Some(rest) if rest.bytes().rev().take_while(|&byte| byte == b'\\').count() % 2 == 0 => rest,
+    _ => name,
+  }
+}
+
+/// Name compression state of a message being serialized.
+///
+/// Maps every name suffix already written, lowercased in wire format, to its
+/// offset in the message, so that later occurrences can be replaced by a
+/// pointer (RFC 1035 §4.1.4). `None` disables compression and keeps names
+/// canonical.
+pub(crate) type NameOffsets = Option<HashMap<Vec<u8>, u16>>;
+
+/// Largest offset a compression pointer is able to address.
+const MAX_POINTER_OFFSET: usize = 0b0011_1111_1111_1111;
+
+/// Encode `str` as a domain name and append it to `vec`.
+///
+/// When `offsets` is enabled, `vec` must hold the message from its first
+/// byte, because offsets of the written names are taken from `vec.len()`.
+/// Nothing is written for a name with a malformed escape sequence, an empty
+/// or overlong label, or longer than 255 bytes.
 #[inline]
-pub(crate) fn str_to_vec(str: &str, vec: &mut Vec<u8>) -> Result<(), TryFromIntError> {
-  for word in str.split_terminator('.') {
-    let len = word.len();
+pub(crate) fn str_to_vec(str: &str, vec: &mut Vec<u8>, offsets: &mut NameOffsets
+) -> Result<(), DnsQueryWriteError> {
+  let words = name_to_labels(str).ok_or(DnsQueryWriteError::BadEscape)?;
+
+  /* Check lengths, see RFC 1035 §2.3.4 */ {
+    if words.iter().any(|word| word.is_empty() || word.len() > MAX_LABEL_LEN) {
+      return Err(DnsQueryWriteError::BadLabelLength);
+    }
+    if words.iter().map(|word| 1 + word.len()).sum::<usize>() + 1 > MAX_NAME_LEN {
+      return Err(DnsQueryWriteError::NameTooLong);
+    }
+  }
+
+  for (i, word) in words.iter().enumerate() {
+    /* handle compression */ {
+      if let Some(offsets) = offsets {
+        /* Length bytes are at most 63, which lowercasing leaves alone */
+        let mut suffix = vec![];
+        for word in &words[i..] {
+          suffix.push(u8::try_from(word.len())?);
+          suffix.extend_from_slice(word);
+        }
+        suffix.make_ascii_lowercase();
+
+        if let Some(offset) = offsets.get(&suffix) {
+          vec.extend_from_slice(&(0b1100_0000_0000_0000 | offset).to_be_bytes());
+          return Ok(());
+        }
+
+        if vec.len() <= MAX_POINTER_OFFSET {
+          offsets.insert(suffix, u16::try_from(vec.len())?);
+        }
+      }
+    }
 
     /* handle len */ {
-      #[allow(clippy::cast_possible_truncation)] let len_u8 = len as u8;
-      debug_assert!(len_u8 == u8::try_from(len)?);
-      vec.push(len_u8);
+      vec.push(u8::try_from(word.len())?);
     }
 
     /* handle bytes */ {
-      for i in 0..len {
-        vec.push(word.as_bytes()[i]);
-      }
+      vec.extend_from_slice(word);
     }
   }
 
@@ -58,21 +207,230 @@ pub(crate) fn str_to_vec(str: &str, vec: &mut Vec<u8>) -> Result<(), TryFromIntE
   Ok(())
 }
 
+/// Encode `str` in the canonical form of RFC 4034 §6.2, i.e. lowercase and
+/// uncompressed, and append it to `vec`.
+pub(crate) fn str_to_canonical_vec(str: &str, vec: &mut Vec<u8>) -> Result<(), DnsQueryWriteError> {
+  let start = vec.len();
+  str_to_vec(str, vec, &mut None)?;
+  vec[start..].make_ascii_lowercase();
+  Ok(())
+}
+
+/// `name` in presentation form with its labels lowercased once unescaped, so
+/// that `\065` is lowercased like `A`. `None` for a malformed escape sequence.
+pub(crate) fn canonical_name(name: &str) -> Option<String> {
+  let labels = name_to_labels(name)?;
+  Some(labels.iter().map(|label| escape_label(&label.to_ascii_lowercase()))
+    .collect::<Vec<_>>().join("."))
+}
+
+/// Take a character-string, i.e. a length byte followed by that many bytes.
+#[inline]
+pub(crate) fn iter_to_char_str<'a>(msg: &[u8], iter: &mut Iter<'a, u8>
+) -> Result<&'a [u8], DnsQueryParseError> {
+  let len = iter_to_u8(msg, iter)?;
+  iter_to_slice(msg, iter, len as usize)
+}
+
+#[inline]
+pub(crate) fn char_str_to_vec(bytes: &[u8], vec: &mut Vec<u8>) -> Result<(), TryFromIntError> {
+  vec.push(u8::try_from(bytes.len())?);
+  vec.extend_from_slice(bytes);
+  Ok(())
+}
+
+/// Escape `bytes` as the content of a character-string in presentation form
+/// (RFC 1035 §5.1), escaping every byte in `special` as well. The result is
+/// meant to be put in quotes.
+pub(crate) fn escape_char_str(bytes: &[u8], special: &[u8]) -> String {
+  let mut str = String::with_capacity(bytes.len());
+  for &byte in bytes {
+    match byte {
+      b'"' | b'\\' => {
+        str.push('\\');
+        str.push(byte as char);
+      }
+      _ if special.contains(&byte) => {
+        str.push('\\');
+        str.push(byte as char);
+      }
+      0x20..=0x7e => str.push(byte as char),
+      _ => str.push_str(&format!("\\{:03}", byte)),
+    }
+  }
+  str
+}
+
+/// Reverse `escape_char_str`, `None` for a malformed escape sequence.
+pub(crate) fn unescape_char_str(str: &str) -> Option<Vec<u8>> {
+  let bytes = str.as_bytes();
+  let mut result = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+
+  while i < bytes.len() {
+    if bytes[i] != b'\\' {
+      result.push(bytes[i]);
+      i += 1;
+      continue;
+    }
+
+    let rest = &bytes[i + 1..];
+    if rest.len() >= 3 && rest[..3].iter().all(u8::is_ascii_digit) {
+      let val = rest[..3].iter().fold(0_u16, |val, digit| val * 10 + u16::from(digit - b'0'));
+      result.push(u8::try_from(val).ok()?);
+      i += 4;
+    } else {
+      result.push(*rest.first()?);
+      i += 2;
+    }
+  }
+  Some(result)
+}
+
+/// Split `str` at every `sep` that is not escaped by a backslash.
+pub(crate) fn split_unescaped(str: &str, sep: u8) -> Vec<&str> {
+  let bytes = str.as_bytes();
+  let mut result = vec![];
+  let mut start = 0;
+  let mut i = 0;
+
+  while i < bytes.len() {
+    if bytes[i] == b'\\' {
+      i += 2;
+      continue;
+    }
+    if bytes[i] == sep {
+      result.push(&str[start..i]);
+      start = i + 1;
+    }
+    i += 1;
+  }
+  result.push(&str[start..]);
+  result
+}
+
+/// Split the presentation form of a record into whitespace separated fields.
+///
+/// Quotes are removed, while escape sequences are left for the caller to
+/// handle. `None` for an unterminated quote.
+pub(crate) fn split_presentation(str: &str) -> Option<Vec<String>> {
+  let mut result = vec![];
+  let mut field = String::new();
+  let mut in_field = false;
+  let mut in_quote = false;
+  let mut chars = str.chars();
+
+  while let Some(char) = chars.next() {
+    match char {
+      '\\' => {
+        field.push(char);
+        field.push(chars.next()?);
+        in_field = true;
+      }
+      '"' => {
+        in_quote = !in_quote;
+        in_field = true;
+      }
+      _ if char.is_whitespace() && !in_quote => {
+        if in_field {
+          result.push(std::mem::take(&mut field));
+          in_field = false;
+        }
+      }
+      _ => {
+        field.push(char);
+        in_field = true;
+      }
+    }
+  }
+
+  if in_quote {
+    return None;
+  }
+  if in_field {
+    result.push(field);
+  }
+  Some(result)
+}
+
+/// Take the next `len` bytes of `iter` as a slice.
 #[inline]
-pub(crate) fn iter_to_u16_be(iter: &mut Iter<u8>
-) -> Result<u16, NoneError> {
-  Ok(u16::from_be_bytes([*iter.next()?, *iter.next()?]))
+pub(crate) fn iter_to_slice<'a>(msg: &[u8], iter: &mut Iter<'a, u8>, len: usize
+) -> Result<&'a [u8], DnsQueryParseError> {
+  let slice = iter.as_slice();
+  if slice.len() < len {
+    return Err(DnsQueryParseError::at(msg, iter, Truncated));
+  }
+  *iter = slice[len..].iter();
+  Ok(&slice[..len])
 }
 
 #[inline]
-pub(crate) fn iter_to_u32_be(iter: &mut Iter<u8>
-) -> Result<u32, NoneError> {
-  Ok(u32::from_be_bytes(
-    [*iter.next()?, *iter.next()?, *iter.next()?, *iter.next()?]))
+pub(crate) fn iter_to_u8(msg: &[u8], iter: &mut Iter<u8>
+) -> Result<u8, DnsQueryParseError> {
+  Ok(iter_to_slice(msg, iter, 1)?[0])
 }
 
-#[derive(Debug, Copy, Clone)]
-pub enum DnsQueryType {
+#[inline]
+pub(crate) fn iter_to_u16_be(msg: &[u8], iter: &mut Iter<u8>
+) -> Result<u16, DnsQueryParseError> {
+  let slice = iter_to_slice(msg, iter, 2)?;
+  Ok(u16::from_be_bytes([slice[0], slice[1]]))
+}
+
+#[inline]
+pub(crate) fn iter_to_u32_be(msg: &[u8], iter: &mut Iter<u8>
+) -> Result<u32, DnsQueryParseError> {
+  let slice = iter_to_slice(msg, iter, 4)?;
+  Ok(u32::from_be_bytes([slice[0], slice[1], slice[2], slice[3]]))
+}
+
+/// Define an enum of protocol code points with lossless conversions.
+///
+/// Values without a variant of their own are kept in an extra `Unknown`
+/// variant, so that decoding and re-encoding any value gives it back exactly.
+macro_rules! dns_code_enum {
+  ($(#[$meta:meta])* $vis:vis enum $name:ident: $repr:ty {
+    $($(#[$v_meta:meta])* $variant:ident = $value:expr,)*
+  }) => {
+    $(#[$meta])*
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    $vis enum $name {
+      $($(#[$v_meta])* $variant,)*
+      /// Any value that has no variant of its own
+      Unknown($repr),
+    }
+
+    impl From<$repr> for $name {
+      fn from(num: $repr) -> Self {
+        match num {
+          $($value => Self::$variant,)*
+          _ => Self::Unknown(num),
+        }
+      }
+    }
+
+    impl From<&$name> for $repr {
+      fn from(enum_: &$name) -> Self {
+        match enum_ {
+          $($name::$variant => $value,)*
+          $name::Unknown(num) => *num,
+        }
+      }
+    }
+
+    impl $name {
+      /// Every variant except `Unknown`
+      #[allow(dead_code)]
+      pub(crate) const KNOWN: &'static [Self] = &[$(Self::$variant,)*];
+    }
+  };
+}
+
+pub(crate) use dns_code_enum;
+
+dns_code_enum! {
+pub enum DnsQueryType: u16 {
   /// a host address
   A = 1,
   /// an authoritative name server
@@ -179,8 +537,8 @@ pub enum DnsQueryType {
   Tlsa = 52,
   /// S/MIME cert association
   SMimeA = 53,
-  /// Unassigned
-  _UnAssign54 = 54,
+  // /// Unassigned
+  // = 54,
   /// Host Identity Protocol
   Hip = 55,
   /// NINFO
@@ -199,9 +557,11 @@ pub enum DnsQueryType {
   CSync = 62,
   /// message digest for DNS zone
   ZoneMd = 63,
-  /// 64-98: Unassigned
-  // use largest possible for correct `std::mem::transmute()` parsing
-  _UnAssign64To98 = 98,
+  /// Service Binding
+  Svcb = 64,
+  /// HTTPS Binding
+  Https = 65,
+  // /// 66-98: Unassigned
   /// Transaction Key record
   TKey = 249,
   /// Transaction Signature
@@ -216,71 +576,143 @@ pub enum DnsQueryType {
   DOa = 259,
   /// Automatic Multicast Tunneling Relay
   AmtRelay = 260,
-  /// Unassigned
-  _UnAssign32767 = 32767,
+  // /// Unassigned
+  // = 32767,
   /// DNSSEC Trust Authorities
   Ta = 32768,
   /// DNSSEC Lookaside Validation record
   Dlv = 32769,
-  /// 32770-65279: Unassigned
-  // use largest possible for correct `std::mem::transmute()` parsing
-  _UnAssign32770To65279 = 65279,
-  /// 65280-65534: Private use
-  // use largest possible for correct `std::mem::transmute()` parsing
-  _PrivUse = 65534,
-  /// Reserved
-  _Resv65535 = 65535,
+  // /// 32770-65279: Unassigned
+  // /// 65280-65534: Private use
+  // /// 65535: Reserved
 }
-
-impl From<u16> for DnsQueryType {
-  fn from(num: u16) -> Self {
-    unsafe { transmute::<u16, Self>(num) }
-  }
 }
 
-impl From<&DnsQueryType> for u16 {
-  fn from(enum_: &DnsQueryType) -> Self {
-    *enum_ as Self
-  }
-}
-
-#[derive(Debug, Copy, Clone)]
-pub enum DnsQueryClass {
-  /// 0: Reserved
-  _Resv0 = 0,
+dns_code_enum! {
+pub enum DnsQueryClass: u16 {
+  // /// 0: Reserved
   /// 1: Internet (IN)
   In = 1,
-  /// 2: Unassigned
-  _UnAssign2 = 2,
+  // /// 2: Unassigned
   /// 3: Chaos (CH)
   Ch = 3,
   /// 4: Hesiod (HS)
   Hs = 4,
-  /// 5-253: Unassigned
-  // use largest possible for correct `std::mem::transmute()` parsing
-  _UnAssign5To253 = 253,
+  // /// 5-253: Unassigned
   /// 254: QCLASS NONE
   QClsNone = 254,
   /// 255: QCLASS * (ANY)
   QClsAny = 255,
-  /// 256-65279: Unassigned
-  // use largest possible for correct `std::mem::transmute()` parsing
-  _UnAssign256To65279 = 65279,
-  /// 65280-65534: Reserved for Private Use
-  // use largest possible for correct `std::mem::transmute()` parsing
-  _Priv65280To65534 = 65534,
-  /// 65535: Reserved
-  _Resv65535 = 65535,
+  // /// 256-65279: Unassigned
+  // /// 65280-65534: Reserved for Private Use
+  // /// 65535: Reserved
+}
 }
 
-impl From<u16> for DnsQueryClass {
-  fn from(num: u16) -> Self {
-    unsafe { transmute::<u16, Self>(num) }
+/// Mnemonic of the type, or `TYPE<num>` for unknown ones (RFC 3597 §5).
+impl Display for DnsQueryType {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Self::NsapPtr => write!(f, "NSAP-PTR"),
+      Self::Unknown(num) => write!(f, "TYPE{}", num),
+      _ => write!(f, "{}", format!("{:?}", self).to_ascii_uppercase()),
+    }
   }
 }
 
-impl From<&DnsQueryClass> for u16 {
-  fn from(class: &DnsQueryClass) -> Self {
-    *class as Self
+impl FromStr for DnsQueryType {
+  type Err = DnsQueryPresentationError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let upper = s.to_ascii_uppercase();
+
+    if let Some(num) = upper.strip_prefix("TYPE") {
+      if let Ok(num) = num.parse::<u16>() {
+        return Ok(num.into());
+      }
+    }
+
+    Self::KNOWN.iter()
+      .find(|type_| type_.to_string() == upper)
+      .copied()
+      .ok_or(DnsQueryPresentationError { reason: "unknown type" })
   }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn name_to_vec(name: &str) -> Result<Vec<u8>, DnsQueryWriteError> {
+    let mut vec = vec![];
+    str_to_vec(name, &mut vec, &mut None).map(|_| vec)
+  }
+
+  #[test]
+  fn name_round_trip() {
+    for name in &["", "example.com", &["a"; 127].join(".")] {
+      let vec = name_to_vec(name).unwrap();
+      let mut str = String::new();
+      iter_to_str(&vec, &mut vec.iter(), &mut str).unwrap();
+      assert_eq!(&str, name);
+    }
+    assert_eq!(name_to_vec(".").unwrap(), [0]);
+    assert_eq!(name_to_vec("com.").unwrap(), b"\x03com\x00");
+  }
+
+  #[test]
+  fn name_escapes_round_trip() {
+    /* The single label `a.b` and a label with byte 0xff */
+    let wire = b"\x03a.b\x03\xffA\\\x07example\x00";
+    let mut str = String::new();
+    iter_to_str(wire, &mut wire.iter(), &mut str).unwrap();
+    assert_eq!(str, "a\\.b.\\255A\\\\.example");
+    assert_eq!(name_to_vec(&str).unwrap(), wire);
+
+    assert_eq!(name_to_vec("a\\.b.").unwrap(), b"\x03a.b\x00");
+    assert_eq!(name_to_vec("a\\256"), Err(DnsQueryWriteError::BadEscape));
+    assert_eq!(name_to_vec("a\\"), Err(DnsQueryWriteError::BadEscape));
+    assert_eq!(trim_root("a\\."), "a\\.");
+    assert_eq!(trim_root("a\\\\."), "a\\\\");
+  }
+
+  #[test]
+  fn name_escapes_canonical_and_compressed() {
+    let mut vec = vec![];
+    str_to_canonical_vec("\\065B.Com", &mut vec).unwrap();
+    assert_eq!(vec, b"\x02ab\x03com\x00");
+
+    /* `a\.b` and `a.b` are different names, and must not share a pointer */
+    let mut vec = vec![];
+    let mut offsets = Some(HashMap::new());
+    str_to_vec("a\\.b", &mut vec, &mut offsets).unwrap();
+    str_to_vec("a.b", &mut vec, &mut offsets).unwrap();
+    str_to_vec("X.A.B", &mut vec, &mut offsets).unwrap();
+    assert_eq!(vec, b"\x03a.b\x00\x01a\x01b\x00\x01X\xc0\x05");
+  }
+
+  #[test]
+  fn name_bad_label_length() {
+    assert_eq!(name_to_vec(&"a".repeat(64)), Err(DnsQueryWriteError::BadLabelLength));
+    assert_eq!(name_to_vec(&format!("{}.com", "a".repeat(70))),
+               Err(DnsQueryWriteError::BadLabelLength));
+    assert_eq!(name_to_vec("a..com"), Err(DnsQueryWriteError::BadLabelLength));
+    assert_eq!(name_to_vec(".com"), Err(DnsQueryWriteError::BadLabelLength));
+    assert!(name_to_vec(&"a".repeat(63)).is_ok());
+  }
+
+  #[test]
+  fn name_too_long() {
+    /* 4 labels of 63 bytes take 256 bytes with the root */
+    let label = "a".repeat(63);
+    assert_eq!(name_to_vec(&[label.as_str(); 4].join(".")), Err(DnsQueryWriteError::NameTooLong));
+    assert_eq!(name_to_vec(&[&label[..61], &label, &label, &label].join(".")).unwrap().len(), 255);
+  }
+
+  #[test]
+  fn name_error_writes_nothing() {
+    let mut vec = vec![1, 2];
+    assert!(str_to_vec("a..com", &mut vec, &mut Some(HashMap::new())).is_err());
+    assert_eq!(vec, [1, 2]);
+  }
+}
@@ -90,7 +90,7 @@ pub(crate) fn parse_hosts() -> Result<HostEntryVec, Error> {
     }
 
     host_entries.push(HostEntry {
-      ip: IpAddr::from_str(&ip.to_owned()).unwrap(),
+      ip: IpAddr::from_str(ip).unwrap(),
       host: host.trim().to_owned(),
       desc_no_pound_sign: desc.trim().to_owned(),
     })
@@ -101,8 +101,8 @@ pub(crate) fn parse_hosts() -> Result<HostEntryVec, Error> {
 fn hosts_path() -> &'static Path {
   static mut HOSTS_PATH: Option<&Path> = None;
 
-  if unsafe { HOSTS_PATH } == None {
-    let mut path;
+  if unsafe { HOSTS_PATH }.is_none() {
+    #[allow(unused_mut)] let mut path;
 
     #[cfg(windows)] {
       path = Path::new("C:/Windows/System32/drivers/etc/hosts");
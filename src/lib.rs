@@ -1,5 +1,3 @@
-#![feature(try_trait)]
-
 pub mod host_entry;
 pub mod tcping;
 pub mod dns_query;
\ No newline at end of file